@@ -0,0 +1,126 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use very_large_collections::{
+    bitset::SparseBitset,
+    block::{AlignedBitArray, AlignedVec, BlockCollection, BlockStore},
+    merge::Intersection,
+};
+
+const WORDS: usize = 1024;
+
+/// Deterministic pseudo-random words, so that every run works on the same input.
+fn words(seed: u64) -> [u64; WORDS] {
+    let mut x = seed;
+    std::array::from_fn(|_| {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x
+    })
+}
+
+fn bench_bit_array(c: &mut Criterion) {
+    let a = AlignedBitArray::new_from(0, words(0x9E37_79B9_7F4A_7C15));
+    let b = AlignedBitArray::new_from(0, words(0x2545_F491_4F6C_DD1D));
+
+    let mut group = c.benchmark_group("bit_array");
+    // Xor in place, so that only the operation itself is measured.
+    let mut x = a;
+    group.bench_function("xor/scalar", |bench| {
+        bench.iter(|| {
+            for (x, y) in x.words_mut().iter_mut().zip(b.words().iter()) {
+                *x ^= *y;
+            }
+            black_box(&x);
+        })
+    });
+    group.bench_function("xor/simd", |bench| {
+        bench.iter(|| {
+            x.xor(&b);
+            black_box(&x);
+        })
+    });
+    group.bench_function("count_ones/scalar", |bench| {
+        bench.iter(|| {
+            black_box(&a)
+                .words()
+                .iter()
+                .map(|w| w.count_ones() as usize)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("count_ones/simd", |bench| {
+        bench.iter(|| black_box(&a).count_ones())
+    });
+    group.finish();
+}
+
+/// About four bits in each of 1024 blocks, so that nearly every block of one set has a match in the other.
+fn sparse_bitset(seed: u64) -> SparseBitset<u64> {
+    let mut bs = SparseBitset::default();
+    for w in words(seed) {
+        for k in 0..4 {
+            bs.store((w >> (16 * k)) & 0xffff, true);
+        }
+    }
+    bs
+}
+
+fn bench_sparse_bitset(c: &mut Criterion) {
+    let a = sparse_bitset(0x9E37_79B9_7F4A_7C15);
+    let b = sparse_bitset(0x2545_F491_4F6C_DD1D);
+
+    let mut group = c.benchmark_group("sparse_bitset");
+    group.bench_function("and/iter", |bench| {
+        bench.iter(|| Intersection::new(black_box(&a).iter(), b.iter()).count())
+    });
+    group.bench_function("and/simd", |bench| {
+        bench.iter_batched(
+            || SparseBitset::new_from(a.blocks().to_vec()),
+            |mut x| {
+                x.and(&b);
+                x
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("count_ones/scalar", |bench| {
+        bench.iter(|| {
+            black_box(&a)
+                .blocks()
+                .iter()
+                .map(|b| b.bits().count_ones() as usize)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("count_ones/simd", |bench| {
+        bench.iter(|| black_box(&a).count_ones())
+    });
+    group.finish();
+}
+
+fn bench_reductions(c: &mut Criterion) {
+    let v: AlignedVec<u32, 4096> = AlignedVec::new_from(
+        0,
+        words(7)
+            .iter()
+            .flat_map(|w| [*w as u32, (*w >> 32) as u32])
+            .cycle()
+            .take(4096)
+            .collect(),
+    );
+
+    let mut group = c.benchmark_group("aligned_vec");
+    group.bench_function("max/scalar", |bench| {
+        bench.iter(|| black_box(&v).iter().copied().max())
+    });
+    group.bench_function("max/simd", |bench| bench.iter(|| black_box(&v).max()));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_bit_array,
+    bench_sparse_bitset,
+    bench_reductions
+);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use very_large_collections::{
+    block::{AlignedVec, DenseVec},
+    sort::{Pair, RadixSortable, SortedPair},
+};
+
+const BLOCK: usize = 4096;
+
+/// Deterministic pseudo-random keys, so that every run sorts the same input.
+fn keys(n: usize) -> Vec<u64> {
+    let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+    (0..n)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        })
+        .collect()
+}
+
+fn dense_vec(keys: &[u64]) -> DenseVec<AlignedVec<u64, BLOCK>> {
+    DenseVec::new_from(
+        keys.chunks_exact(BLOCK)
+            .enumerate()
+            .map(|(i, chunk)| AlignedVec::new_from(i * BLOCK, chunk.to_vec()))
+            .collect(),
+    )
+}
+
+fn bench_pair(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pair");
+    for n in [BLOCK, 16 * BLOCK] {
+        let input = keys(n);
+
+        group.bench_with_input(BenchmarkId::new("comparison", n), &input, |bench, input| {
+            bench.iter_batched(
+                || (input[..n / 2].to_vec(), input[n / 2..].to_vec()),
+                |(mut a, mut b)| {
+                    let mut pair = Pair::new(&mut a, &mut b);
+                    let mut sorter = SortedPair::<_, 16>::new(&mut pair);
+                    sorter.sort_by_key(|x| *x);
+                    sorter[0]
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("radix", n), &input, |bench, input| {
+            bench.iter_batched(
+                || (input[..n / 2].to_vec(), input[n / 2..].to_vec()),
+                |(mut a, mut b)| {
+                    let mut pair = Pair::new(&mut a, &mut b);
+                    pair.radix_sort();
+                    (a, b)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_dense_vec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dense_vec");
+    for n in [16 * BLOCK, 256 * BLOCK] {
+        let input = keys(n);
+
+        group.bench_with_input(BenchmarkId::new("comparison", n), &input, |bench, input| {
+            bench.iter_batched(
+                || dense_vec(input),
+                |v| {
+                    let mut flat: Vec<u64> = v.iter().collect();
+                    flat.sort_unstable();
+                    dense_vec(&flat)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("radix", n), &input, |bench, input| {
+            bench.iter_batched(
+                || dense_vec(input),
+                |mut v| {
+                    v.radix_sort();
+                    v
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pair, bench_dense_vec);
+criterion_main!(benches);
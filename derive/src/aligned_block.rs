@@ -0,0 +1,227 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Expr, Field, Member, Result, Type};
+
+/// How an `#[elements]` field can be built from an iterator, if at all.
+enum Elements {
+    Array,
+    Vec,
+    Other,
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "AlignedBlock can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut alignment: Option<Expr> = None;
+    let mut item: Option<Type> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("aligned_block"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("alignment") {
+                alignment = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("item") {
+                item = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `alignment` or `item`"))
+            }
+        })?;
+    }
+    let alignment = alignment.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "AlignedBlock needs an alignment, given by #[aligned_block(alignment = ...)]",
+        )
+    })?;
+
+    let marked = |name: &str| -> Result<Option<(Member, &Field)>> {
+        let mut found = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.attrs.iter().any(|a| a.path().is_ident(name)))
+            .map(|(i, f)| (member(i, f), f));
+        let first = found.next();
+        if let Some((_, extra)) = found.next() {
+            return Err(Error::new_spanned(
+                extra,
+                format!("only one field can be marked #[{name}]"),
+            ));
+        }
+        Ok(first)
+    };
+    let (position, position_field) = marked("position")?.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "AlignedBlock needs a field marked #[position]",
+        )
+    })?;
+    let elements = marked("elements")?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let index = &position_field.ty;
+    let item = match (&item, &elements) {
+        (Some(item), _) => quote!(#item),
+        (None, Some((_, field))) => {
+            let ty = &field.ty;
+            quote!(<#ty as ::core::ops::Index<usize>>::Output)
+        }
+        (None, None) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "AlignedBlock needs a field marked #[elements], or an item type given by #[aligned_block(item = ...)]",
+            ))
+        }
+    };
+
+    let krate = quote!(::very_large_collections);
+    let mut tokens = quote! {
+        impl #impl_generics #krate::block::IndexedBlock for #name #ty_generics #where_clause {
+            type Index = #index;
+            type Item = #item;
+        }
+
+        impl #impl_generics #krate::block::AlignedBlock for #name #ty_generics #where_clause {
+            fn alignment() -> Self::Index {
+                #alignment
+            }
+
+            fn position(&self) -> Self::Index {
+                self.#position
+            }
+        }
+    };
+
+    let Some((elements, elements_field)) = elements else {
+        return Ok(tokens);
+    };
+    let kind = elements_kind(&elements_field.ty);
+    let numerical_index = quote!(<Self::Index as #krate::numerical_index::NumericalIndex>);
+    let in_block = quote! {
+        self.#position <= index
+            && index <= #numerical_index::last_in_block(self.#position, Self::alignment())
+    };
+    let offset = quote!(#numerical_index::offset_from(index, self.#position));
+    // Arrays and Vecs can look up an element without panicking, but other kinds of field may only be indexable.
+    let (lookup, lookup_mut) = match kind {
+        Elements::Array | Elements::Vec => (
+            quote!(self.#elements.get(#offset)),
+            quote!(self.#elements.get_mut(#offset)),
+        ),
+        Elements::Other => (
+            quote!(::core::option::Option::Some(&self.#elements[#offset])),
+            quote!(::core::option::Option::Some(&mut self.#elements[#offset])),
+        ),
+    };
+    tokens.extend(quote! {
+        impl #impl_generics #krate::block::BlockFetch for #name #ty_generics #where_clause {
+            fn fetch(&self, index: Self::Index) -> Self::Item {
+                ::core::assert!(#in_block, "index out of bounds");
+                ::core::clone::Clone::clone(&self.#elements[#offset])
+            }
+        }
+
+        impl #impl_generics #krate::block::BlockStore for #name #ty_generics #where_clause {
+            fn store(&mut self, index: Self::Index, item: Self::Item) {
+                ::core::assert!(#in_block, "index out of bounds");
+                self.#elements[#offset] = item;
+            }
+        }
+
+        impl #impl_generics #krate::block::BlockGet for #name #ty_generics #where_clause {
+            fn get(&self, index: Self::Index) -> ::core::option::Option<&Self::Item> {
+                if !(#in_block) {
+                    return ::core::option::Option::None;
+                }
+                #lookup
+            }
+        }
+
+        impl #impl_generics #krate::block::BlockGetMut for #name #ty_generics #where_clause {
+            fn get_mut(&mut self, index: Self::Index) -> ::core::option::Option<&mut Self::Item> {
+                if !(#in_block) {
+                    return ::core::option::Option::None;
+                }
+                #lookup_mut
+            }
+        }
+    });
+
+    let expect = quote! {
+        iter.next().expect("iterator should provide at least as many elements as the alignment of the block")
+    };
+    let build = match (kind, &elements_field.ty) {
+        // The alignment is only known at run time, so check the length of the array whenever a block is built.
+        (Elements::Array, Type::Array(array)) => {
+            let len = &array.len;
+            quote! {{
+                let min = #numerical_index::MIN;
+                let alignment = #numerical_index::offset_from(
+                    #numerical_index::last_in_block(min, Self::alignment()),
+                    min,
+                ) + 1;
+                ::core::assert_eq!(
+                    alignment, #len,
+                    "the #[elements] array should be exactly as long as the alignment of the block"
+                );
+                ::core::array::from_fn(|_| #expect)
+            }}
+        }
+        (Elements::Vec, _) => quote! {
+            <Self::Index as #krate::numerical_index::NumericalIndex>::range(position, Self::alignment())
+                .map(|_| #expect)
+                .collect()
+        },
+        _ => return Ok(tokens),
+    };
+    let defaults = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| member(i, f))
+        .filter(|m| *m != position && *m != elements)
+        .map(|m| quote!(#m: ::core::default::Default::default()));
+    tokens.extend(quote! {
+        impl #impl_generics #krate::block::AlignedBlockFromIterator for #name #ty_generics #where_clause {
+            fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+            where
+                I: ::core::iter::Iterator<Item = Self::Item>,
+            {
+                Self {
+                    #position: position,
+                    #elements: #build,
+                    #(#defaults,)*
+                }
+            }
+        }
+    });
+    Ok(tokens)
+}
+
+fn member(i: usize, field: &Field) -> Member {
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(i.into()),
+    }
+}
+
+fn elements_kind(ty: &Type) -> Elements {
+    match ty {
+        Type::Array(_) => Elements::Array,
+        Type::Path(path) if path.path.segments.last().is_some_and(|s| s.ident == "Vec") => {
+            Elements::Vec
+        }
+        _ => Elements::Other,
+    }
+}
@@ -0,0 +1,43 @@
+#![deny(unused)]
+#![deny(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(clippy::correctness)]
+#![deny(clippy::perf)]
+#![allow(clippy::style)]
+#![allow(clippy::complexity)]
+
+//! Derive macros for very large collections.
+//! These are re-exported by `very_large_collections`, next to the traits that they implement.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod aligned_block;
+mod numerical_index;
+
+/// Implement `NumericalIndex` for a struct with a single field, such as `struct RowId(u64)`,
+/// by delegating to the field. The struct must also implement `Copy`, `Eq` and `Ord`.
+#[proc_macro_derive(NumericalIndex)]
+pub fn derive_numerical_index(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    numerical_index::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `IndexedBlock` and `AlignedBlock` for a struct.
+///
+/// The struct needs a field marked `#[position]`, which holds the position of the block and decides the index type,
+/// and an alignment given by `#[aligned_block(alignment = ...)]`.
+/// The elements can be kept in a field marked `#[elements]` that can be indexed by `usize`, such as an array or a `Vec`.
+/// Then `BlockFetch` (which clones the element), `BlockStore`, `BlockGet` and `BlockGetMut` are implemented as well,
+/// and if the field is an array or a `Vec`, so is `AlignedBlockFromIterator`, with every other field set to its default.
+/// An array must be exactly as long as the alignment, which `AlignedBlockFromIterator` checks when it builds a block.
+/// Without an `#[elements]` field, the item type must be given by `#[aligned_block(item = ...)]`.
+#[proc_macro_derive(AlignedBlock, attributes(aligned_block, position, elements))]
+pub fn derive_aligned_block(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    aligned_block::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
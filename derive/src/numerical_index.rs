@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Member, Result};
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "NumericalIndex can only be derived for structs",
+            ))
+        }
+    };
+    let field = match fields {
+        Fields::Named(_) | Fields::Unnamed(_) if fields.len() == 1 => fields
+            .iter()
+            .next()
+            .expect("a struct with one field should have a first field"),
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "NumericalIndex can only be derived for structs with exactly one field",
+            ))
+        }
+    };
+
+    let member = match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(0.into()),
+    };
+    let inner = &field.ty;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let index = quote!(<#inner as ::very_large_collections::numerical_index::NumericalIndex>);
+
+    Ok(quote! {
+        impl #impl_generics ::very_large_collections::numerical_index::NumericalIndex for #name #ty_generics #where_clause {
+            const ONE: Self = Self { #member: #index::ONE };
+            const MIN: Self = Self { #member: #index::MIN };
+            const MAX: Self = Self { #member: #index::MAX };
+
+            fn is_min(self) -> bool {
+                #index::is_min(self.#member)
+            }
+
+            fn modulo(self, divisor: Self) -> Self {
+                Self { #member: #index::modulo(self.#member, divisor.#member) }
+            }
+
+            fn divide(self, divisor: Self) -> Self {
+                Self { #member: #index::divide(self.#member, divisor.#member) }
+            }
+
+            fn block(self, alignment: Self) -> Self {
+                Self { #member: #index::block(self.#member, alignment.#member) }
+            }
+
+            fn last_in_block(self, alignment: Self) -> Self {
+                Self { #member: #index::last_in_block(self.#member, alignment.#member) }
+            }
+
+            fn checked_add(self, other: Self) -> ::core::option::Option<Self> {
+                #index::checked_add(self.#member, other.#member).map(|i| Self { #member: i })
+            }
+
+            fn checked_next(self) -> ::core::option::Option<Self> {
+                #index::checked_next(self.#member).map(|i| Self { #member: i })
+            }
+
+            fn checked_prev(self) -> ::core::option::Option<Self> {
+                #index::checked_prev(self.#member).map(|i| Self { #member: i })
+            }
+
+            fn range(self, alignment: Self) -> impl ::core::iter::Iterator<Item = Self> {
+                #index::range(self.#member, alignment.#member).map(|i| Self { #member: i })
+            }
+
+            fn offset_from(self, origin: Self) -> usize {
+                #index::offset_from(self.#member, origin.#member)
+            }
+        }
+    })
+}
@@ -1,3 +1,4 @@
+pub mod persist;
 mod sparse_bitset;
 
 pub use sparse_bitset::*;
@@ -0,0 +1,522 @@
+//! A binary format for `SparseBitset<u64>`.
+//!
+//! All integers are little-endian `u64`s. A file is laid out as:
+//!
+//! | Section | Contents |
+//! |---------|----------|
+//! | magic   | the 8 bytes `VLCBITS1` |
+//! | flags   | bit 0 is set if the rank directory is present |
+//! | blocks  | for each block with at least one set bit, in increasing order by position: the position (a multiple of 64), then the 64 bits of the block, where bit `i` is the element at `position + i` |
+//! | rank    | if present, for every `RANK_INTERVAL`th block: the number of set bits in all blocks before it |
+//! | footer  | the number of blocks, the number of set bits, then an FNV-1a checksum of every preceding byte |
+//!
+//! Everything but the footer can be written without knowing how many blocks there will be, so the format can be streamed.
+
+use std::io::{self, Read, Write};
+
+use crate::block::{AlignedBitfield, AlignedBlock, BlockCollection, BlockFetch, IndexedBlock};
+
+use super::SparseBitset;
+
+const MAGIC: &[u8; 8] = b"VLCBITS1";
+const FLAG_RANK: u64 = 1;
+const HEADER_BYTES: usize = 16;
+const BLOCK_BYTES: usize = 16;
+const FOOTER_BYTES: usize = 24;
+
+/// The rank directory has one entry per this many blocks.
+pub const RANK_INTERVAL: usize = 64;
+
+/// 64-bit FNV-1a.
+#[derive(Clone, Copy)]
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Self {
+        Checksum(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Writes a bitset in the binary format, one set bit or one block at a time, in increasing order.
+pub struct SparseBitsetWriter<W: Write> {
+    w: W,
+    checksum: Checksum,
+    rank: Option<Vec<u64>>,
+    /// The block being filled in, if any.
+    current: Option<(u64, u64)>,
+    block_count: u64,
+    ones: u64,
+}
+
+impl<W: Write> SparseBitsetWriter<W> {
+    /// Start writing, optionally with a rank directory.
+    pub fn new(w: W, with_rank: bool) -> io::Result<Self> {
+        let mut result = SparseBitsetWriter {
+            w,
+            checksum: Checksum::new(),
+            rank: with_rank.then(Vec::new),
+            current: None,
+            block_count: 0,
+            ones: 0,
+        };
+        result.write_bytes(MAGIC)?;
+        result.write_u64(if with_rank { FLAG_RANK } else { 0 })?;
+        Ok(result)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.checksum.update(bytes);
+        self.w.write_all(bytes)
+    }
+
+    fn write_u64(&mut self, x: u64) -> io::Result<()> {
+        self.write_bytes(&x.to_le_bytes())
+    }
+
+    /// Set a bit. Bits must be set in strictly increasing order.
+    pub fn push(&mut self, index: u64) -> io::Result<()> {
+        let position = index - index % 64;
+        match &mut self.current {
+            Some((p, bits)) if *p == position => {
+                assert!(
+                    *bits >> (index % 64) <= 1,
+                    "bits must be set in increasing order"
+                );
+                *bits |= 1 << (index % 64);
+                Ok(())
+            }
+            _ => self.push_block(position, 1 << (index % 64)),
+        }
+    }
+
+    /// Write an entire block. Blocks must be written in strictly increasing order by position.
+    pub fn push_block(&mut self, position: u64, bits: u64) -> io::Result<()> {
+        assert!(position % 64 == 0, "blocks must be aligned");
+        if let Some((p, _)) = self.current {
+            assert!(position > p, "blocks must be written in increasing order");
+        }
+        self.flush_block()?;
+        self.current = Some((position, bits));
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some((_, 0)) | None => Ok(()),
+            Some((position, bits)) => {
+                if let Some(rank) = &mut self.rank {
+                    if self.block_count as usize % RANK_INTERVAL == 0 {
+                        rank.push(self.ones);
+                    }
+                }
+                self.write_u64(position)?;
+                self.write_u64(bits)?;
+                self.block_count += 1;
+                self.ones += bits.count_ones() as u64;
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the rank directory and footer, and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        for r in self.rank.take().unwrap_or_default() {
+            self.write_u64(r)?;
+        }
+        self.write_u64(self.block_count)?;
+        self.write_u64(self.ones)?;
+        let checksum = self.checksum.0;
+        self.w.write_all(&checksum.to_le_bytes())?;
+        Ok(self.w)
+    }
+}
+
+impl SparseBitset<u64> {
+    /// Write this bitset in the binary format.
+    pub fn write_to<W: Write>(&self, w: W, with_rank: bool) -> io::Result<W> {
+        let mut writer = SparseBitsetWriter::new(w, with_rank)?;
+        for block in self.blocks() {
+            writer.push_block(block.position(), block.bits())?;
+        }
+        writer.finish()
+    }
+
+    /// Read a bitset in the binary format.
+    pub fn read_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let mut bytes = vec![];
+        r.read_to_end(&mut bytes)?;
+        Ok(SparseBitsetView::new(&bytes)?.to_sparse_bitset())
+    }
+}
+
+/// A bitset in the binary format, queried directly from its bytes without loading it.
+#[derive(Clone, Copy)]
+pub struct SparseBitsetView<'a> {
+    blocks: &'a [u8],
+    rank: Option<&'a [u8]>,
+    ones: u64,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+impl<'a> SparseBitsetView<'a> {
+    /// Check the structure and checksum of the bytes, without copying them.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_BYTES + FOOTER_BYTES || &bytes[0..8] != MAGIC {
+            return Err(invalid_data("not a sparse bitset"));
+        }
+        let footer = bytes.len() - FOOTER_BYTES;
+        let mut checksum = Checksum::new();
+        checksum.update(&bytes[..footer + 16]);
+        if checksum.0 != read_u64(bytes, footer + 16) {
+            return Err(invalid_data("checksum does not match"));
+        }
+
+        let flags = read_u64(bytes, 8);
+        let block_count = usize::try_from(read_u64(bytes, footer))
+            .map_err(|_| invalid_data("sections do not match the block count"))?;
+        let ones = read_u64(bytes, footer + 8);
+        let rank_entries = if flags & FLAG_RANK != 0 {
+            block_count.div_ceil(RANK_INTERVAL)
+        } else {
+            0
+        };
+        // The block count is not trusted, so the section sizes must not overflow.
+        let blocks_end = block_count
+            .checked_mul(BLOCK_BYTES)
+            .and_then(|block_bytes| block_bytes.checked_add(HEADER_BYTES))
+            .filter(|blocks_end| blocks_end.checked_add(8 * rank_entries) == Some(footer))
+            .ok_or_else(|| invalid_data("sections do not match the block count"))?;
+
+        let result = SparseBitsetView {
+            blocks: &bytes[HEADER_BYTES..blocks_end],
+            rank: (flags & FLAG_RANK != 0).then(|| &bytes[blocks_end..footer]),
+            ones,
+        };
+        let well_formed = (0..block_count).all(|k| {
+            let (position, _) = result.block(k);
+            position % 64 == 0 && (k == 0 || result.block(k - 1).0 < position)
+        });
+        if !well_formed {
+            return Err(invalid_data(
+                "blocks are not aligned and in increasing order",
+            ));
+        }
+        Ok(result)
+    }
+
+    /// The number of stored blocks.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len() / BLOCK_BYTES
+    }
+
+    /// Position and bits of the `k`th stored block.
+    pub fn block(&self, k: usize) -> (u64, u64) {
+        (
+            read_u64(self.blocks, k * BLOCK_BYTES),
+            read_u64(self.blocks, k * BLOCK_BYTES + 8),
+        )
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> u64 {
+        self.ones
+    }
+
+    /// Which stored block (by `k`) has the given position, or where it would be inserted.
+    fn search(&self, position: u64) -> Result<usize, usize> {
+        let (mut lo, mut hi) = (0, self.block_count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.block(mid).0.cmp(&position) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(lo)
+    }
+
+    /// The number of set bits at indices less than `index`.
+    /// With a rank directory, this reads at most `RANK_INTERVAL` blocks. Without one, it reads every block before `index`.
+    pub fn rank(&self, index: u64) -> u64 {
+        let k = match self.search(index - index % 64) {
+            Ok(k) | Err(k) => k,
+        };
+        let (start, mut ones) = match self.rank {
+            Some(rank) if !rank.is_empty() => {
+                let r = (k / RANK_INTERVAL).min(rank.len() / 8 - 1);
+                (r * RANK_INTERVAL, read_u64(rank, 8 * r))
+            }
+            _ => (0, 0),
+        };
+        for j in start..k {
+            ones += self.block(j).1.count_ones() as u64;
+        }
+        if let Ok(k) = self.search(index - index % 64) {
+            let mask = (1_u64 << (index % 64)) - 1;
+            ones += (self.block(k).1 & mask).count_ones() as u64;
+        }
+        ones
+    }
+
+    /// Iterate over the indices of every set bit, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + 'a {
+        let view = *self;
+        (0..self.block_count()).flat_map(move |k| {
+            let (position, bits) = view.block(k);
+            (0..64)
+                .filter(move |i| bits >> i & 1 != 0)
+                .map(move |i| position + i)
+        })
+    }
+
+    /// Load every block into memory.
+    pub fn to_sparse_bitset(&self) -> SparseBitset<u64> {
+        SparseBitset::new_from(
+            (0..self.block_count())
+                .map(|k| {
+                    let (position, bits) = self.block(k);
+                    AlignedBitfield::new_from(position, bits)
+                })
+                .collect(),
+        )
+    }
+
+    /// Write the set bits in the portable serialization format of Roaring bitmaps, without run containers.
+    /// Every set bit must be less than 2^32.
+    pub fn write_roaring<W: Write>(&self, mut w: W) -> io::Result<W> {
+        const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+        const ARRAY_LIMIT: usize = 4096;
+
+        let mut containers: Vec<(u16, Vec<u16>)> = vec![];
+        for i in self.iter() {
+            let i = u32::try_from(i)
+                .map_err(|_| invalid_data("Roaring bitmaps only hold 32-bit values"))?;
+            let key = (i >> 16) as u16;
+            match containers.last_mut() {
+                Some((k, values)) if *k == key => values.push(i as u16),
+                _ => containers.push((key, vec![i as u16])),
+            }
+        }
+
+        w.write_all(&SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes())?;
+        w.write_all(&(containers.len() as u32).to_le_bytes())?;
+        for (key, values) in containers.iter() {
+            w.write_all(&key.to_le_bytes())?;
+            w.write_all(&((values.len() - 1) as u16).to_le_bytes())?;
+        }
+
+        let container_bytes = |values: &Vec<u16>| {
+            if values.len() <= ARRAY_LIMIT {
+                2 * values.len()
+            } else {
+                8192
+            }
+        };
+        let mut offset = 8 + 8 * containers.len();
+        for (_, values) in containers.iter() {
+            w.write_all(&(offset as u32).to_le_bytes())?;
+            offset += container_bytes(values);
+        }
+
+        for (_, values) in containers.iter() {
+            if values.len() <= ARRAY_LIMIT {
+                for v in values {
+                    w.write_all(&v.to_le_bytes())?;
+                }
+            } else {
+                let mut words = [0_u64; 1024];
+                for v in values {
+                    words[*v as usize / 64] |= 1 << (v % 64);
+                }
+                for word in words {
+                    w.write_all(&word.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(w)
+    }
+}
+
+impl<'a> IndexedBlock for SparseBitsetView<'a> {
+    type Index = u64;
+    type Item = bool;
+}
+
+impl<'a> BlockFetch for SparseBitsetView<'a> {
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        match self.search(index - index % 64) {
+            Ok(k) => self.block(k).1 >> (index % 64) & 1 != 0,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Checksum, SparseBitsetView, SparseBitsetWriter, BLOCK_BYTES, FOOTER_BYTES, RANK_INTERVAL,
+    };
+    use crate::{
+        bitset::SparseBitset,
+        block::{BlockFetch, BlockStore},
+    };
+    use proptest::prelude::*;
+
+    fn bitset(indices: &[u64]) -> SparseBitset<u64> {
+        let mut bs = SparseBitset::default();
+        for i in indices {
+            bs.store(*i, true);
+        }
+        bs
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bs = bitset(&[3, 63, 64, 1000, 1 << 40]);
+        let bytes = bs.write_to(vec![], true).unwrap();
+
+        let view = SparseBitsetView::new(&bytes).unwrap();
+        assert_eq!(view.count_ones(), 5);
+        assert_eq!(view.block_count(), 4);
+        assert!(view.fetch(63));
+        assert!(!view.fetch(65));
+        assert!(view.fetch(1 << 40));
+        assert_eq!(
+            view.iter().collect::<Vec<_>>(),
+            vec![3, 63, 64, 1000, 1 << 40]
+        );
+        assert_eq!(view.rank(64), 2);
+        assert_eq!(view.rank(65), 3);
+        assert_eq!(view.rank(u64::MAX), 5);
+
+        let loaded = SparseBitset::<u64>::read_from(&bytes[..]).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            vec![3, 63, 64, 1000, 1 << 40]
+        );
+
+        // The last rank entry doesn't cover the end, when the block count is a multiple of the interval.
+        let indices: Vec<u64> = (0..RANK_INTERVAL as u64).map(|k| k * 64).collect();
+        let bytes = bitset(&indices).write_to(vec![], true).unwrap();
+        let view = SparseBitsetView::new(&bytes).unwrap();
+        assert_eq!(view.rank(u64::MAX), RANK_INTERVAL as u64);
+    }
+
+    #[test]
+    fn test_streaming_writer() {
+        let mut writer = SparseBitsetWriter::new(vec![], false).unwrap();
+        for i in (0..100_000).step_by(7) {
+            writer.push(i).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        let view = SparseBitsetView::new(&bytes).unwrap();
+        assert_eq!(view.count_ones(), 100_000_u64.div_ceil(7));
+        assert!(view.fetch(700));
+        assert!(!view.fetch(701));
+        assert_eq!(view.rank(7001), 1001);
+    }
+
+    #[test]
+    fn test_corruption() {
+        let bytes = bitset(&[1, 2, 3]).write_to(vec![], false).unwrap();
+        assert!(SparseBitsetView::new(&bytes).is_ok());
+
+        let mut corrupt = bytes.clone();
+        corrupt[20] ^= 1;
+        assert!(SparseBitsetView::new(&corrupt).is_err());
+        assert!(SparseBitsetView::new(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SparseBitsetView::new(b"not a bitset at all, really not").is_err());
+    }
+
+    #[test]
+    fn test_huge_block_count() {
+        let bytes = bitset(&[1, 2, 3]).write_to(vec![], true).unwrap();
+        let footer = bytes.len() - FOOTER_BYTES;
+        for block_count in [u64::MAX, u64::MAX / BLOCK_BYTES as u64 + 1, 1 << 62] {
+            let mut corrupt = bytes.clone();
+            corrupt[footer..footer + 8].copy_from_slice(&block_count.to_le_bytes());
+            let mut checksum = Checksum::new();
+            checksum.update(&corrupt[..footer + 16]);
+            corrupt[footer + 16..].copy_from_slice(&checksum.0.to_le_bytes());
+
+            let err = SparseBitsetView::new(&corrupt).err().unwrap();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_roaring() {
+        let view_bytes = bitset(&[1, 5, 65536 + 2]).write_to(vec![], false).unwrap();
+        let roaring = SparseBitsetView::new(&view_bytes)
+            .unwrap()
+            .write_roaring(vec![])
+            .unwrap();
+
+        let mut expected = vec![];
+        expected.extend_from_slice(&12346_u32.to_le_bytes());
+        expected.extend_from_slice(&2_u32.to_le_bytes());
+        expected.extend_from_slice(&[0, 0, 1, 0, 1, 0, 0, 0]);
+        expected.extend_from_slice(&24_u32.to_le_bytes());
+        expected.extend_from_slice(&28_u32.to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 5, 0, 2, 0]);
+        assert_eq!(roaring, expected);
+
+        // Containers with more than 4096 values are bitmaps.
+        let dense: Vec<u64> = (0..70_000).filter(|i| i % 3 != 0).collect();
+        let bytes = bitset(&dense).write_to(vec![], false).unwrap();
+        let roaring = SparseBitsetView::new(&bytes)
+            .unwrap()
+            .write_roaring(vec![])
+            .unwrap();
+        let loaded = roaring::RoaringBitmap::deserialize_from(&roaring[..]).unwrap();
+        assert_eq!(loaded.iter().map(u64::from).collect::<Vec<_>>(), dense);
+
+        let too_big = bitset(&[1 << 32]).write_to(vec![], false).unwrap();
+        assert!(SparseBitsetView::new(&too_big)
+            .unwrap()
+            .write_roaring(vec![])
+            .is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roaring_interop(indices in proptest::collection::btree_set(0..(1_u64 << 18), 0..2_000)) {
+            let bytes = bitset(&indices.iter().copied().collect::<Vec<_>>()).write_to(vec![], false).unwrap();
+            let roaring = SparseBitsetView::new(&bytes).unwrap().write_roaring(vec![]).unwrap();
+
+            let loaded = roaring::RoaringBitmap::deserialize_from(&roaring[..]).unwrap();
+            assert_eq!(loaded.iter().map(u64::from).collect::<Vec<_>>(), indices.into_iter().collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn test_rank(indices in proptest::collection::btree_set(0..(RANK_INTERVAL as u64 * 64 * 5), 0..500), probe in 0..(RANK_INTERVAL as u64 * 64 * 5)) {
+            let indices: Vec<u64> = indices.into_iter().collect();
+            let bs = bitset(&indices);
+            let expected = indices.iter().filter(|i| **i < probe).count() as u64;
+
+            for with_rank in [false, true] {
+                let bytes = bs.write_to(vec![], with_rank).unwrap();
+                let view = SparseBitsetView::new(&bytes).unwrap();
+                assert_eq!(view.rank(probe), expected);
+                assert_eq!(view.fetch(probe), indices.contains(&probe));
+            }
+        }
+    }
+}
@@ -1,58 +1,226 @@
-use crate::block::{AlignedBitfield, BlockFetch, BlockStore, DefaultValue, IndexedBlock, SparseVec};
+use std::marker::PhantomData;
+
+use crate::block::{
+    AlignedBitfield, AlignedBlock, AlignedBlockFromIterator, BitSearch, BitWords, BlockCollection, BlockCursor, BlockFetch, BlockStore,
+    DefaultValue, IndexedBlock, SparseVec,
+};
+use crate::merge::SkipTo;
+use crate::numerical_index::NumericalIndex;
+use crate::simd::{self, BitOp};
+
+/// The number of words that `count_ones` gathers from the blocks before counting them all at once.
+const COUNT_WORDS: usize = 256;
 
 /// Implementation of a sparse bitset.
-#[derive(Default)]
-pub struct SparseBitset<T> 
+///
+/// The blocks are `AlignedBitfield<T>` by default, one word each. Medium-density sets can use larger blocks,
+/// such as `SparseBitset<usize, AlignedBitArray<8>>`, so that fewer positions are stored.
+pub struct SparseBitset<T, B = AlignedBitfield<T>>
+{
+    bitset: SparseVec<B,DefaultValue>,
+    index: PhantomData<T>,
+}
+
+impl<T, B> Default for SparseBitset<T, B>
 {
-    bitset: SparseVec<AlignedBitfield<T>,DefaultValue>
+    fn default() -> Self {
+        SparseBitset {
+            bitset: SparseVec::default(),
+            index: PhantomData,
+        }
+    }
 }
 
-impl<T> IndexedBlock for SparseBitset<T>
+impl<T, B> IndexedBlock for SparseBitset<T, B>
 where
-AlignedBitfield<T>: IndexedBlock,
-<AlignedBitfield<T> as IndexedBlock>::Item: Default,
+    B: IndexedBlock<Index = T, Item = bool>,
 {
     type Index = T;
 
     type Item = bool;
 }
 
-impl BlockFetch for SparseBitset<usize>
+impl<T, B> SparseBitset<T, B>
+where
+    B: AlignedBlock<Index = T, Item = bool>,
+    T: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        self.bitset.fetch(index)
+    /// Construct a new SparseBitset from blocks, which must be in order by position.
+    pub fn new_from(blocks: Vec<B>) -> Self {
+        SparseBitset {
+            bitset: SparseVec::new_from(DefaultValue, blocks),
+            index: PhantomData,
+        }
     }
 }
 
-impl BlockStore for SparseBitset<usize>
+impl<T, B> BlockCollection for SparseBitset<T, B>
+where
+    B: AlignedBlock<Index = T, Item = bool>,
 {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        self.bitset.store(index,item);
+    type Block = B;
+
+    fn blocks(&self) -> &[B] {
+        self.bitset.blocks()
     }
 }
 
-impl BlockFetch for SparseBitset<u64>
+impl<T, B> SparseBitset<T, B>
+where
+    B: BitSearch<Index = T>,
+    T: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        self.bitset.fetch(index)
+    /// Iterate over the indices of every set bit, in increasing order.
+    /// The iterator can skip ahead over whole blocks (see `SkipTo`).
+    pub fn iter(&self) -> SparseBitsetIter<'_, B> {
+        SparseBitsetIter {
+            cursor: BlockCursor::new(self.bitset.blocks()),
+        }
     }
 }
 
-impl BlockStore for SparseBitset<u64>
+/// An iterator over the indices of the set bits of a `SparseBitset`, in increasing order.
+pub struct SparseBitsetIter<'a, B>
+where
+    B: AlignedBlock,
 {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        self.bitset.store(index,item);
+    cursor: BlockCursor<'a, B>,
+}
+
+impl<'a, B> Iterator for SparseBitsetIter<'a, B>
+where
+    B: BitSearch,
+    B::Index: NumericalIndex,
+{
+    type Item = B::Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (block, from) = self.cursor.current()?;
+            match block.first_set_from(from) {
+                Some(index) => {
+                    self.cursor.advance_past(index);
+                    return Some(index);
+                }
+                None => self.cursor.next_block(),
+            }
+        }
+    }
+}
+
+impl<'a, B> SkipTo for SparseBitsetIter<'a, B>
+where
+    B: BitSearch,
+    B::Index: NumericalIndex,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        self.cursor.seek(target);
+        self.next()
+    }
+}
+
+impl<T, B> SparseBitset<T, B>
+where
+    B: BitWords<Index = T>,
+{
+    /// The number of set bits. The words of many blocks are gathered and counted at once.
+    pub fn count_ones(&self) -> usize {
+        let mut words = vec![0; COUNT_WORDS.max(B::WORDS)];
+        let mut count = 0;
+        for blocks in self.bitset.blocks().chunks((COUNT_WORDS / B::WORDS).max(1)) {
+            let words = &mut words[..blocks.len() * B::WORDS];
+            for (block, words) in blocks.iter().zip(words.chunks_exact_mut(B::WORDS)) {
+                block.write_words(words);
+            }
+            count += simd::count_ones(words);
+        }
+        count
     }
 }
 
-impl BlockFetch for SparseBitset<u128>
+impl<T, B> SparseBitset<T, B>
+where
+    B: BitWords<Index = T> + Clone,
+    T: NumericalIndex,
+{
+    /// Keep only the bits that are also set in `other`.
+    pub fn and(&mut self, other: &Self) {
+        self.combine(other, BitOp::And);
+    }
+
+    /// Set every bit that is set in `other`.
+    pub fn or(&mut self, other: &Self) {
+        self.combine(other, BitOp::Or);
+    }
+
+    /// Flip every bit that is set in `other`.
+    pub fn xor(&mut self, other: &Self) {
+        self.combine(other, BitOp::Xor);
+    }
+
+    /// Clear every bit that is set in `other`.
+    pub fn and_not(&mut self, other: &Self) {
+        self.combine(other, BitOp::AndNot);
+    }
+
+    /// Merge the blocks of both sets by position. A block that is in only one set is kept or dropped whole.
+    /// The words of every pair of blocks at the same position are gathered, and combined all at once.
+    /// Combined blocks that end up empty are dropped.
+    fn combine(&mut self, other: &Self, op: BitOp) {
+        let keep_ours = op != BitOp::And;
+        let keep_theirs = matches!(op, BitOp::Or | BitOp::Xor);
+
+        let mut theirs = other.bitset.blocks().iter().peekable();
+        let mut blocks = vec![];
+        // Where each pair of blocks at the same position goes in `blocks`. Their words are gathered side by side.
+        let mut matched = vec![];
+        let (mut ours_words, mut theirs_words) = (vec![], vec![]);
+        for block in std::mem::take(&mut self.bitset).into_vec() {
+            while let Some(t) = theirs.next_if(|t| t.position() < block.position()) {
+                if keep_theirs {
+                    blocks.push(Some(t.clone()));
+                }
+            }
+            match theirs.next_if(|t| t.position() == block.position()) {
+                Some(t) => {
+                    matched.push((blocks.len(), block.position()));
+                    let end = ours_words.len() + B::WORDS;
+                    ours_words.resize(end, 0);
+                    theirs_words.resize(end, 0);
+                    block.write_words(&mut ours_words[end - B::WORDS..]);
+                    t.write_words(&mut theirs_words[end - B::WORDS..]);
+                    blocks.push(Some(block));
+                }
+                None if keep_ours => blocks.push(Some(block)),
+                None => {}
+            }
+        }
+        if keep_theirs {
+            blocks.extend(theirs.map(|t| Some(t.clone())));
+        }
+
+        simd::bitwise(op, &mut ours_words, &theirs_words);
+        for ((k, position), words) in matched.into_iter().zip(ours_words.chunks_exact(B::WORDS)) {
+            blocks[k] = words.iter().any(|w| *w != 0).then(|| B::from_words(position, words));
+        }
+        self.bitset = SparseVec::new_from(DefaultValue, blocks.into_iter().flatten().collect());
+    }
+}
+
+impl<T, B> BlockFetch for SparseBitset<T, B>
+where
+    B: AlignedBlock<Index = T, Item = bool> + BlockFetch,
+    T: NumericalIndex,
 {
     fn fetch(&self, index: Self::Index) -> Self::Item {
         self.bitset.fetch(index)
     }
 }
 
-impl BlockStore for SparseBitset<u128>
+impl<T, B> BlockStore for SparseBitset<T, B>
+where
+    B: AlignedBlock<Index = T, Item = bool> + BlockStore + AlignedBlockFromIterator,
+    T: NumericalIndex,
 {
     fn store(&mut self, index: Self::Index, item: Self::Item) {
         self.bitset.store(index,item);
@@ -61,7 +229,10 @@ impl BlockStore for SparseBitset<u128>
 
 #[cfg(test)]
 mod test {
-    use crate::block::{BlockFetch, BlockStore};
+    use crate::block::{AlignedBitArray, BlockCollection, BlockFetch, BlockStore};
+    use crate::merge::SkipTo;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
 
     use super::SparseBitset;
 
@@ -91,4 +262,159 @@ mod test {
         assert_eq!(bs.fetch(220),false);
         assert_eq!(bs.fetch(221),true);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fetch_store_signed() {
+        let mut bs: SparseBitset<i32> = SparseBitset::default();
+        bs.store(-1, true);
+        bs.store(i32::MIN, true);
+        bs.store(40, true);
+
+        assert_eq!(bs.fetch(-1), true);
+        assert_eq!(bs.fetch(0), false);
+        assert_eq!(bs.iter().collect::<Vec<_>>(), vec![i32::MIN, -1, 40]);
+    }
+
+    #[test]
+    fn test_bit_array_blocks() {
+        let mut bs: SparseBitset<usize, AlignedBitArray<4>> = SparseBitset::default();
+        bs.store(1000, true);
+        bs.store(3, true);
+        bs.store(255, true);
+        bs.store(256, true);
+        bs.store(256, false);
+
+        assert_eq!(bs.blocks().len(), 3);
+        assert_eq!(bs.blocks()[0].count_ones(), 2);
+        assert_eq!(bs.count_ones(), 3);
+        assert_eq!(bs.fetch(255), true);
+        assert_eq!(bs.fetch(256), false);
+        assert_eq!(bs.iter().collect::<Vec<_>>(), vec![3, 255, 1000]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut bs: SparseBitset<u64> = SparseBitset::default();
+        bs.store(1000, true);
+        bs.store(3, true);
+        bs.store(64, true);
+        bs.store(63, true);
+        bs.store(64, false);
+
+        assert_eq!(bs.iter().collect::<Vec<_>>(), vec![3, 63, 1000]);
+        assert_eq!(bs.count_ones(), 3);
+    }
+
+    #[test]
+    fn test_top_of_index_space() {
+        let mut bs: SparseBitset<u64> = SparseBitset::default();
+        bs.store(u64::MAX, true);
+        bs.store(u64::MAX - 63, true);
+        bs.store(u64::MAX - 64, true);
+
+        assert!(bs.fetch(u64::MAX));
+        assert!(!bs.fetch(u64::MAX - 1));
+        assert_eq!(
+            bs.iter().collect::<Vec<_>>(),
+            vec![u64::MAX - 64, u64::MAX - 63, u64::MAX]
+        );
+
+        let mut bs: SparseBitset<u128> = SparseBitset::default();
+        bs.store(u128::MAX, true);
+        assert_eq!(bs.iter().collect::<Vec<_>>(), vec![u128::MAX]);
+    }
+
+    #[test]
+    fn test_skip_to() {
+        let mut bs: SparseBitset<usize, AlignedBitArray<2>> = SparseBitset::default();
+        for i in [5, 127, 128, 4000, 4001, usize::MAX] {
+            bs.store(i, true);
+        }
+
+        let mut iter = bs.iter();
+        assert_eq!(iter.skip_to(6), Some(127));
+        assert_eq!(iter.skip_to(0), Some(128));
+        assert_eq!(iter.skip_to(1000), Some(4000));
+        assert_eq!(iter.skip_to(4001), Some(4001));
+        assert_eq!(iter.next(), Some(usize::MAX));
+        assert_eq!(iter.skip_to(usize::MAX), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_skip_to_anywhere(
+            indices in proptest::collection::btree_set(any::<i16>(), 0..50),
+            targets in proptest::collection::vec(any::<i16>(), 0..5),
+        ) {
+            let mut bs: SparseBitset<i16> = SparseBitset::default();
+            for i in indices.iter() {
+                bs.store(*i, true);
+            }
+            let mut iter = bs.iter();
+            let mut expected = indices.into_iter();
+            for target in targets {
+                assert_eq!(iter.skip_to(target), expected.find(|i| *i >= target));
+            }
+            assert_eq!(iter.collect::<Vec<_>>(), expected.collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn test_bulk_ops(
+            a in proptest::collection::btree_set(any::<i16>(), 0..200),
+            b in proptest::collection::btree_set(any::<i16>(), 0..200),
+        ) {
+            let from = |set: &BTreeSet<i16>| {
+                let mut bs: SparseBitset<i16> = SparseBitset::default();
+                for i in set.iter() {
+                    bs.store(*i, true);
+                }
+                bs
+            };
+            let (bs_a, bs_b) = (from(&a), from(&b));
+            let check = |op: fn(&mut SparseBitset<i16>, &SparseBitset<i16>), expected: BTreeSet<i16>| {
+                let mut result = from(&a);
+                op(&mut result, &bs_b);
+                assert_eq!(result.iter().collect::<BTreeSet<_>>(), expected);
+                assert_eq!(result.count_ones(), expected.len());
+                assert!(result.blocks().iter().all(|b| b.bits() != 0));
+            };
+            check(SparseBitset::and, a.intersection(&b).copied().collect());
+            check(SparseBitset::or, a.union(&b).copied().collect());
+            check(SparseBitset::xor, a.symmetric_difference(&b).copied().collect());
+            check(SparseBitset::and_not, a.difference(&b).copied().collect());
+            assert_eq!(bs_a.count_ones(), a.len());
+        }
+
+        #[test]
+        fn test_bulk_ops_bit_array(
+            a in proptest::collection::btree_set(0..100_000_usize, 0..500),
+            b in proptest::collection::btree_set(0..100_000_usize, 0..500),
+        ) {
+            let from = |set: &BTreeSet<usize>| {
+                let mut bs: SparseBitset<usize, AlignedBitArray<2>> = SparseBitset::default();
+                for i in set.iter() {
+                    bs.store(*i, true);
+                }
+                bs
+            };
+            let mut result = from(&a);
+            result.xor(&from(&b));
+            assert_eq!(result.iter().collect::<BTreeSet<_>>(), a.symmetric_difference(&b).copied().collect());
+            let mut result = from(&a);
+            result.and(&from(&b));
+            assert_eq!(result.count_ones(), a.intersection(&b).count());
+        }
+
+        #[test]
+        fn test_store_anywhere(indices in proptest::collection::btree_set(any::<u128>(), 0..20)) {
+            let mut bs: SparseBitset<u128> = SparseBitset::default();
+            for i in indices.iter() {
+                bs.store(*i, true);
+            }
+            for i in indices.iter() {
+                assert!(bs.fetch(*i));
+            }
+            assert_eq!(bs.iter().collect::<Vec<_>>(), indices.into_iter().collect::<Vec<_>>());
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::numerical_index::NumericalIndex;
 
+pub use very_large_collections_derive::AlignedBlock;
+
 /// A block of data (an array) which has an item type and index type.
 pub trait IndexedBlock {
     /// How the block is indexed (for example, by usize)
@@ -36,6 +38,63 @@ pub trait BlockStore: IndexedBlock {
     fn store(&mut self, index: Self::Index, item: Self::Item);
 }
 
+/// A block where it is possible to borrow any individual element.
+/// Unlike `BlockFetch`, this does not require the element type to be `Copy`.
+/// Blocks that do not store their elements directly, such as bit-packed or compressed blocks, cannot implement this.
+pub trait BlockGet: IndexedBlock {
+    /// Get a reference to an element of a block, or `None` if there is no stored element to refer to.
+    fn get(&self, index: Self::Index) -> Option<&Self::Item>;
+}
+
+/// A block where it is possible to mutably borrow any individual element.
+/// Wrappers that update something else on every store, such as an index or per-block statistics, only implement `BlockGet`,
+/// because a mutable borrow would change an element without updating it.
+pub trait BlockGetMut: BlockGet {
+    /// Get a mutable reference to an element of a block, or `None` if there is no stored element to refer to.
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item>;
+}
+
+/// A block of booleans that can count its set elements without visiting each one.
+pub trait BitCount: AlignedBlock<Item = bool> {
+    /// The number of elements that are `true`.
+    fn count_ones(&self) -> usize;
+}
+
+/// A block of booleans that can find its next `true` element without visiting each one.
+pub trait BitSearch: AlignedBlock<Item = bool> {
+    /// The index of the first `true` element at or after `index`, or `None` if there is none in this block.
+    /// The index does not need to be inside of the block.
+    fn first_set_from(&self, index: Self::Index) -> Option<Self::Index>;
+}
+
+/// A block of booleans that is stored as whole 64-bit words, so that bulk operations can work on the words of many blocks at once.
+/// Bit `i` of word `w` is the element at `position + 64 * w + i`. Blocks of fewer than 64 elements use the low bits of a single word.
+pub trait BitWords: AlignedBlock<Item = bool> {
+    /// The number of words in every block.
+    const WORDS: usize;
+
+    /// Write the words of this block into `words`, which must be exactly `WORDS` long.
+    fn write_words(&self, words: &mut [u64]);
+
+    /// Construct a block from exactly `WORDS` words. Bits past the end of the block are ignored.
+    fn from_words(position: Self::Index, words: &[u64]) -> Self;
+}
+
+/// A collection made up of AlignedBlocks.
+pub trait BlockCollection: IndexedBlock {
+    /// The type of the blocks.
+    type Block: AlignedBlock<Index = Self::Index, Item = Self::Item>;
+
+    /// All blocks that are actually stored in this collection, in order by position.
+    fn blocks(&self) -> &[Self::Block];
+}
+
+/// A block or collection whose stored elements can be enumerated along with their indices.
+pub trait BlockEnumerate: IndexedBlock {
+    /// Iterate over every stored element and its index, in order by index.
+    fn enumerate_items(&self) -> impl Iterator<Item = (Self::Index, Self::Item)> + '_;
+}
+
 /// Aligned block that can be constructed from the leading elements of an iterator.
 pub trait AlignedBlockFromIterator: AlignedBlock + Sized {
     /// Construct an AlignedBlock from a function that takes an index and returns a value.
@@ -56,6 +115,12 @@ pub trait AlignedBlockFromIterator: AlignedBlock + Sized {
         I: Iterator<Item = Self::Item>;
 }
 
+/// Default values that can also be borrowed, so that a `SparseVec` can return references to elements of absent blocks.
+pub trait DefaultPerIndexRef<Index, Item>: DefaultPerIndex<Index, Item> {
+    /// Borrow the default value at the given index, or `None` if the default value is not stored anywhere.
+    fn default_ref_at_index(&self, i: Index) -> Option<&Item>;
+}
+
 /// Initialize an AlignedBlock using its Default impl.
 #[derive(Default)]
 pub struct DefaultValue;
@@ -64,4 +129,134 @@ impl<Index, Item: Default> DefaultPerIndex<Index, Item> for DefaultValue {
     fn default_at_index(&self, _: Index) -> Item {
         Item::default()
     }
-}
\ No newline at end of file
+}
+
+/// The default value is constructed on demand, so it can't be borrowed.
+impl<Index, Item: Default> DefaultPerIndexRef<Index, Item> for DefaultValue {
+    fn default_ref_at_index(&self, _: Index) -> Option<&Item> {
+        None
+    }
+}
+
+/// Initialize an AlignedBlock with clones of a single shared value, which can also be borrowed.
+#[derive(Clone, Default)]
+pub struct SharedDefault<Item>(pub Item);
+
+impl<Index, Item: Clone> DefaultPerIndex<Index, Item> for SharedDefault<Item> {
+    fn default_at_index(&self, _: Index) -> Item {
+        self.0.clone()
+    }
+}
+
+impl<Index, Item: Clone> DefaultPerIndexRef<Index, Item> for SharedDefault<Item> {
+    fn default_ref_at_index(&self, _: Index) -> Option<&Item> {
+        Some(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block::{
+        AlignedBlock, AlignedBlockFromIterator, BlockCollection, BlockFetch, BlockGet, BlockGetMut,
+        BlockStore, DefaultValue, DenseVec, IndexedBlock, SparseVec,
+    };
+    use crate::numerical_index::NumericalIndex;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, NumericalIndex)]
+    struct RowId(u64);
+
+    #[derive(AlignedBlock)]
+    #[aligned_block(alignment = RowId(4))]
+    struct Rows {
+        #[position]
+        start: RowId,
+        #[elements]
+        values: [u32; 4],
+        touched: bool,
+    }
+
+    #[derive(AlignedBlock)]
+    #[aligned_block(alignment = RowId(4))]
+    struct TooManyRows {
+        #[position]
+        start: RowId,
+        #[elements]
+        values: [u32; 8],
+    }
+
+    #[derive(AlignedBlock)]
+    #[aligned_block(alignment = 3)]
+    struct Names(#[position] usize, #[elements] Vec<String>);
+
+    #[derive(AlignedBlock)]
+    #[aligned_block(alignment = 8, item = bool)]
+    struct Flags {
+        #[position]
+        position: usize,
+    }
+
+    #[test]
+    fn test_derive_array_block() {
+        let mut rows = Rows::from_iterator(RowId(8), &mut (1..));
+        assert_eq!(Rows::alignment(), RowId(4));
+        assert_eq!(rows.position(), RowId(8));
+        assert_eq!(rows.fetch(RowId(10)), 3);
+        assert!(!rows.touched);
+
+        rows.store(RowId(11), 40);
+        rows.touched = true;
+        assert_eq!(rows.get(RowId(11)), Some(&40));
+        assert_eq!(rows.get(RowId(7)), None);
+        assert_eq!(rows.get_mut(RowId(12)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_derive_fetch_out_of_bounds() {
+        let rows = Rows::from_iterator(RowId(8), &mut (1..));
+        rows.fetch(RowId(7));
+    }
+
+    #[test]
+    #[should_panic(expected = "exactly as long as the alignment")]
+    fn test_derive_array_length_mismatch() {
+        TooManyRows::from_iterator(RowId(0), &mut (1..));
+    }
+
+    #[test]
+    fn test_derive_in_sparse_vec() {
+        let mut v: SparseVec<Rows, DefaultValue> = SparseVec::default();
+        v.store(RowId(1_000_001), 7);
+        v.store(RowId(2), 5);
+
+        assert_eq!(v.fetch(RowId(1_000_001)), 7);
+        assert_eq!(v.fetch(RowId(1_000_000)), 0);
+        assert_eq!(v.fetch(RowId(3)), 0);
+        assert_eq!(
+            v.blocks().iter().map(|b| b.position()).collect::<Vec<_>>(),
+            vec![RowId(0), RowId(1_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_derive_in_dense_vec() {
+        let mut v = DenseVec::new_from(vec![
+            Names::from_function(0, |i| i.to_string()),
+            Names::from_function(3, |i| i.to_string()),
+        ]);
+        v.store(4, "four".to_string());
+
+        assert_eq!(v.fetch(2), "2");
+        assert_eq!(v.get(4).map(String::as_str), Some("four"));
+        assert_eq!(v.blocks()[1].1.len(), 3);
+    }
+
+    #[test]
+    fn test_derive_without_elements() {
+        let flags = Flags { position: 16 };
+        assert_eq!(Flags::alignment(), 8);
+        assert_eq!(flags.position(), 16);
+        assert_eq!(flags.position.last_in_block(Flags::alignment()), 23);
+        static_assertions::assert_type_eq_all!(<Flags as IndexedBlock>::Item, bool);
+    }
+}
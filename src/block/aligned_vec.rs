@@ -1,8 +1,17 @@
 use std::ops::{Index, IndexMut};
 
-use super::{AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore, IndexedBlock};
+use crate::{
+    simd::{self, SimdNumber},
+    sort::RadixSortable,
+};
+
+use super::{
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockGet, BlockGetMut, BlockSize,
+    BlockStore, IndexedBlock,
+};
 
 /// A vector as an AlignedBlock.s
+#[derive(Clone)]
 pub struct AlignedVec<T, const N: usize> {
     position: usize,
     vec: Vec<T>,
@@ -56,6 +65,27 @@ impl<T, const N: usize> AlignedVec<T, N> {
     }
 }
 
+impl<T, const N: usize> AlignedVec<T, N>
+where
+    T: SimdNumber,
+{
+    /// The sum of the elements. Integers wrap around on overflow.
+    /// Floats are added up in several lanes, so rounding can differ from a sequential sum.
+    pub fn sum(&self) -> T {
+        simd::sum(&self.vec)
+    }
+
+    /// The smallest element.
+    pub fn min(&self) -> T {
+        simd::min(&self.vec)
+    }
+
+    /// The largest element.
+    pub fn max(&self) -> T {
+        simd::max(&self.vec)
+    }
+}
+
 impl<T, const N: usize> BlockFetch for AlignedVec<T, N>
 where
     T: Copy,
@@ -65,15 +95,31 @@ where
     }
 }
 
-impl<T, const N: usize> BlockStore for AlignedVec<T, N>
-where
-    T: Copy,
-{
+impl<T, const N: usize> BlockStore for AlignedVec<T, N> {
     fn store(&mut self, index: Self::Index, item: Self::Item) {
         self[index] = item;
     }
 }
 
+impl<T, const N: usize> BlockGet for AlignedVec<T, N> {
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.vec.get(index.checked_sub(self.position)?)
+    }
+}
+
+impl<T, const N: usize> BlockGetMut for AlignedVec<T, N> {
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        self.vec.get_mut(index.checked_sub(self.position)?)
+    }
+}
+
+/// The elements are on the heap, so they are counted along with the block itself.
+impl<T, const N: usize> BlockSize for AlignedVec<T, N> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + N * std::mem::size_of::<T>()
+    }
+}
+
 impl<T, const N: usize> Index<usize> for AlignedVec<T, N> {
     type Output = T;
 
@@ -89,6 +135,27 @@ impl<T, const N: usize> IndexMut<usize> for AlignedVec<T, N> {
     }
 }
 
+/// Radix sorting an AlignedVec sorts the elements within the block. Elements are addressed from zero, not from the position of the block.
+impl<T, const N: usize> RadixSortable for AlignedVec<T, N> {
+    type Item = T;
+
+    fn radix_len(&self) -> usize {
+        N
+    }
+
+    fn radix_get(&self, i: usize) -> &T {
+        &self.vec[i]
+    }
+
+    fn radix_get_mut(&mut self, i: usize) -> &mut T {
+        &mut self.vec[i]
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.vec.swap(i, j);
+    }
+}
+
 impl<T, const N: usize> AlignedBlockFromIterator for AlignedVec<T, N> {
     fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
     where
@@ -107,7 +174,10 @@ impl<T, const N: usize> AlignedBlockFromIterator for AlignedVec<T, N> {
 
 #[cfg(test)]
 mod test {
-    use crate::block::AlignedBlockFromIterator;
+    use crate::{
+        block::{AlignedBlock, AlignedBlockFromIterator, BlockGet, BlockGetMut},
+        sort::RadixSortable,
+    };
 
     use super::AlignedVec;
 
@@ -121,6 +191,22 @@ mod test {
         assert_eq!(av[39], 8);
     }
 
+    #[test]
+    pub fn test_get() {
+        let v = vec![
+            "a".to_string(),
+            "b".to_string(),
+            "c".to_string(),
+            "d".to_string(),
+        ];
+        let mut av: AlignedVec<String, 4> = AlignedVec::new_from(8, v);
+
+        av.get_mut(9).unwrap().push('!');
+        assert_eq!(av.get(9).map(String::as_str), Some("b!"));
+        assert_eq!(av.get(7), None);
+        assert_eq!(av.get(12), None);
+    }
+
     #[test]
     pub fn test_write() {
         let v = vec![1, 2, 3, 4, 5, 6, 7, 8];
@@ -141,4 +227,29 @@ mod test {
 
         assert_eq!(v1, v2);
     }
+
+    #[test]
+    fn test_reductions() {
+        let av: AlignedVec<i32, 64> = AlignedVec::from_function(0, |i| 30 - i as i32);
+        assert_eq!(av.sum(), (-33..=30).sum::<i32>());
+        assert_eq!(av.min(), -33);
+        assert_eq!(av.max(), 30);
+
+        let av: AlignedVec<u8, 4> = AlignedVec::new_from(0, vec![200, 100, 1, 2]);
+        assert_eq!(av.sum(), 47);
+
+        let av: AlignedVec<f32, 4> = AlignedVec::new_from(0, vec![0.5, -1.5, 2.0, 0.25]);
+        assert_eq!(av.sum(), 1.25);
+        assert_eq!(av.min(), -1.5);
+    }
+
+    #[test]
+    fn test_radix_sort() {
+        let v = vec![5, 3, 7, 9, 1, 1, 90, 3];
+        let mut av: AlignedVec<u32, 8> = AlignedVec::new_from(32, v);
+        av.radix_sort();
+
+        assert_eq!(av.position(), 32);
+        assert_eq!(av.into_vec(), vec![1, 1, 3, 3, 5, 7, 9, 90]);
+    }
 }
@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use super::{
-    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore, DefaultPerIndex, IndexedBlock,
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockGet, BlockGetMut, BlockStore,
+    DefaultPerIndex, DefaultPerIndexRef, IndexedBlock,
 };
 
 impl<B> IndexedBlock for Arc<B>
@@ -44,6 +45,25 @@ where
     }
 }
 
+impl<B> BlockGet for Arc<B>
+where
+    B: BlockGet,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.as_ref().get(index)
+    }
+}
+
+impl<B> BlockGetMut for Arc<B>
+where
+    B: BlockGetMut,
+    B: Clone,
+{
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        Arc::make_mut(self).get_mut(index)
+    }
+}
+
 impl<B> AlignedBlockFromIterator for Arc<B>
 where
     B: AlignedBlockFromIterator,
@@ -64,3 +84,12 @@ where
         Arc::as_ref(self).default_at_index(i)
     }
 }
+
+impl<D, Index, Item> DefaultPerIndexRef<Index, Item> for Arc<D>
+where
+    D: DefaultPerIndexRef<Index, Item>,
+{
+    fn default_ref_at_index(&self, i: Index) -> Option<&Item> {
+        Arc::as_ref(self).default_ref_at_index(i)
+    }
+}
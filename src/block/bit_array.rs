@@ -0,0 +1,291 @@
+use crate::simd::{self, BitOp};
+
+use super::{
+    AlignedBlock, AlignedBlockFromIterator, BitCount, BitSearch, BitWords, BlockFetch, BlockSize,
+    BlockStore, IndexedBlock,
+};
+
+/// An aligned block of booleans spanning several words, with alignment `64 * WORDS`.
+/// Bit `i` of word `w` is the element at `position + 64 * w + i`.
+///
+/// Compared to an `AlignedBitfield`, each block covers more indices for the same position,
+/// which keeps the per-block overhead of a `SparseBitset` small for medium-density sets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AlignedBitArray<const WORDS: usize> {
+    position: usize,
+    words: [u64; WORDS],
+}
+
+impl<const WORDS: usize> AlignedBitArray<WORDS> {
+    /// Construct a block from its raw words.
+    pub fn new_from(position: usize, words: [u64; WORDS]) -> Self {
+        assert!(position % Self::alignment() == 0, "blocks must be aligned");
+        AlignedBitArray { position, words }
+    }
+
+    /// The raw words, where bit `i` of word `w` is the element at `position + 64 * w + i`.
+    pub fn words(&self) -> &[u64; WORDS] {
+        &self.words
+    }
+
+    /// The raw words, which may be modified in place.
+    pub fn words_mut(&mut self) -> &mut [u64; WORDS] {
+        &mut self.words
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        simd::count_ones(&self.words)
+    }
+
+    /// The index of the first set bit, or `None` if no bit is set.
+    pub fn first_set(&self) -> Option<usize> {
+        self.first_set_from_word(0)
+    }
+
+    /// The index of the first set bit after `index`, or `None` if there is none in this block.
+    /// The index does not need to be inside of the block.
+    pub fn next_set_after(&self, index: usize) -> Option<usize> {
+        if index < self.position {
+            return self.first_set();
+        }
+        let offset = (index - self.position).checked_add(1)?;
+        let (w, bit) = (offset / 64, offset % 64);
+        if w >= WORDS {
+            return None;
+        }
+        let word = self.words[w] & (!0 << bit);
+        if word != 0 {
+            return Some(self.position + 64 * w + word.trailing_zeros() as usize);
+        }
+        self.first_set_from_word(w + 1)
+    }
+
+    /// Iterate over the indices of every set bit, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(move |(w, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                let bit = (word != 0).then(|| word.trailing_zeros() as usize)?;
+                word &= word - 1;
+                Some(self.position + 64 * w + bit)
+            })
+        })
+    }
+
+    /// Keep only the bits that are also set in `other`.
+    pub fn and(&mut self, other: &Self) {
+        self.combine(other, BitOp::And);
+    }
+
+    /// Set every bit that is set in `other`.
+    pub fn or(&mut self, other: &Self) {
+        self.combine(other, BitOp::Or);
+    }
+
+    /// Flip every bit that is set in `other`.
+    pub fn xor(&mut self, other: &Self) {
+        self.combine(other, BitOp::Xor);
+    }
+
+    /// Clear every bit that is set in `other`.
+    pub fn and_not(&mut self, other: &Self) {
+        self.combine(other, BitOp::AndNot);
+    }
+
+    fn combine(&mut self, other: &Self, op: BitOp) {
+        assert_eq!(
+            self.position, other.position,
+            "blocks must have the same position"
+        );
+        simd::bitwise(op, &mut self.words, &other.words);
+    }
+
+    fn first_set_from_word(&self, w: usize) -> Option<usize> {
+        (w..WORDS)
+            .find(|w| self.words[*w] != 0)
+            .map(|w| self.position + 64 * w + self.words[w].trailing_zeros() as usize)
+    }
+
+    fn word_and_bit(&self, index: usize) -> (usize, usize) {
+        assert!(index >= self.position);
+        let offset = index - self.position;
+        assert!(offset < Self::alignment());
+        (offset / 64, offset % 64)
+    }
+}
+
+impl<const WORDS: usize> Default for AlignedBitArray<WORDS> {
+    fn default() -> Self {
+        AlignedBitArray {
+            position: 0,
+            words: [0; WORDS],
+        }
+    }
+}
+
+impl<const WORDS: usize> IndexedBlock for AlignedBitArray<WORDS> {
+    type Index = usize;
+    type Item = bool;
+}
+
+impl<const WORDS: usize> AlignedBlock for AlignedBitArray<WORDS> {
+    fn alignment() -> Self::Index {
+        64 * WORDS
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<const WORDS: usize> BitCount for AlignedBitArray<WORDS> {
+    fn count_ones(&self) -> usize {
+        AlignedBitArray::count_ones(self)
+    }
+}
+
+impl<const WORDS: usize> BitSearch for AlignedBitArray<WORDS> {
+    fn first_set_from(&self, index: usize) -> Option<usize> {
+        if index <= self.position {
+            self.first_set()
+        } else {
+            self.next_set_after(index - 1)
+        }
+    }
+}
+
+impl<const WORDS: usize> BitWords for AlignedBitArray<WORDS> {
+    const WORDS: usize = WORDS;
+
+    fn write_words(&self, words: &mut [u64]) {
+        words.copy_from_slice(&self.words);
+    }
+
+    fn from_words(position: usize, words: &[u64]) -> Self {
+        AlignedBitArray::new_from(
+            position,
+            words
+                .try_into()
+                .expect("there should be exactly WORDS words"),
+        )
+    }
+}
+
+impl<const WORDS: usize> BlockFetch for AlignedBitArray<WORDS> {
+    fn fetch(&self, index: Self::Index) -> bool {
+        let (w, bit) = self.word_and_bit(index);
+        (self.words[w] >> bit) & 0x01 != 0
+    }
+}
+
+impl<const WORDS: usize> BlockStore for AlignedBitArray<WORDS> {
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let (w, bit) = self.word_and_bit(index);
+        if item {
+            self.words[w] |= 0x01 << bit;
+        } else {
+            self.words[w] &= !(0x01 << bit);
+        }
+    }
+}
+
+impl<const WORDS: usize> BlockSize for AlignedBitArray<WORDS> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+impl<const WORDS: usize> AlignedBlockFromIterator for AlignedBitArray<WORDS> {
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let mut words = [0; WORDS];
+        for i in 0..Self::alignment() {
+            if iter.next().expect(
+                "iterator should provide at least as many elements as there are bits in the block",
+            ) {
+                words[i / 64] |= 0x01 << (i % 64);
+            }
+        }
+        Self::new_from(position, words)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AlignedBitArray;
+    use crate::block::{AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore};
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    fn block(position: usize, indices: &BTreeSet<usize>) -> AlignedBitArray<4> {
+        AlignedBitArray::from_function(position, |i| indices.contains(&i))
+    }
+
+    #[test]
+    fn test_fetch_store() {
+        assert_eq!(AlignedBitArray::<4>::alignment(), 256);
+
+        let mut b = AlignedBitArray::<4>::new_from(512, [0; 4]);
+        b.store(512, true);
+        b.store(700, true);
+        b.store(767, true);
+        b.store(512, false);
+
+        assert!(!b.fetch(512));
+        assert!(b.fetch(700));
+        assert!(b.fetch(767));
+        assert_eq!(b.words(), &[0, 0, 1 << (700 - 512 - 128), 1 << 63]);
+        assert_eq!(b.count_ones(), 2);
+    }
+
+    #[test]
+    fn test_search() {
+        let b = AlignedBitArray::<2>::new_from(128, [0, 1 << 5 | 1 << 63]);
+        assert_eq!(b.first_set(), Some(197));
+        assert_eq!(b.next_set_after(0), Some(197));
+        assert_eq!(b.next_set_after(197), Some(255));
+        assert_eq!(b.next_set_after(255), None);
+        assert_eq!(b.next_set_after(usize::MAX), None);
+        assert_eq!(AlignedBitArray::<2>::default().first_set(), None);
+    }
+
+    #[test]
+    fn test_bulk() {
+        let mut a = AlignedBitArray::<1>::new_from(64, [0b1100]);
+        let b = AlignedBitArray::<1>::new_from(64, [0b1010]);
+
+        a.xor(&b);
+        assert_eq!(a.words(), &[0b0110]);
+        a.or(&b);
+        assert_eq!(a.words(), &[0b1110]);
+        a.and_not(&b);
+        assert_eq!(a.words(), &[0b0100]);
+        a.and(&b);
+        assert_eq!(a.words(), &[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "blocks must have the same position")]
+    fn test_bulk_position() {
+        let mut a = AlignedBitArray::<1>::new_from(64, [0]);
+        a.or(&AlignedBitArray::new_from(0, [0]));
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_set(
+            indices in proptest::collection::btree_set(1024..1280_usize, 0..40),
+            probe in 1000..1300_usize,
+        ) {
+            let b = block(1024, &indices);
+
+            assert_eq!(b.count_ones(), indices.len());
+            assert_eq!(b.first_set(), indices.first().copied());
+            assert_eq!(b.next_set_after(probe), indices.range(probe + 1..).next().copied());
+            assert_eq!(b.ones().collect::<BTreeSet<_>>(), indices);
+        }
+    }
+}
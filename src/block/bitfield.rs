@@ -1,182 +1,217 @@
+use crate::{numerical_index::NumericalIndex, simd::BitOp};
+
 use super::{
     aligned_block::{AlignedBlock, BlockFetch},
-    AlignedBlockFromIterator, BlockStore, IndexedBlock,
+    AlignedBlockFromIterator, BitCount, BitSearch, BitWords, BlockSize, BlockStore, IndexedBlock,
 };
 
 /// An aligned block of booleans.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct AlignedBitfield<T> {
     position: T,
     bits: T,
 }
 
-impl IndexedBlock for AlignedBitfield<usize> {
-    type Index = usize;
-    type Item = bool;
-}
-
-impl AlignedBlock for AlignedBitfield<usize> {
-    fn alignment() -> Self::Index {
-        8 * std::mem::size_of::<Self::Index>()
-    }
-
-    fn position(&self) -> Self::Index {
-        self.position
-    }
-}
-
-impl IndexedBlock for AlignedBitfield<u64> {
-    type Index = u64;
-    type Item = bool;
-}
-
-impl AlignedBlock for AlignedBitfield<u64> {
-    fn alignment() -> Self::Index {
-        8 * std::mem::size_of::<Self::Index>() as Self::Index
-    }
-
-    fn position(&self) -> Self::Index {
-        self.position
-    }
-}
-
-impl IndexedBlock for AlignedBitfield<u128> {
-    type Index = u128;
-    type Item = bool;
-}
-
-impl AlignedBlock for AlignedBitfield<u128> {
-    fn alignment() -> Self::Index {
-        8 * std::mem::size_of::<Self::Index>() as Self::Index
-    }
-
-    fn position(&self) -> Self::Index {
-        self.position
+impl<T> AlignedBitfield<T>
+where
+    Self: AlignedBlock<Index = T>,
+    T: NumericalIndex,
+{
+    /// Construct a bitfield from its raw bits, where bit `i` is the element at `position + i`.
+    pub fn new_from(position: T, bits: T) -> Self {
+        assert!(
+            position.modulo(Self::alignment()).is_min(),
+            "blocks must be aligned"
+        );
+        AlignedBitfield { position, bits }
     }
-}
 
-impl BlockFetch for AlignedBitfield<usize> {
-    fn fetch(&self, index: Self::Index) -> bool {
-        let index = index - self.position;
-        assert!(index < Self::alignment());
-        (self.bits >> index) & 0x01 != 0
+    /// The raw bits, where bit `i` is the element at `position + i`.
+    pub fn bits(&self) -> T {
+        self.bits
     }
 }
 
-impl BlockFetch for AlignedBitfield<u64> {
-    fn fetch(&self, index: Self::Index) -> bool {
-        let index = index - self.position;
-        assert!(index < Self::alignment());
-        (self.bits >> index) & 0x01 != 0
+impl<T> BlockSize for AlignedBitfield<T> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
     }
 }
 
-impl BlockFetch for AlignedBitfield<u128> {
-    fn fetch(&self, index: Self::Index) -> bool {
-        let index = index - self.position;
-        assert!(index < Self::alignment());
-        (self.bits >> index) & 0x01 != 0
-    }
-}
+/// Bitfields of every integer type, with one element per bit.
+/// Signed bitfields are aligned by offset (see `NumericalIndex`), which for these power of two alignments
+/// means that a block begins at a multiple of the alignment.
+macro_rules! aligned_bitfield {
+    ($($t:ty),*) => {
+        $(
+            impl IndexedBlock for AlignedBitfield<$t> {
+                type Index = $t;
+                type Item = bool;
+            }
 
-impl BlockStore for AlignedBitfield<usize> {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        let index = index - self.position;
-        assert!(index < 8 * std::mem::size_of::<Self::Index>() as Self::Index);
-        if item {
-            self.bits = self.bits | 0x01 << index;
-        } else {
-            self.bits = self.bits & !(0x01 << index);
-        }
-    }
-}
+            impl AlignedBlock for AlignedBitfield<$t> {
+                fn alignment() -> Self::Index {
+                    <$t>::BITS as Self::Index
+                }
 
-impl BlockStore for AlignedBitfield<u64> {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        let index = index - self.position;
-        assert!(index < 8 * std::mem::size_of::<Self::Index>() as Self::Index);
-        if item {
-            self.bits = self.bits | 0x01 << index;
-        } else {
-            self.bits = self.bits & !(0x01 << index);
-        }
-    }
-}
+                fn position(&self) -> Self::Index {
+                    self.position
+                }
+            }
 
-impl BlockStore for AlignedBitfield<u128> {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        let index = index - self.position;
-        assert!(index < 8 * std::mem::size_of::<Self::Index>() as Self::Index);
-        if item {
-            self.bits = self.bits | 0x01 << index;
-        } else {
-            self.bits = self.bits & !(0x01 << index);
-        }
-    }
-}
+            impl AlignedBitfield<$t> {
+                /// Keep only the bits that are also set in `other`.
+                pub fn and(&mut self, other: &Self) {
+                    self.combine(other, BitOp::And);
+                }
+
+                /// Set every bit that is set in `other`.
+                pub fn or(&mut self, other: &Self) {
+                    self.combine(other, BitOp::Or);
+                }
+
+                /// Flip every bit that is set in `other`.
+                pub fn xor(&mut self, other: &Self) {
+                    self.combine(other, BitOp::Xor);
+                }
+
+                /// Clear every bit that is set in `other`.
+                pub fn and_not(&mut self, other: &Self) {
+                    self.combine(other, BitOp::AndNot);
+                }
+
+                fn combine(&mut self, other: &Self, op: BitOp) {
+                    assert_eq!(
+                        self.position, other.position,
+                        "blocks must have the same position"
+                    );
+                    self.bits = match op {
+                        BitOp::And => self.bits & other.bits,
+                        BitOp::Or => self.bits | other.bits,
+                        BitOp::Xor => self.bits ^ other.bits,
+                        BitOp::AndNot => self.bits & !other.bits,
+                    };
+                }
+            }
 
-impl AlignedBlockFromIterator for AlignedBitfield<usize> {
-    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
-    where
-        I: Iterator<Item = Self::Item>,
-    {
-        let mut bits: Self::Index = 0;
-
-        for i in 0..Self::alignment() {
-            if iter.next().expect(
-                "iterator should provide at least as many elements as there are bits in a 'usize'",
-            ) {
-                bits |= 0x01 << i;
+            /// A bitfield of up to 64 elements is one word. A bitfield of 128 elements is two words, low bits first.
+            impl BitWords for AlignedBitfield<$t> {
+                const WORDS: usize = (<$t>::BITS as usize).div_ceil(64);
+
+                fn write_words(&self, words: &mut [u64]) {
+                    // Mask off the sign extension of signed bitfields.
+                    let bits = self.bits as u128 & (u128::MAX >> (128 - <$t>::BITS));
+                    words[0] = bits as u64;
+                    if Self::WORDS == 2 {
+                        words[1] = (bits >> 64) as u64;
+                    }
+                }
+
+                fn from_words(position: Self::Index, words: &[u64]) -> Self {
+                    assert_eq!(words.len(), Self::WORDS, "there should be exactly WORDS words");
+                    let bits = words.iter().rev().fold(0_u128, |bits, w| bits << 64 | *w as u128);
+                    AlignedBitfield::new_from(position, bits as $t)
+                }
             }
-        }
 
-        AlignedBitfield { position, bits }
-    }
-}
+            impl BitCount for AlignedBitfield<$t> {
+                fn count_ones(&self) -> usize {
+                    self.bits.count_ones() as usize
+                }
+            }
 
-impl AlignedBlockFromIterator for AlignedBitfield<u64> {
-    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
-    where
-        I: Iterator<Item = Self::Item>,
-    {
-        let mut bits: Self::Index = 0;
-
-        for i in 0..Self::alignment() {
-            if iter.next().expect(
-                "iterator should provide at least as many elements as there are bits in a 'u64'",
-            ) {
-                bits |= 0x01 << i;
+            impl BitSearch for AlignedBitfield<$t> {
+                fn first_set_from(&self, index: Self::Index) -> Option<Self::Index> {
+                    if index > self.position.last_in_block(Self::alignment()) {
+                        return None;
+                    }
+                    let offset = index.max(self.position) - self.position;
+                    let bits = self.bits & (!0 << offset);
+                    (bits != 0).then(|| self.position + bits.trailing_zeros() as Self::Index)
+                }
             }
-        }
 
-        AlignedBitfield { position, bits }
-    }
-}
+            impl BlockFetch for AlignedBitfield<$t> {
+                fn fetch(&self, index: Self::Index) -> bool {
+                    let index = index - self.position;
+                    assert!((0..Self::alignment()).contains(&index));
+                    (self.bits >> index) & 0x01 != 0
+                }
+            }
 
-impl AlignedBlockFromIterator for AlignedBitfield<u128> {
-    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
-    where
-        I: Iterator<Item = Self::Item>,
-    {
-        let mut bits: Self::Index = 0;
-
-        for i in 0..Self::alignment() {
-            if iter.next().expect(
-                "iterator should provide at least as many elements as there are bits in a 'u128'",
-            ) {
-                bits |= 0x01 << i;
+            impl BlockStore for AlignedBitfield<$t> {
+                fn store(&mut self, index: Self::Index, item: Self::Item) {
+                    let index = index - self.position;
+                    assert!((0..Self::alignment()).contains(&index));
+                    if item {
+                        self.bits = self.bits | 0x01 << index;
+                    } else {
+                        self.bits = self.bits & !(0x01 << index);
+                    }
+                }
             }
-        }
 
-        AlignedBitfield { position, bits }
-    }
+            impl AlignedBlockFromIterator for AlignedBitfield<$t> {
+                fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+                where
+                    I: Iterator<Item = Self::Item>,
+                {
+                    let mut bits: Self::Index = 0;
+
+                    for i in 0..Self::alignment() {
+                        if iter.next().expect(concat!(
+                            "iterator should provide at least as many elements as there are bits in a '",
+                            stringify!($t),
+                            "'"
+                        )) {
+                            bits |= 0x01 << i;
+                        }
+                    }
+
+                    AlignedBitfield { position, bits }
+                }
+            }
+        )*
+    };
 }
 
+aligned_bitfield!(usize, u64, u128, u32, u16, u8, isize, i64, i128, i32, i16, i8);
+
 #[cfg(test)]
 mod test {
-    use crate::block::{AlignedBlock, BlockFetch, BlockStore};
+    use crate::block::{AlignedBlock, BitSearch, BitWords, BlockFetch, BlockStore};
 
     use super::AlignedBitfield;
 
+    #[test]
+    fn test_words() {
+        let b = AlignedBitfield::<i8>::new_from(-8, -2);
+        let mut words = [0];
+        b.write_words(&mut words);
+        assert_eq!(words, [0xfe]);
+        assert_eq!(AlignedBitfield::<i8>::from_words(-8, &words), b);
+
+        let b = AlignedBitfield::<u128>::new_from(128, 1 << 100 | 1);
+        let mut words = [0; 2];
+        b.write_words(&mut words);
+        assert_eq!(words, [1, 1 << 36]);
+        assert_eq!(AlignedBitfield::<u128>::from_words(128, &words), b);
+    }
+
+    #[test]
+    fn test_bulk_ops() {
+        let mut a = AlignedBitfield::<u16>::new_from(16, 0b1100);
+        let b = AlignedBitfield::<u16>::new_from(16, 0b1010);
+        a.xor(&b);
+        assert_eq!(a.bits(), 0b0110);
+        a.or(&b);
+        assert_eq!(a.bits(), 0b1110);
+        a.and_not(&b);
+        assert_eq!(a.bits(), 0b0100);
+        a.and(&b);
+        assert_eq!(a.bits(), 0);
+    }
+
     #[test]
     fn test_bitfield_usize() {
         assert_eq!(
@@ -244,6 +279,49 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bitfield_small() {
+        assert_eq!(AlignedBitfield::<u32>::alignment(), 32);
+        assert_eq!(AlignedBitfield::<u8>::alignment(), 8);
+
+        let mut b = AlignedBitfield::<u8>::new_from(248, 0);
+        b.store(255, true);
+        b.store(248, true);
+        assert_eq!(b.bits(), 0x81);
+        assert_eq!(b.fetch(255), true);
+    }
+
+    #[test]
+    fn test_bitfield_search() {
+        let b = AlignedBitfield::<i8>::new_from(-8, 0b1000_0010_u8 as i8);
+        assert_eq!(b.first_set_from(i8::MIN), Some(-7));
+        assert_eq!(b.first_set_from(-6), Some(-1));
+        assert_eq!(b.first_set_from(-1), Some(-1));
+        assert_eq!(b.first_set_from(0), None);
+    }
+
+    #[test]
+    fn test_bitfield_signed() {
+        assert_eq!(AlignedBitfield::<i16>::alignment(), 16);
+
+        let mut b = AlignedBitfield::<i16>::new_from(-16, 0);
+        b.store(-1, true);
+        b.store(-16, true);
+        assert_eq!(b.fetch(-1), true);
+        assert_eq!(b.fetch(-2), false);
+        assert_eq!(b.bits(), i16::MIN | 1);
+
+        let b = AlignedBitfield::<i8>::new_from(i8::MIN, -1);
+        assert_eq!(b.fetch(i8::MIN), true);
+        assert_eq!(b.fetch(i8::MIN + 7), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "blocks must be aligned")]
+    fn test_bitfield_signed_unaligned() {
+        AlignedBitfield::<i32>::new_from(-20, 0);
+    }
+
     #[test]
     fn test_bitfield_u64_set() {
         assert_eq!(AlignedBitfield::<usize>::alignment(), 64);
@@ -0,0 +1,447 @@
+use std::{cell::RefCell, collections::BTreeMap, io};
+
+use crate::numerical_index::NumericalIndex;
+
+use super::{
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore, DefaultPerIndex, IndexedBlock,
+    SparseVec,
+};
+
+/// Somewhere that blocks can be loaded from and saved to, such as a file or a collection of compressed blocks.
+pub trait BlockSource {
+    /// Type of the blocks. Their size is counted against the budget of a `BlockCache`.
+    type Block: AlignedBlock + BlockSize;
+
+    /// Load the block at the given position, or `None` if there is no block there.
+    fn load(
+        &mut self,
+        position: <Self::Block as IndexedBlock>::Index,
+    ) -> io::Result<Option<Self::Block>>;
+
+    /// Save a block, replacing any block at the same position.
+    fn save(&mut self, block: &Self::Block) -> io::Result<()>;
+}
+
+/// A block that knows how much memory it occupies, including whatever it owns on the heap.
+pub trait BlockSize {
+    /// The number of bytes of memory that this block occupies.
+    fn size_in_bytes(&self) -> usize;
+}
+
+/// A SparseVec can serve as an in-memory source of blocks.
+impl<T, D> BlockSource for SparseVec<T, D>
+where
+    T: AlignedBlock + BlockSize + Clone,
+    T::Index: NumericalIndex,
+    D: DefaultPerIndex<T::Index, T::Item>,
+{
+    type Block = T;
+
+    fn load(&mut self, position: T::Index) -> io::Result<Option<T>> {
+        Ok(self.block_containing(position).cloned())
+    }
+
+    fn save(&mut self, block: &T) -> io::Result<()> {
+        self.insert_block(block.clone());
+        Ok(())
+    }
+}
+
+/// Counters describing how well a `BlockCache` is working.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStatistics {
+    /// Accesses to a block that was already loaded.
+    pub hits: u64,
+    /// Accesses to a block that had to be loaded from the source (or found to be absent).
+    pub misses: u64,
+    /// Blocks that were dropped from the cache to stay within the budget.
+    pub evictions: u64,
+    /// Modified blocks that were saved back to the source.
+    pub write_backs: u64,
+}
+
+struct CacheEntry<B> {
+    block: B,
+    dirty: bool,
+    referenced: bool,
+}
+
+struct CacheState<S>
+where
+    S: BlockSource,
+{
+    source: S,
+    entries: Vec<Option<CacheEntry<S::Block>>>,
+    /// Entries that are `None`, and can be reused.
+    free: Vec<usize>,
+    /// Which entry holds the block at each position.
+    slots: BTreeMap<<S::Block as IndexedBlock>::Index, usize>,
+    /// Where the CLOCK hand is pointing.
+    hand: usize,
+    bytes: usize,
+    statistics: CacheStatistics,
+}
+
+/// A bounded cache of loaded blocks, in front of a `BlockSource`.
+///
+/// Blocks are loaded on first access and kept until the total size of the loaded blocks exceeds the byte budget.
+/// Then blocks are evicted by the CLOCK algorithm: each access marks a block as referenced, and the clock hand sweeps
+/// the cache, clearing references until it finds an unreferenced block to evict. Modified blocks are saved back to
+/// the source when they are evicted, or by `flush`. The most recently accessed block is never evicted, even if it
+/// alone exceeds the budget.
+///
+/// Indices in absent blocks (ones that the source does not have) hold default values, as in a `SparseVec`.
+/// Storing into an absent block creates it.
+///
+/// `BlockFetch` and `BlockStore` panic if the source fails. Use `try_fetch` and `try_store` to handle errors instead.
+pub struct BlockCache<S, D>
+where
+    S: BlockSource,
+{
+    state: RefCell<CacheState<S>>,
+    budget: usize,
+    default_value: D,
+}
+
+impl<S, D> BlockCache<S, D>
+where
+    S: BlockSource,
+    S::Block: BlockFetch + BlockStore + AlignedBlockFromIterator,
+    <S::Block as IndexedBlock>::Index: NumericalIndex,
+    D: DefaultPerIndex<<S::Block as IndexedBlock>::Index, <S::Block as IndexedBlock>::Item>,
+{
+    /// Construct a cache that holds at most `budget` bytes of blocks.
+    pub fn new(source: S, budget: usize, default_value: D) -> Self {
+        BlockCache {
+            state: RefCell::new(CacheState {
+                source,
+                entries: vec![],
+                free: vec![],
+                slots: BTreeMap::new(),
+                hand: 0,
+                bytes: 0,
+                statistics: CacheStatistics::default(),
+            }),
+            budget,
+            default_value,
+        }
+    }
+
+    /// Counters of hits, misses, evictions and write-backs so far.
+    pub fn statistics(&self) -> CacheStatistics {
+        self.state.borrow().statistics
+    }
+
+    /// The number of blocks that are currently loaded.
+    pub fn loaded_blocks(&self) -> usize {
+        self.state.borrow().slots.len()
+    }
+
+    /// The total size of the blocks that are currently loaded.
+    pub fn loaded_bytes(&self) -> usize {
+        self.state.borrow().bytes
+    }
+
+    /// Fetch an element, loading its block if necessary.
+    pub fn try_fetch(
+        &self,
+        index: <S::Block as IndexedBlock>::Index,
+    ) -> io::Result<<S::Block as IndexedBlock>::Item> {
+        let mut state = self.state.borrow_mut();
+        let position = index.block(S::Block::alignment());
+        match Self::find_or_load(&mut state, self.budget, position)? {
+            Some(slot) => Ok(state.entries[slot].as_ref().unwrap().block.fetch(index)),
+            None => Ok(self.default_value.default_at_index(index)),
+        }
+    }
+
+    /// Store an element, loading (or creating) its block if necessary.
+    pub fn try_store(
+        &mut self,
+        index: <S::Block as IndexedBlock>::Index,
+        item: <S::Block as IndexedBlock>::Item,
+    ) -> io::Result<()> {
+        let state = self.state.get_mut();
+        let position = index.block(S::Block::alignment());
+        let slot = match Self::find_or_load(state, self.budget, position)? {
+            Some(slot) => slot,
+            None => {
+                let block =
+                    S::Block::from_function(position, |i| self.default_value.default_at_index(i));
+                Self::insert(state, self.budget, block)?
+            }
+        };
+        let entry = state.entries[slot].as_mut().unwrap();
+        entry.block.store(index, item);
+        entry.dirty = true;
+        Ok(())
+    }
+
+    /// Save every modified block back to the source. Blocks stay loaded.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let state = self.state.get_mut();
+        for entry in state.entries.iter_mut().flatten() {
+            if entry.dirty {
+                state.source.save(&entry.block)?;
+                state.statistics.write_backs += 1;
+                entry.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save every modified block, and unwrap the source.
+    pub fn into_source(mut self) -> io::Result<S> {
+        self.flush()?;
+        Ok(self.state.into_inner().source)
+    }
+
+    /// Find the slot of the block at the given position, loading it if needed. `None` if the source has no such block.
+    fn find_or_load(
+        state: &mut CacheState<S>,
+        budget: usize,
+        position: <S::Block as IndexedBlock>::Index,
+    ) -> io::Result<Option<usize>> {
+        if let Some(slot) = state.slots.get(&position).copied() {
+            state.statistics.hits += 1;
+            state.entries[slot].as_mut().unwrap().referenced = true;
+            return Ok(Some(slot));
+        }
+
+        state.statistics.misses += 1;
+        match state.source.load(position)? {
+            Some(block) => Ok(Some(Self::insert(state, budget, block)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Add a newly loaded or created block, evicting other blocks to stay within the budget.
+    fn insert(state: &mut CacheState<S>, budget: usize, block: S::Block) -> io::Result<usize> {
+        let size = block.size_in_bytes();
+        while state.bytes + size > budget && !state.slots.is_empty() {
+            Self::evict_one(state)?;
+        }
+
+        let position = block.position();
+        let entry = CacheEntry {
+            block,
+            dirty: false,
+            referenced: true,
+        };
+        let slot = match state.free.pop() {
+            Some(slot) => {
+                state.entries[slot] = Some(entry);
+                slot
+            }
+            None => {
+                state.entries.push(Some(entry));
+                state.entries.len() - 1
+            }
+        };
+        state.slots.insert(position, slot);
+        state.bytes += size;
+        Ok(slot)
+    }
+
+    /// Advance the clock hand to an unreferenced block, and evict it.
+    fn evict_one(state: &mut CacheState<S>) -> io::Result<()> {
+        loop {
+            state.hand = (state.hand + 1) % state.entries.len();
+            let Some(entry) = state.entries[state.hand].as_mut() else {
+                continue;
+            };
+            if entry.referenced {
+                entry.referenced = false;
+                continue;
+            }
+
+            // Save the block before removing it, so that if the save fails, the block stays loaded and dirty.
+            if entry.dirty {
+                state.source.save(&entry.block)?;
+                state.statistics.write_backs += 1;
+            }
+            let entry = state.entries[state.hand].take().unwrap();
+            state.slots.remove(&entry.block.position());
+            state.free.push(state.hand);
+            state.bytes -= entry.block.size_in_bytes();
+            state.statistics.evictions += 1;
+            return Ok(());
+        }
+    }
+}
+
+impl<S, D> IndexedBlock for BlockCache<S, D>
+where
+    S: BlockSource,
+{
+    type Index = <S::Block as IndexedBlock>::Index;
+    type Item = <S::Block as IndexedBlock>::Item;
+}
+
+impl<S, D> BlockFetch for BlockCache<S, D>
+where
+    S: BlockSource,
+    S::Block: BlockFetch + BlockStore + AlignedBlockFromIterator,
+    <S::Block as IndexedBlock>::Index: NumericalIndex,
+    D: DefaultPerIndex<<S::Block as IndexedBlock>::Index, <S::Block as IndexedBlock>::Item>,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.try_fetch(index)
+            .expect("block source should be able to load blocks")
+    }
+}
+
+impl<S, D> BlockStore for BlockCache<S, D>
+where
+    S: BlockSource,
+    S::Block: BlockFetch + BlockStore + AlignedBlockFromIterator,
+    <S::Block as IndexedBlock>::Index: NumericalIndex,
+    D: DefaultPerIndex<<S::Block as IndexedBlock>::Index, <S::Block as IndexedBlock>::Item>,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        self.try_store(index, item)
+            .expect("block source should be able to load and save blocks")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BlockCache, BlockSource, CacheStatistics};
+    use crate::block::{AlignedVec, BlockFetch, BlockStore, DefaultValue, SparseVec};
+    use std::io;
+
+    type Block = AlignedVec<u32, 4>;
+    const BLOCK_BYTES: usize = std::mem::size_of::<Block>() + 4 * std::mem::size_of::<u32>();
+
+    fn source() -> SparseVec<Block, DefaultValue> {
+        let mut source = SparseVec::default();
+        for i in 0..40 {
+            source.store(i, i as u32);
+        }
+        source
+    }
+
+    /// Fails to save any block, after the first `saves` succeed.
+    struct FailingSource {
+        blocks: SparseVec<Block, DefaultValue>,
+        saves: usize,
+    }
+
+    impl BlockSource for FailingSource {
+        type Block = Block;
+
+        fn load(&mut self, position: usize) -> io::Result<Option<Block>> {
+            self.blocks.load(position)
+        }
+
+        fn save(&mut self, block: &Block) -> io::Result<()> {
+            if self.saves == 0 {
+                return Err(io::Error::other("disk full"));
+            }
+            self.saves -= 1;
+            self.blocks.save(block)
+        }
+    }
+
+    #[test]
+    fn test_failed_write_back() {
+        let source = FailingSource {
+            blocks: source(),
+            saves: 0,
+        };
+        let mut cache = BlockCache::new(source, BLOCK_BYTES, DefaultValue);
+        cache.store(0, 100);
+        assert!(cache.try_store(4, 104).is_err());
+
+        // The dirty block is still loaded, so nothing is lost and the next access still works.
+        assert_eq!(cache.fetch(0), 100);
+        cache.state.get_mut().source.saves = 1;
+        cache.store(4, 104);
+        assert_eq!(cache.statistics().write_backs, 1);
+        assert_eq!(cache.fetch(4), 104);
+        assert_eq!(cache.state.get_mut().source.blocks.fetch(0), 100);
+    }
+
+    #[test]
+    fn test_hits_and_misses() {
+        let cache = BlockCache::new(source(), 2 * BLOCK_BYTES, DefaultValue);
+
+        assert_eq!(cache.fetch(0), 0);
+        assert_eq!(cache.fetch(1), 1);
+        assert_eq!(cache.fetch(5), 5);
+        assert_eq!(cache.fetch(2), 2);
+        assert_eq!(cache.fetch(1000), 0);
+        assert_eq!(
+            cache.statistics(),
+            CacheStatistics {
+                hits: 2,
+                misses: 3,
+                evictions: 0,
+                write_backs: 0,
+            }
+        );
+        assert_eq!(cache.loaded_blocks(), 2);
+
+        assert_eq!(cache.fetch(9), 9);
+        assert_eq!(cache.statistics().evictions, 1);
+        assert_eq!(cache.loaded_blocks(), 2);
+        assert_eq!(cache.loaded_bytes(), 2 * BLOCK_BYTES);
+    }
+
+    #[test]
+    fn test_write_back() {
+        let mut cache = BlockCache::new(source(), 2 * BLOCK_BYTES, DefaultValue);
+
+        cache.store(0, 100);
+        cache.store(5, 105);
+        cache.store(1000, 1100);
+        assert_eq!(cache.statistics().write_backs, 1);
+        assert_eq!(cache.fetch(0), 100);
+        assert_eq!(cache.fetch(1000), 1100);
+
+        for i in 0..40 {
+            let expected = match i {
+                0 => 100,
+                5 => 105,
+                _ => i as u32,
+            };
+            assert_eq!(cache.fetch(i), expected);
+        }
+        assert_eq!(cache.loaded_blocks(), 2);
+
+        let source = cache.into_source().unwrap();
+        assert_eq!(source.fetch(0), 100);
+        assert_eq!(source.fetch(5), 105);
+        assert_eq!(source.fetch(1000), 1100);
+        assert_eq!(source.fetch(6), 6);
+    }
+
+    #[test]
+    fn test_budget_counts_heap_bytes() {
+        let mut source: SparseVec<AlignedVec<u64, 512>, DefaultValue> = SparseVec::default();
+        for b in 0..20 {
+            source.store(b * 512, 1);
+        }
+        // Each block holds 4 KiB of elements on the heap, so the budget has room for 9 blocks, not 10.
+        let cache = BlockCache::new(source, 10 * 512 * 8, DefaultValue);
+        for b in 0..20 {
+            assert_eq!(cache.fetch(b * 512), 1);
+        }
+        assert_eq!(cache.loaded_blocks(), 9);
+        assert!(cache.loaded_bytes() <= 10 * 512 * 8);
+    }
+
+    #[test]
+    fn test_clock_keeps_referenced_blocks() {
+        let cache = BlockCache::new(source(), 3 * BLOCK_BYTES, DefaultValue);
+
+        // Block 0 is referenced again before each new block is loaded, so it survives the sweep.
+        for b in 1..10 {
+            cache.fetch(0);
+            cache.fetch(b * 4);
+        }
+        let before = cache.statistics().misses;
+        cache.fetch(0);
+        assert_eq!(cache.statistics().misses, before);
+    }
+}
@@ -0,0 +1,310 @@
+use std::ops::{Index, IndexMut};
+
+use super::{
+    AlignedVec, BlockCollection, BlockFetch, BlockGet, BlockGetMut, BlockStore, DenseVec,
+    IndexedBlock,
+};
+
+/// A growable vector of individual elements, stored as a `DenseVec` of `AlignedVec` blocks.
+///
+/// Full blocks are kept in the DenseVec, and the elements pushed since the last full block are kept in a
+/// partially-filled tail. Growing never reallocates and copies the elements that are already stored,
+/// only the list of blocks.
+pub struct BlockVec<T, const N: usize> {
+    blocks: DenseVec<AlignedVec<T, N>>,
+    tail: Vec<T>,
+}
+
+impl<T, const N: usize> Default for BlockVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> BlockVec<T, N> {
+    /// Construct a new, empty BlockVec.
+    pub fn new() -> Self {
+        BlockVec {
+            blocks: DenseVec::new_from(vec![]),
+            tail: Vec::with_capacity(N),
+        }
+    }
+
+    /// Construct a BlockVec from full blocks.
+    pub fn new_from(blocks: DenseVec<AlignedVec<T, N>>) -> Self {
+        BlockVec {
+            blocks,
+            tail: Vec::with_capacity(N),
+        }
+    }
+
+    /// Unwrap into a DenseVec of the full blocks, and the elements of the partially-filled tail.
+    pub fn into_dense_vec(self) -> (DenseVec<AlignedVec<T, N>>, Vec<T>) {
+        (self.blocks, self.tail)
+    }
+
+    /// The full blocks of this BlockVec. Elements in the tail are not included.
+    pub fn blocks(&self) -> &DenseVec<AlignedVec<T, N>> {
+        &self.blocks
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.tail_position() + self.tail.len()
+    }
+
+    /// True if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tail_position(&self) -> usize {
+        self.blocks.blocks().len() * N
+    }
+
+    /// Append an element.
+    pub fn push(&mut self, item: T) {
+        self.tail.push(item);
+        if self.tail.len() == N {
+            let position = self.tail_position();
+            let full = std::mem::replace(&mut self.tail, Vec::with_capacity(N));
+            let blocks = std::mem::replace(&mut self.blocks, DenseVec::new_from(vec![]));
+            self.blocks = blocks.push_block(AlignedVec::new_from(position, full));
+        }
+    }
+
+    /// Remove the last element and return it, or `None` if empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.tail.is_empty() && !self.pop_block_into_tail() {
+            return None;
+        }
+        self.tail.pop()
+    }
+
+    /// Move the last full block into the (empty) tail. Returns false if there are no full blocks.
+    fn pop_block_into_tail(&mut self) -> bool {
+        debug_assert!(self.tail.is_empty());
+        let blocks = std::mem::replace(&mut self.blocks, DenseVec::new_from(vec![]));
+        let (blocks, last) = blocks.pop_block();
+        self.blocks = blocks;
+        match last {
+            Some(last) => {
+                self.tail = last.into_vec();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shorten to the given length, dropping the rest of the elements. Has no effect if already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        while self.tail_position() > len {
+            self.tail.clear();
+            self.pop_block_into_tail();
+        }
+        self.tail.truncate(len - self.tail_position());
+    }
+
+    /// Resize to the given length, either truncating or pushing clones of `value`.
+    pub fn resize(&mut self, len: usize, value: T)
+    where
+        T: Clone,
+    {
+        self.truncate(len);
+        while self.len() < len {
+            self.push(value.clone());
+        }
+    }
+
+    /// Iterate over every element.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.blocks
+            .blocks()
+            .iter()
+            .flat_map(|b| b.iter())
+            .chain(self.tail.iter())
+    }
+}
+
+impl<T, const N: usize> Extend<T> for BlockVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for BlockVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = BlockVec::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T, const N: usize> IndexedBlock for BlockVec<T, N> {
+    type Index = usize;
+    type Item = T;
+}
+
+/// Returns `None` for indices beyond the end of the BlockVec.
+impl<T, const N: usize> BlockGet for BlockVec<T, N> {
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        let tail_position = self.tail_position();
+        if index < tail_position {
+            self.blocks.get(index)
+        } else {
+            self.tail.get(index - tail_position)
+        }
+    }
+}
+
+impl<T, const N: usize> BlockGetMut for BlockVec<T, N> {
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        let tail_position = self.tail_position();
+        if index < tail_position {
+            self.blocks.get_mut(index)
+        } else {
+            self.tail.get_mut(index - tail_position)
+        }
+    }
+}
+
+impl<T, const N: usize> BlockFetch for BlockVec<T, N>
+where
+    T: Copy,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self[index]
+    }
+}
+
+impl<T, const N: usize> BlockStore for BlockVec<T, N> {
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        self[index] = item;
+    }
+}
+
+impl<T, const N: usize> Index<usize> for BlockVec<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for BlockVec<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockVec;
+    use crate::block::{BlockCollection, BlockFetch};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_push_pop() {
+        let mut v: BlockVec<u32, 4> = BlockVec::new();
+        v.extend(0..10);
+
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.blocks().blocks().len(), 2);
+        assert_eq!(v[3], 3);
+        assert_eq!(v.fetch(9), 9);
+
+        v[9] = 90;
+        v[2] = 20;
+        assert_eq!(v.pop(), Some(90));
+        assert_eq!(v.pop(), Some(8));
+        assert_eq!(v.pop(), Some(7));
+        assert_eq!(v.blocks().blocks().len(), 1);
+        assert_eq!(
+            v.iter().copied().collect::<Vec<_>>(),
+            vec![0, 1, 20, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_truncate_resize() {
+        let mut v: BlockVec<String, 3> = ["a", "b", "c", "d", "e", "f", "g"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        v.truncate(2);
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.blocks().blocks().len(), 0);
+        assert_eq!(v[1], "b");
+
+        v.resize(5, "z".to_string());
+        assert_eq!(
+            v.iter().cloned().collect::<Vec<_>>(),
+            vec!["a", "b", "z", "z", "z"]
+        );
+
+        v.truncate(10);
+        assert_eq!(v.len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_out_of_bounds() {
+        let v: BlockVec<u8, 4> = (0..5).collect();
+        let _ = v[5];
+    }
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Push(u16),
+        Pop,
+        Truncate(usize),
+        Resize(usize, u16),
+        Set(usize, u16),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            any::<u16>().prop_map(Op::Push),
+            Just(Op::Pop),
+            (0..40_usize).prop_map(Op::Truncate),
+            (0..40_usize, any::<u16>()).prop_map(|(n, v)| Op::Resize(n, v)),
+            (any::<usize>(), any::<u16>()).prop_map(|(i, v)| Op::Set(i, v)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_vec(ops in proptest::collection::vec(op(), 0..100)) {
+            let mut expected = vec![];
+            let mut v: BlockVec<u16, 4> = BlockVec::new();
+            for op in ops {
+                match op {
+                    Op::Push(x) => {
+                        expected.push(x);
+                        v.push(x);
+                    }
+                    Op::Pop => assert_eq!(v.pop(), expected.pop()),
+                    Op::Truncate(n) => {
+                        expected.truncate(n);
+                        v.truncate(n);
+                    }
+                    Op::Resize(n, x) => {
+                        expected.resize(n, x);
+                        v.resize(n, x);
+                    }
+                    Op::Set(i, x) if !expected.is_empty() => {
+                        let i = i % expected.len();
+                        expected[i] = x;
+                        v[i] = x;
+                    }
+                    Op::Set(..) => {}
+                }
+                assert_eq!(v.len(), expected.len());
+            }
+            assert_eq!(v.iter().copied().collect::<Vec<_>>(), expected);
+        }
+    }
+}
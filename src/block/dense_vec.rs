@@ -1,5 +1,8 @@
+use crate::sort::RadixSortable;
+
 use super::{
-    AlignedBlock, BlockFetch, BlockFetchIterator, BlockStore, DefaultPerIndex, IndexedBlock,
+    AlignedBlock, BlockCollection, BlockEnumerate, BlockFetch, BlockFetchIterator, BlockGet,
+    BlockGetMut, BlockStore, DefaultPerIndex, IndexedBlock,
 };
 
 /// A vector of items that are themselves AlignedBlocks.
@@ -43,9 +46,15 @@ where
     }
 
     /// Push an entire block onto the end of this DenseVec.
+    /// Only the new block is validated, so this is a fast operation.
     pub fn push_block(mut self, t: T) -> Self {
+        assert_eq!(
+            self.vec.len() * T::alignment(),
+            t.position(),
+            "blocks must be densely packed and aligned"
+        );
         self.vec.push(t);
-        self.assert_well_formed()
+        self
     }
 
     /// Pop an entire block off of the end of this DenseVec.
@@ -91,6 +100,45 @@ where
     }
 }
 
+impl<T> BlockGet for DenseVec<T>
+where
+    T: AlignedBlock<Index = usize> + BlockGet,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.vec.get(self.index_of(index))?.get(index)
+    }
+}
+
+impl<T> BlockGetMut for DenseVec<T>
+where
+    T: AlignedBlock<Index = usize> + BlockGetMut,
+{
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        let big = self.index_of(index);
+        self.vec.get_mut(big)?.get_mut(index)
+    }
+}
+
+impl<T> BlockCollection for DenseVec<T>
+where
+    T: AlignedBlock<Index = usize>,
+{
+    type Block = T;
+
+    fn blocks(&self) -> &[T] {
+        &self.vec
+    }
+}
+
+impl<T> BlockEnumerate for DenseVec<T>
+where
+    T: AlignedBlock<Index = usize> + BlockFetch,
+{
+    fn enumerate_items(&self) -> impl Iterator<Item = (Self::Index, Self::Item)> + '_ {
+        self.iter().enumerate()
+    }
+}
+
 impl<T> DefaultPerIndex<T::Index, Option<T::Item>> for DenseVec<T>
 where
     T: AlignedBlock<Index = usize> + BlockFetch,
@@ -104,10 +152,51 @@ where
     }
 }
 
+/// Radix sorting a DenseVec sorts across all of its blocks, in place, without copying the blocks into a single buffer.
+impl<T> RadixSortable for DenseVec<T>
+where
+    T: AlignedBlock<Index = usize> + RadixSortable,
+{
+    type Item = <T as RadixSortable>::Item;
+
+    fn radix_len(&self) -> usize {
+        self.vec.len() * T::alignment()
+    }
+
+    fn radix_get(&self, i: usize) -> &Self::Item {
+        self.vec[self.index_of(i)].radix_get(i % T::alignment())
+    }
+
+    fn radix_get_mut(&mut self, i: usize) -> &mut Self::Item {
+        let big = self.index_of(i);
+        self.vec[big].radix_get_mut(i % T::alignment())
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        let (big_i, big_j) = (self.index_of(i), self.index_of(j));
+        let (small_i, small_j) = (i % T::alignment(), j % T::alignment());
+
+        if big_i == big_j {
+            self.vec[big_i].swap(small_i, small_j);
+        } else {
+            let (lo, hi) = (big_i.min(big_j), big_i.max(big_j));
+            let (left, right) = self.vec.split_at_mut(hi);
+            let (a, b) = (&mut left[lo], &mut right[0]);
+            if big_i < big_j {
+                std::mem::swap(a.radix_get_mut(small_i), b.radix_get_mut(small_j));
+            } else {
+                std::mem::swap(a.radix_get_mut(small_j), b.radix_get_mut(small_i));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::DenseVec;
-    use crate::block::{AlignedVec, BlockFetch, BlockStore};
+    use crate::block::{AlignedVec, BlockFetch, BlockGet, BlockGetMut, BlockStore};
+    use crate::sort::RadixSortable;
+    use proptest::prelude::*;
 
     #[test]
     pub fn test_from_into_vec() {
@@ -181,4 +270,55 @@ mod test {
         assert_eq!(v.fetch(31), 1031);
         assert_eq!(v.fetch(16), 1016);
     }
+
+    #[test]
+    pub fn test_radix_sort() {
+        let v: DenseVec<AlignedVec<u64, 4>> = DenseVec::new_from(vec![]);
+        let v = v.push_block(AlignedVec::new_from(0, vec![9, 2, 11, 0]));
+        let v = v.push_block(AlignedVec::new_from(4, vec![5, 7, 1, 3]));
+        let mut v = v.push_block(AlignedVec::new_from(8, vec![10, 4, 8, 6]));
+
+        v.radix_sort();
+
+        assert_eq!(
+            v.iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+        );
+    }
+
+    #[test]
+    pub fn test_get_strings() {
+        let mut v: DenseVec<(usize, String)> = DenseVec::new_from(vec![
+            (0, "a".to_string()),
+            (1, "b".to_string()),
+            (2, "c".to_string()),
+        ]);
+        v.get_mut(1).unwrap().push('!');
+
+        assert_eq!(v.get(1).map(String::as_str), Some("b!"));
+        assert_eq!(v.get(3), None);
+        assert!(v.get_mut(3).is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn test_radix_sort_by_key(original in proptest::collection::vec(any::<(u32, i8)>(), 0..64)) {
+            let blocks: Vec<AlignedVec<(u32, i8), 8>> = original
+                .chunks_exact(8)
+                .enumerate()
+                .map(|(i, chunk)| AlignedVec::new_from(i * 8, chunk.to_vec()))
+                .collect();
+            let mut expected: Vec<_> = blocks.iter().flat_map(|b| b.iter().copied()).collect();
+            expected.sort_by_key(|x| x.0);
+
+            let mut v = DenseVec::new_from(blocks);
+            v.radix_sort_by_key(|x| x.0);
+            let result: Vec<_> = v.iter().collect();
+
+            assert_eq!(
+                result.iter().map(|x| x.0).collect::<Vec<_>>(),
+                expected.iter().map(|x| x.0).collect::<Vec<_>>()
+            );
+        }
+    }
 }
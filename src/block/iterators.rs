@@ -1,6 +1,6 @@
 use std::ops::Index;
 
-use crate::numerical_index::NumericalIndex;
+use crate::{merge::SkipTo, numerical_index::NumericalIndex};
 
 use super::{AlignedBlock, BlockFetch};
 
@@ -9,8 +9,8 @@ pub struct BlockIndexIterator<B>
 where
     B: AlignedBlock,
 {
-    start_index: B::Index,
-    next_index: B::Index,
+    next_index: Option<B::Index>,
+    last_index: B::Index,
 }
 
 impl<B> Iterator for BlockIndexIterator<B>
@@ -21,25 +21,26 @@ where
     type Item = B::Index;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.next_index.block(B::alignment()) == self.start_index.block(B::alignment()) {
-            let i = self.next_index;
-            self.next_index = self.next_index.next();
-            Some(i)
-        } else {
+        let i = self.next_index?;
+        self.next_index = if i == self.last_index {
             None
-        }
+        } else {
+            i.checked_next()
+        };
+        Some(i)
     }
 }
 
 impl<B> BlockIndexIterator<B>
 where
     B: AlignedBlock,
+    B::Index: NumericalIndex,
 {
     /// Make a new BlockIterator for a block
     pub fn new<'a>(block: &'a B) -> Self {
         BlockIndexIterator {
-            start_index: block.position(),
-            next_index: block.position(),
+            next_index: Some(block.position()),
+            last_index: block.position().last_in_block(B::alignment()),
         }
     }
 }
@@ -68,6 +69,7 @@ where
 impl<'b, B> BlockFetchIterator<'b, B>
 where
     B: AlignedBlock,
+    B::Index: NumericalIndex,
 {
     /// Make a new BlockIterator for a block
     pub fn new(block: &'b B) -> Self {
@@ -102,6 +104,7 @@ where
 impl<'b, B> BlockRefIterator<'b, B>
 where
     B: AlignedBlock + Index<B::Index, Output = B::Item>,
+    B::Index: NumericalIndex,
 {
     /// Make a new BlockIterator for a block
     pub fn new(block: &'b B) -> Self {
@@ -111,3 +114,111 @@ where
         }
     }
 }
+
+/// A position within the indices of a sorted slice of blocks, which can skip ahead by binary searching the blocks.
+pub(crate) struct BlockCursor<'a, B>
+where
+    B: AlignedBlock,
+{
+    blocks: &'a [B],
+    block: usize,
+    /// The next index within the current block, or `None` for the position of the block.
+    from: Option<B::Index>,
+}
+
+impl<'a, B> BlockCursor<'a, B>
+where
+    B: AlignedBlock,
+    B::Index: NumericalIndex,
+{
+    pub(crate) fn new(blocks: &'a [B]) -> Self {
+        BlockCursor {
+            blocks,
+            block: 0,
+            from: None,
+        }
+    }
+
+    /// The current block, and the next index within it.
+    pub(crate) fn current(&self) -> Option<(&'a B, B::Index)> {
+        let block = self.blocks.get(self.block)?;
+        Some((block, self.from.unwrap_or(block.position())))
+    }
+
+    /// Move on to the index after `index`, which must be in the current block.
+    pub(crate) fn advance_past(&mut self, index: B::Index) {
+        let block = &self.blocks[self.block];
+        if index == block.position().last_in_block(B::alignment()) {
+            self.next_block();
+        } else {
+            self.from = Some(index.next());
+        }
+    }
+
+    /// Move on to the beginning of the next block.
+    pub(crate) fn next_block(&mut self) {
+        self.block += 1;
+        self.from = None;
+    }
+
+    /// Move on to `target`, unless the cursor is already past it.
+    pub(crate) fn seek(&mut self, target: B::Index) {
+        let skip = self.blocks[self.block..]
+            .partition_point(|b| b.position().last_in_block(B::alignment()) < target);
+        if skip > 0 {
+            self.block += skip;
+            self.from = None;
+        }
+        if let Some((_, from)) = self.current() {
+            if target > from {
+                self.from = Some(target);
+            }
+        }
+    }
+}
+
+/// An iterator over every index of a sorted slice of blocks, which can skip ahead to a target (see `SkipTo`).
+pub struct OccupiedIndexIterator<'a, B>
+where
+    B: AlignedBlock,
+{
+    cursor: BlockCursor<'a, B>,
+}
+
+impl<'a, B> OccupiedIndexIterator<'a, B>
+where
+    B: AlignedBlock,
+    B::Index: NumericalIndex,
+{
+    /// Iterate over the indices of blocks that are in order by position.
+    pub fn new(blocks: &'a [B]) -> Self {
+        OccupiedIndexIterator {
+            cursor: BlockCursor::new(blocks),
+        }
+    }
+}
+
+impl<'a, B> Iterator for OccupiedIndexIterator<'a, B>
+where
+    B: AlignedBlock,
+    B::Index: NumericalIndex,
+{
+    type Item = B::Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, index) = self.cursor.current()?;
+        self.cursor.advance_past(index);
+        Some(index)
+    }
+}
+
+impl<'a, B> SkipTo for OccupiedIndexIterator<'a, B>
+where
+    B: AlignedBlock,
+    B::Index: NumericalIndex,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        self.cursor.seek(target);
+        self.next()
+    }
+}
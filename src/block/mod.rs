@@ -1,15 +1,25 @@
 mod aligned_block;
 mod aligned_vec;
 mod arc_block;
+mod bit_array;
 mod bitfield;
+mod block_cache;
+mod block_vec;
 mod dense_vec;
 mod iterators;
+mod packed_block;
 mod singleton;
+mod soa_block;
 mod sparse_vec;
 
 pub use aligned_block::*;
 pub use aligned_vec::*;
+pub use bit_array::*;
 pub use bitfield::*;
+pub use block_cache::*;
+pub use block_vec::*;
 pub use dense_vec::*;
 pub use iterators::*;
+pub use packed_block::*;
+pub use soa_block::*;
 pub use sparse_vec::*;
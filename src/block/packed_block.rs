@@ -0,0 +1,245 @@
+use std::marker::PhantomData;
+
+use crate::compress::{clear_bits, pack_bits, packed_words, unpack_bits, PackedInteger};
+
+use super::{
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockSize, BlockStore, IndexedBlock,
+};
+
+/// An AlignedBlock of N integers, each packed densely into BITS bits.
+/// For example, a `PackedBlock<12, 4096>` stores 4096 12-bit values in 6144 bytes, where an `AlignedVec<u32, 4096>` would use 16384.
+///
+/// Elements are stored by their `PackedInteger::to_zigzag` representation, which must fit in BITS bits.
+/// Zero is always stored as zero bits, and signed elements of small magnitude fit in few bits.
+/// Storing an element that does not fit will panic.
+pub struct PackedBlock<const BITS: usize, const N: usize, T = u32> {
+    position: usize,
+    words: Vec<u64>,
+    item: PhantomData<T>,
+}
+
+impl<const BITS: usize, const N: usize, T> Clone for PackedBlock<BITS, N, T> {
+    fn clone(&self) -> Self {
+        PackedBlock {
+            position: self.position,
+            words: self.words.clone(),
+            item: PhantomData,
+        }
+    }
+}
+
+impl<const BITS: usize, const N: usize, T> IndexedBlock for PackedBlock<BITS, N, T> {
+    type Index = usize;
+    type Item = T;
+}
+
+impl<const BITS: usize, const N: usize, T> AlignedBlock for PackedBlock<BITS, N, T> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<const BITS: usize, const N: usize, T> PackedBlock<BITS, N, T>
+where
+    T: PackedInteger,
+{
+    /// Construct a new PackedBlock where every element is zero, starting at the given position.
+    /// The position must be aligned with (divisible by) N.
+    pub fn new(position: usize) -> Self {
+        assert!(BITS >= 1 && BITS <= 64, "BITS must be between 1 and 64");
+        assert!(position % N == 0);
+        PackedBlock {
+            position,
+            words: vec![0; packed_words(BITS as u32, N)],
+            item: PhantomData,
+        }
+    }
+
+    fn index_of(&self, index: usize) -> usize {
+        assert!(index >= self.position);
+        let index = index - self.position;
+        assert!(index < N);
+        index
+    }
+
+    /// Unpack every element of this block into a slice, which must be exactly N elements long.
+    /// This is much faster than fetching the elements one at a time.
+    pub fn unpack_into(&self, out: &mut [T]) {
+        assert_eq!(out.len(), N);
+        let width = BITS as u32;
+        let mask = if width < 64 {
+            (1 << width) - 1
+        } else {
+            u64::MAX
+        };
+        let mut word = 0;
+        let mut offset = 0;
+        for x in out.iter_mut() {
+            let mut value = self.words[word] >> offset;
+            offset += width;
+            if offset >= 64 {
+                word += 1;
+                offset -= 64;
+                if offset > 0 {
+                    value |= self.words[word] << (width - offset);
+                }
+            }
+            *x = T::from_zigzag(value & mask);
+        }
+    }
+
+    /// Iterator over every element of this block.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..N).map(|i| T::from_zigzag(unpack_bits(&self.words, BITS as u32, i)))
+    }
+}
+
+impl<const BITS: usize, const N: usize, T> BlockFetch for PackedBlock<BITS, N, T>
+where
+    T: PackedInteger,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        let i = self.index_of(index);
+        T::from_zigzag(unpack_bits(&self.words, BITS as u32, i))
+    }
+}
+
+impl<const BITS: usize, const N: usize, T> BlockStore for PackedBlock<BITS, N, T>
+where
+    T: PackedInteger,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let i = self.index_of(index);
+        let bits = item.to_zigzag();
+        assert!(
+            BITS == 64 || bits >> BITS == 0,
+            "item does not fit in the packed width"
+        );
+        clear_bits(&mut self.words, BITS as u32, i);
+        pack_bits(&mut self.words, BITS as u32, i, bits);
+    }
+}
+
+/// The packed words are on the heap, so they are counted along with the block itself.
+impl<const BITS: usize, const N: usize, T> BlockSize for PackedBlock<BITS, N, T> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.words.len() * std::mem::size_of::<u64>()
+    }
+}
+
+impl<const BITS: usize, const N: usize, T> AlignedBlockFromIterator for PackedBlock<BITS, N, T>
+where
+    T: PackedInteger,
+{
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let mut result = Self::new(position);
+        for i in 0..N {
+            let item = iter
+                .next()
+                .expect("iterator to contain at least as many elements as Self::alignment()");
+            result.store(position + i, item);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PackedBlock;
+    use crate::block::{
+        AlignedBlockFromIterator, BlockFetch, BlockStore, DefaultValue, DenseVec, SparseVec,
+    };
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_fetch_store() {
+        let mut b: PackedBlock<3, 100> = PackedBlock::new(300);
+        b.store(300, 7);
+        b.store(321, 5);
+        b.store(399, 1);
+        b.store(321, 2);
+
+        assert_eq!(b.fetch(300), 7);
+        assert_eq!(b.fetch(301), 0);
+        assert_eq!(b.fetch(321), 2);
+        assert_eq!(b.fetch(399), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "item does not fit in the packed width")]
+    fn test_too_wide() {
+        let mut b: PackedBlock<12, 16> = PackedBlock::new(0);
+        b.store(3, 4096);
+    }
+
+    #[test]
+    fn test_dense_and_sparse() {
+        let v = DenseVec::new_from(vec![
+            PackedBlock::<20, 8>::from_iterator(0, &mut (1_000_000..)),
+            PackedBlock::<20, 8>::from_iterator(8, &mut (2_000..)),
+        ]);
+        assert_eq!(
+            v.iter().collect::<Vec<_>>()[6..10],
+            [1_000_006, 1_000_007, 2_000, 2_001]
+        );
+
+        let mut v: SparseVec<PackedBlock<5, 64, u8>, DefaultValue> = SparseVec::default();
+        v.store(1_000, 31);
+        assert_eq!(v.fetch(1_000), 31);
+        assert_eq!(v.fetch(1_001), 0);
+    }
+
+    #[test]
+    fn test_signed() {
+        let mut b: PackedBlock<4, 16, i32> = PackedBlock::new(0);
+        assert_eq!(b.fetch(0), 0);
+        b.store(1, -8);
+        b.store(2, 7);
+        b.store(3, 0);
+        assert_eq!(b.iter().take(4).collect::<Vec<_>>(), vec![0, -8, 7, 0]);
+
+        let v: SparseVec<PackedBlock<6, 64, i8>, DefaultValue> = SparseVec::default();
+        assert_eq!(v.fetch(5), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "item does not fit in the packed width")]
+    fn test_signed_too_wide() {
+        let mut b: PackedBlock<4, 16, i64> = PackedBlock::new(0);
+        b.store(3, 8);
+    }
+
+    proptest! {
+        #[test]
+        fn test_unpack_into(values in proptest::collection::vec(0..(1_u64 << 41), 37)) {
+            let b: PackedBlock<41, 37, u64> = PackedBlock::from_iterator(37, &mut values.iter().copied());
+            let mut out = vec![0; 37];
+            b.unpack_into(&mut out);
+            assert_eq!(&out, &values);
+            assert_eq!(b.iter().collect::<Vec<_>>(), values);
+        }
+
+        #[test]
+        fn test_full_width(values in proptest::collection::vec(any::<u64>(), 5)) {
+            let b: PackedBlock<64, 5, u64> = PackedBlock::from_iterator(0, &mut values.iter().copied());
+            let mut out = vec![0; 5];
+            b.unpack_into(&mut out);
+            assert_eq!(&out, &values);
+        }
+
+        #[test]
+        fn test_signed_unpack_into(values in proptest::collection::vec(-(1_i64 << 40)..(1_i64 << 40), 37)) {
+            let b: PackedBlock<41, 37, i64> = PackedBlock::from_iterator(0, &mut values.iter().copied());
+            let mut out = vec![0; 37];
+            b.unpack_into(&mut out);
+            assert_eq!(&out, &values);
+        }
+    }
+}
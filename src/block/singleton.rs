@@ -1,166 +1,100 @@
+use crate::numerical_index::NumericalIndex;
+
 use super::{
-    aligned_block::{AlignedBlock, BlockFetch, BlockStore},
-    IndexedBlock,
+    aligned_block::{AlignedBlock, BlockFetch, BlockGet, BlockGetMut, BlockStore},
+    AlignedBlockFromIterator, IndexedBlock,
 };
 
-impl<Item> IndexedBlock for (usize, Item) {
-    type Index = usize;
-    type Item = Item;
-}
-
-impl<Item> AlignedBlock for (usize, Item) {
-    fn alignment() -> Self::Index {
-        1
-    }
-    fn position(&self) -> Self::Index {
-        self.0
-    }
-}
-
-impl<Item> IndexedBlock for (u16, Item) {
-    type Index = u16;
-    type Item = Item;
-}
-
-impl<Item> AlignedBlock for (u16, Item) {
-    fn alignment() -> Self::Index {
-        1
-    }
-    fn position(&self) -> Self::Index {
-        self.0
-    }
-}
-
-impl<Item> IndexedBlock for (u32, Item) {
-    type Index = u32;
-    type Item = Item;
-}
-
-impl<Item> AlignedBlock for (u32, Item) {
-    fn alignment() -> Self::Index {
-        1
-    }
-    fn position(&self) -> Self::Index {
-        self.0.clone()
-    }
-}
-
-impl<Item> IndexedBlock for (u64, Item) {
-    type Index = u64;
-    type Item = Item;
-}
-
-impl<Item> AlignedBlock for (u64, Item) {
-    fn alignment() -> Self::Index {
-        1
-    }
-    fn position(&self) -> Self::Index {
-        self.0
-    }
-}
-
-impl<Item> IndexedBlock for (u128, Item) {
-    type Index = u128;
+/// A block holding a single element, for any kind of index.
+impl<I, Item> IndexedBlock for (I, Item)
+where
+    I: NumericalIndex,
+{
+    type Index = I;
     type Item = Item;
 }
 
-impl<Item> AlignedBlock for (u128, Item) {
+impl<I, Item> AlignedBlock for (I, Item)
+where
+    I: NumericalIndex,
+{
     fn alignment() -> Self::Index {
-        1
+        I::ONE
     }
     fn position(&self) -> Self::Index {
         self.0
     }
 }
 
-impl<Item> BlockFetch for (usize, Item)
+impl<I, Item> BlockFetch for (I, Item)
 where
+    I: NumericalIndex,
     Item: Copy,
 {
     fn fetch(&self, index: Self::Index) -> Self::Item {
-        assert_eq!(index, self.0);
+        assert!(
+            index == self.0,
+            "index should be the position of the singleton"
+        );
         self.1
     }
 }
 
-impl<Item> BlockFetch for (u16, Item)
+impl<I, Item> BlockStore for (I, Item)
 where
-    Item: Copy,
+    I: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        assert_eq!(index, self.0);
-        self.1
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        assert!(
+            index == self.0,
+            "index should be the position of the singleton"
+        );
+        self.1 = item;
     }
 }
 
-impl<Item> BlockFetch for (u32, Item)
+impl<I, Item> BlockGet for (I, Item)
 where
-    Item: Copy,
+    I: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        assert_eq!(index, self.0);
-        self.1
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        (index == self.0).then_some(&self.1)
     }
 }
 
-impl<Item> BlockFetch for (u64, Item)
+impl<I, Item> BlockGetMut for (I, Item)
 where
-    Item: Copy,
+    I: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        assert_eq!(index, self.0);
-        self.1
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        (index == self.0).then_some(&mut self.1)
     }
 }
 
-impl<Item> BlockFetch for (u128, Item)
+impl<I, Item> AlignedBlockFromIterator for (I, Item)
 where
-    Item: Copy,
+    I: NumericalIndex,
 {
-    fn fetch(&self, index: Self::Index) -> Self::Item {
-        assert_eq!(index, self.0);
-        self.1
-    }
-}
-
-impl<Item> BlockStore for (usize, Item) {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        assert_eq!(index, self.0);
-        self.1 = item;
-    }
-}
-
-impl<Item> BlockStore for (u16, Item) {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        assert_eq!(index, self.0);
-        self.1 = item;
-    }
-}
-
-impl<Item> BlockStore for (u32, Item) {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        assert_eq!(index, self.0);
-        self.1 = item;
-    }
-}
-
-impl<Item> BlockStore for (u64, Item) {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        assert_eq!(index, self.0);
-        self.1 = item;
-    }
-}
-
-impl<Item> BlockStore for (u128, Item) {
-    fn store(&mut self, index: Self::Index, item: Self::Item) {
-        assert_eq!(index, self.0);
-        self.1 = item;
+    fn from_iterator<It>(position: Self::Index, iter: &mut It) -> Self
+    where
+        It: Iterator<Item = Self::Item>,
+    {
+        (
+            position,
+            iter.next()
+                .expect("iterator should provide at least one element"),
+        )
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::block::{AlignedBlock, BlockFetch, BlockStore};
+    use crate::block::{
+        AlignedBlock, BlockCollection, BlockFetch, BlockGet, BlockGetMut, BlockStore, DefaultValue,
+        SparseVec,
+    };
+    use crate::numerical_index::NumericalIndex;
+    use std::num::NonZeroU64;
 
     #[test]
     pub fn test_singleton_usize() {
@@ -216,4 +150,53 @@ mod test {
         x.store(500, "world");
         assert_eq!(x.fetch(500), "world");
     }
+
+    #[test]
+    pub fn test_singleton_u8() {
+        let mut x: (u8, &str) = (200, "hello");
+
+        assert_eq!(<(u8, &str)>::alignment(), 1);
+        assert_eq!(x.position(), 200);
+        x.store(200, "world");
+        assert_eq!(x.fetch(200), "world");
+    }
+
+    #[test]
+    pub fn test_singleton_signed_and_nonzero() {
+        let x: (i32, &str) = (-500, "hello");
+        assert_eq!(<(i32, &str)>::alignment(), 1);
+        assert_eq!(x.fetch(-500), "hello");
+
+        let one = NonZeroU64::new(1).unwrap();
+        let x: (NonZeroU64, &str) = (one, "hello");
+        assert_eq!(<(NonZeroU64, &str)>::alignment(), one);
+        assert_eq!(x.fetch(one), "hello");
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, NumericalIndex)]
+    struct UserId(u64);
+
+    #[test]
+    pub fn test_sparse_vec_by_newtype() {
+        let mut v: SparseVec<(UserId, u32), DefaultValue> = SparseVec::default();
+        v.store(UserId(1_000_000), 7);
+        v.store(UserId(3), 5);
+        *v.get_mut_or_insert(UserId(3)) += 1;
+
+        assert_eq!(v.fetch(UserId(3)), 6);
+        assert_eq!(v.fetch(UserId(4)), 0);
+        assert_eq!(v.fetch(UserId(1_000_000)), 7);
+        assert_eq!(v.blocks().len(), 2);
+    }
+
+    #[test]
+    pub fn test_singleton_get() {
+        let mut x: (u64, String) = (500, "hello".to_string());
+
+        assert_eq!(x.get(500).map(String::as_str), Some("hello"));
+        assert_eq!(x.get(501), None);
+        x.get_mut(500).unwrap().push_str(", world");
+        assert_eq!(x.1, "hello, world");
+        assert_eq!(x.get_mut(0), None);
+    }
 }
@@ -0,0 +1,203 @@
+use super::{
+    AlignedBlock, AlignedBlockFromIterator, AlignedVec, BlockFetch, BlockStore, IndexedBlock,
+};
+
+/// A block of records stored as a struct of arrays, with one `AlignedVec` per field.
+/// The columns are a tuple, such as `(AlignedVec<u64, N>, AlignedVec<u32, N>, AlignedVec<f64, N>)`,
+/// and each element of the block is the tuple of one element from every column, such as `(u64, u32, f64)`.
+///
+/// All of the columns share the position of the block, so a `DenseVec` or `SparseVec` of these blocks behaves like a table
+/// with a single row index. Scanning one column, through `columns`, only touches that column's memory:
+/// `v.blocks().iter().flat_map(|b| b.columns().1.iter())`.
+///
+/// Records are not stored anywhere as a whole, so they can't be borrowed (see `BlockGet`).
+#[derive(Clone)]
+pub struct SoaBlock<C> {
+    columns: C,
+}
+
+/// A tuple of `AlignedVec`s of the same length that can be the columns of a `SoaBlock`.
+pub trait SoaColumns {
+    /// The position shared by every column, or `None` if the columns disagree.
+    fn position(&self) -> Option<usize>;
+}
+
+impl<C> SoaBlock<C>
+where
+    C: SoaColumns,
+{
+    /// Construct a block from its columns, which must all have the same position.
+    pub fn new_from(columns: C) -> Self {
+        assert!(
+            columns.position().is_some(),
+            "columns must have the same position"
+        );
+        SoaBlock { columns }
+    }
+}
+
+impl<C> SoaBlock<C> {
+    /// The columns of the block.
+    pub fn columns(&self) -> &C {
+        &self.columns
+    }
+
+    /// Unwrap the block into its columns.
+    pub fn into_columns(self) -> C {
+        self.columns
+    }
+}
+
+/// Implement `SoaColumns` and `SoaBlock` for a tuple of columns, naming the element type and tuple field of each column.
+macro_rules! soa_block {
+    ($(($t:ident, $i:tt)),+) => {
+        impl<$($t,)+ const N: usize> SoaColumns for ($(AlignedVec<$t, N>,)+) {
+            fn position(&self) -> Option<usize> {
+                let position = self.0.position();
+                [$(self.$i.position(),)+].iter().all(|p| *p == position).then_some(position)
+            }
+        }
+
+        impl<$($t,)+ const N: usize> SoaBlock<($(AlignedVec<$t, N>,)+)> {
+            /// Mutable iterators over the elements of every column.
+            /// The columns themselves can't be replaced, because every column must keep the position of the block.
+            pub fn iter_columns_mut(&mut self) -> ($(impl Iterator<Item = &mut $t>,)+) {
+                ($(self.columns.$i.iter_mut(),)+)
+            }
+        }
+
+        impl<$($t,)+ const N: usize> IndexedBlock for SoaBlock<($(AlignedVec<$t, N>,)+)> {
+            type Index = usize;
+            type Item = ($($t,)+);
+        }
+
+        impl<$($t,)+ const N: usize> AlignedBlock for SoaBlock<($(AlignedVec<$t, N>,)+)> {
+            fn alignment() -> Self::Index {
+                N
+            }
+
+            fn position(&self) -> Self::Index {
+                self.columns.0.position()
+            }
+        }
+
+        impl<$($t,)+ const N: usize> BlockFetch for SoaBlock<($(AlignedVec<$t, N>,)+)>
+        where
+            $($t: Copy,)+
+        {
+            fn fetch(&self, index: Self::Index) -> Self::Item {
+                ($(self.columns.$i[index],)+)
+            }
+        }
+
+        impl<$($t,)+ const N: usize> BlockStore for SoaBlock<($(AlignedVec<$t, N>,)+)> {
+            fn store(&mut self, index: Self::Index, item: Self::Item) {
+                $(self.columns.$i[index] = item.$i;)+
+            }
+        }
+
+        impl<$($t,)+ const N: usize> AlignedBlockFromIterator for SoaBlock<($(AlignedVec<$t, N>,)+)> {
+            fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+            where
+                I: Iterator<Item = Self::Item>,
+            {
+                let mut columns = ($(Vec::<$t>::with_capacity(N),)+);
+                for _ in 0..N {
+                    let item = iter
+                        .next()
+                        .expect("iterator to contain at least as many elements as Self::alignment()");
+                    $(columns.$i.push(item.$i);)+
+                }
+                SoaBlock {
+                    columns: ($(AlignedVec::new_from(position, columns.$i),)+),
+                }
+            }
+        }
+    };
+}
+
+soa_block!((A, 0), (B, 1));
+soa_block!((A, 0), (B, 1), (C, 2));
+soa_block!((A, 0), (B, 1), (C, 2), (D, 3));
+soa_block!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+soa_block!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+
+#[cfg(test)]
+mod test {
+    use super::SoaBlock;
+    use crate::block::{
+        AlignedBlock, AlignedBlockFromIterator, AlignedVec, BlockCollection, BlockFetch,
+        BlockStore, DefaultValue, DenseVec, SparseVec,
+    };
+
+    type Events = SoaBlock<(AlignedVec<u64, 4>, AlignedVec<u32, 4>, AlignedVec<i16, 4>)>;
+
+    fn events(position: usize) -> Events {
+        Events::from_function(position, |i| (1000 + i as u64, i as u32 % 3, -(i as i16)))
+    }
+
+    #[test]
+    fn test_fetch_store() {
+        let mut b = events(8);
+        assert_eq!(Events::alignment(), 4);
+        assert_eq!(b.position(), 8);
+        assert_eq!(b.fetch(9), (1009, 0, -9));
+
+        b.store(10, (1, 2, 3));
+        assert_eq!(b.fetch(10), (1, 2, 3));
+        assert_eq!(
+            b.columns().1.iter().copied().collect::<Vec<_>>(),
+            vec![2, 0, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_columns_mut() {
+        let mut b = events(0);
+        let (timestamps, _, values) = b.iter_columns_mut();
+        timestamps.for_each(|t| *t -= 1000);
+        values.for_each(|v| *v *= 2);
+
+        assert_eq!(b.fetch(3), (3, 0, -6));
+        let (timestamps, _, _) = b.into_columns();
+        assert_eq!(timestamps.into_vec(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "columns must have the same position")]
+    fn test_misaligned_columns() {
+        SoaBlock::new_from((
+            AlignedVec::<u8, 2>::new_from(0, vec![1, 2]),
+            AlignedVec::<u8, 2>::new_from(2, vec![3, 4]),
+        ));
+    }
+
+    #[test]
+    fn test_table() {
+        let mut table = DenseVec::new_from((0..5).map(|b| events(b * 4)).collect());
+        table.store(7, (7, 7, 7));
+
+        assert_eq!(table.fetch(6), (1006, 0, -6));
+        assert_eq!(table.fetch(7), (7, 7, 7));
+
+        let users: u32 = table
+            .blocks()
+            .iter()
+            .flat_map(|b| b.columns().1.iter())
+            .sum();
+        assert_eq!(users, (0..20).map(|i| i % 3).sum::<u32>() - 1 + 7);
+    }
+
+    #[test]
+    fn test_sparse_table() {
+        let mut table: SparseVec<
+            SoaBlock<(AlignedVec<u64, 8>, AlignedVec<bool, 8>)>,
+            DefaultValue,
+        > = SparseVec::default();
+        table.store(1_000_003, (5, true));
+
+        assert_eq!(table.fetch(1_000_003), (5, true));
+        assert_eq!(table.fetch(1_000_004), (0, false));
+        assert_eq!(table.blocks().len(), 1);
+    }
+}
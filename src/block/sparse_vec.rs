@@ -1,7 +1,9 @@
 use crate::numerical_index::NumericalIndex;
 
 use super::{
-    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore, DefaultPerIndex, IndexedBlock,
+    AlignedBlock, AlignedBlockFromIterator, BlockCollection, BlockEnumerate, BlockFetch,
+    BlockFetchIterator, BlockGet, BlockGetMut, BlockIndexIterator, BlockStore, DefaultPerIndex,
+    DefaultPerIndexRef, IndexedBlock, OccupiedIndexIterator,
 };
 
 /// A vector of items that are themselves AlignedBlocks.
@@ -54,7 +56,7 @@ where
 
         for value in self.vec.iter() {
             assert!(
-                value.position().modulo(T::alignment()).is_zero(),
+                value.position().modulo(T::alignment()).is_min(),
                 "blocks must be aligned"
             );
             assert!(
@@ -97,6 +99,32 @@ where
         (self, result)
     }
 
+    /// Insert an entire block, returning the block that was previously at the same position, if any.
+    pub fn insert_block(&mut self, t: T) -> Option<T> {
+        assert!(
+            t.position().modulo(T::alignment()).is_min(),
+            "blocks must be aligned"
+        );
+        match self.index_of(t.position()) {
+            Ok(exists) => Some(std::mem::replace(&mut self.vec[exists], t)),
+            Err(does_not_exist) => {
+                self.vec.insert(does_not_exist, t);
+                None
+            }
+        }
+    }
+
+    /// Get the block that contains the given index, if it is stored.
+    pub fn block_containing(&self, index: T::Index) -> Option<&T> {
+        self.index_of(index).ok().map(|i| &self.vec[i])
+    }
+
+    /// Iterate over every index inside of a stored block, in increasing order.
+    /// The iterator can skip ahead over whole blocks (see `SkipTo`).
+    pub fn occupied_indices(&self) -> OccupiedIndexIterator<'_, T> {
+        OccupiedIndexIterator::new(&self.vec)
+    }
+
     fn ensure_index_exists(&mut self, index: T::Index) -> usize
     where
         T: AlignedBlockFromIterator,
@@ -155,6 +183,77 @@ where
     }
 }
 
+/// Elements of absent blocks are borrowed from the default value, if `D` stores one (see `SharedDefault`).
+impl<T, D> BlockGet for SparseVec<T, D>
+where
+    T: AlignedBlock + BlockGet,
+    T::Index: NumericalIndex,
+    D: DefaultPerIndexRef<T::Index, T::Item>,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        match self.index_of(index) {
+            Ok(i) => self.vec[i].get(index),
+            Err(_) => self.default_value.default_ref_at_index(index),
+        }
+    }
+}
+
+/// Elements of absent blocks can't be mutably borrowed. Use `get_mut_or_insert` to materialize the block first.
+impl<T, D> BlockGetMut for SparseVec<T, D>
+where
+    T: AlignedBlock + BlockGetMut,
+    T::Index: NumericalIndex,
+    D: DefaultPerIndexRef<T::Index, T::Item>,
+{
+    fn get_mut(&mut self, index: Self::Index) -> Option<&mut Self::Item> {
+        match self.index_of(index) {
+            Ok(i) => self.vec[i].get_mut(index),
+            Err(_) => None,
+        }
+    }
+}
+
+impl<T, D> SparseVec<T, D>
+where
+    T: AlignedBlock + BlockGetMut + AlignedBlockFromIterator,
+    T::Index: NumericalIndex,
+    D: DefaultPerIndex<T::Index, T::Item>,
+{
+    /// Get a mutable reference to an element, first filling in its block with default values if it is absent.
+    pub fn get_mut_or_insert(&mut self, index: T::Index) -> &mut T::Item {
+        let big = self.ensure_index_exists(index);
+        self.vec[big]
+            .get_mut(index)
+            .expect("a stored block should contain every index in its range")
+    }
+}
+
+impl<T, D> BlockCollection for SparseVec<T, D>
+where
+    T: AlignedBlock,
+    D: DefaultPerIndex<T::Index, T::Item>,
+{
+    type Block = T;
+
+    fn blocks(&self) -> &[T] {
+        &self.vec
+    }
+}
+
+/// Only the elements of stored blocks are enumerated. Every other index holds its default value.
+impl<T, D> BlockEnumerate for SparseVec<T, D>
+where
+    T: AlignedBlock + BlockFetch,
+    T::Index: NumericalIndex,
+    D: DefaultPerIndex<T::Index, T::Item>,
+{
+    fn enumerate_items(&self) -> impl Iterator<Item = (Self::Index, Self::Item)> + '_ {
+        self.vec
+            .iter()
+            .flat_map(|b| BlockIndexIterator::new(b).zip(BlockFetchIterator::new(b)))
+    }
+}
+
 impl<T, D> DefaultPerIndex<T::Index, T::Item> for SparseVec<T, D>
 where
     D: DefaultPerIndex<T::Index, T::Item>,
@@ -169,7 +268,12 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::block::{AlignedVec, BlockFetch, BlockStore, DefaultValue};
+    use crate::block::{
+        AlignedVec, BlockEnumerate, BlockFetch, BlockGet, BlockGetMut, BlockStore, DefaultValue,
+        SharedDefault,
+    };
+    use crate::merge::SkipTo;
+    use proptest::prelude::*;
 
     use super::SparseVec;
 
@@ -227,4 +331,96 @@ mod test {
             SparseVec::new_from(DefaultValue, vec![]);
         v.store(25, 25);
     }
+
+    #[test]
+    fn test_get_strings() {
+        let mut v: SparseVec<AlignedVec<String, 4>, SharedDefault<String>> =
+            SparseVec::new(SharedDefault("none".to_string()));
+        v.store(5, "five".to_string());
+        v.get_mut_or_insert(100).push_str("hundred");
+
+        assert_eq!(v.get(5).map(String::as_str), Some("five"));
+        assert_eq!(v.get(6).map(String::as_str), Some("none"));
+        assert_eq!(v.get(50).map(String::as_str), Some("none"));
+        assert_eq!(v.get(100).map(String::as_str), Some("nonehundred"));
+
+        assert!(v.get_mut(50).is_none());
+        v.get_mut(6).unwrap().clear();
+        assert_eq!(v.get(6).map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn test_get_default_value() {
+        let mut v: SparseVec<AlignedVec<Vec<u8>, 4>, DefaultValue> = SparseVec::default();
+        v.store(1, vec![1]);
+
+        assert_eq!(v.get(1), Some(&vec![1]));
+        assert_eq!(v.get(2), Some(&vec![]));
+        assert_eq!(v.get(10), None);
+    }
+
+    #[test]
+    fn test_top_of_index_space() {
+        let mut v: SparseVec<AlignedVec<u8, 16>, DefaultValue> = SparseVec::default();
+        v.store(usize::MAX, 1);
+        v.store(usize::MAX - 15, 2);
+
+        assert_eq!(v.fetch(usize::MAX), 1);
+        assert_eq!(v.fetch(usize::MAX - 1), 0);
+        assert_eq!(
+            v.enumerate_items().filter(|(_, x)| *x != 0).collect::<Vec<_>>(),
+            vec![(usize::MAX - 15, 2), (usize::MAX, 1)]
+        );
+    }
+
+    #[test]
+    fn test_occupied_indices() {
+        let mut v: SparseVec<AlignedVec<u8, 4>, DefaultValue> = SparseVec::default();
+        v.store(9, 1);
+        v.store(2, 1);
+        v.store(usize::MAX, 1);
+
+        assert_eq!(
+            v.occupied_indices().collect::<Vec<_>>(),
+            vec![
+                0,
+                1,
+                2,
+                3,
+                8,
+                9,
+                10,
+                11,
+                usize::MAX - 3,
+                usize::MAX - 2,
+                usize::MAX - 1,
+                usize::MAX
+            ]
+        );
+
+        let mut indices = v.occupied_indices();
+        assert_eq!(indices.skip_to(2), Some(2));
+        assert_eq!(indices.skip_to(2), Some(3));
+        assert_eq!(indices.skip_to(5), Some(8));
+        assert_eq!(indices.skip_to(1000), Some(usize::MAX - 3));
+        assert_eq!(indices.skip_to(usize::MAX), Some(usize::MAX));
+        assert_eq!(indices.next(), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_store_anywhere(indices in proptest::collection::btree_set(any::<usize>(), 1..20)) {
+            let mut v: SparseVec<AlignedVec<usize, 16>, DefaultValue> = SparseVec::default();
+            for i in indices.iter() {
+                v.store(*i, i.wrapping_add(1));
+            }
+            for i in indices.iter() {
+                assert_eq!(v.fetch(*i), i.wrapping_add(1));
+            }
+            assert_eq!(
+                v.enumerate_items().filter(|(i, x)| *x == i.wrapping_add(1)).map(|(i, _)| i).collect::<Vec<_>>(),
+                indices.into_iter().collect::<Vec<_>>()
+            );
+        }
+    }
 }
@@ -0,0 +1,145 @@
+use crate::block::{
+    AlignedBlock, AlignedBlockFromIterator, AlignedVec, BlockFetch, BlockSize, IndexedBlock,
+};
+
+use super::{DeltaBlock, ForBlock};
+
+/// Which encoding an `AdaptiveBlock` chose.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BlockEncoding {
+    /// Stored as-is, in an `AlignedVec`.
+    Plain,
+    /// Stored as a `ForBlock`.
+    FrameOfReference,
+    /// Stored as a `DeltaBlock`.
+    Delta,
+}
+
+/// An AlignedBlock of N `u64`s that picks whichever of plain, frame-of-reference or delta encoding is smallest for its contents.
+/// Each block of a collection chooses independently.
+#[derive(Clone)]
+pub enum AdaptiveBlock<const N: usize, const K: usize = 64> {
+    /// Stored as-is.
+    Plain(AlignedVec<u64, N>),
+    /// Stored with frame-of-reference encoding.
+    FrameOfReference(ForBlock<N>),
+    /// Stored with delta encoding.
+    Delta(DeltaBlock<N, K>),
+}
+
+impl<const N: usize, const K: usize> AdaptiveBlock<N, K> {
+    /// Encode a slice of exactly N values, starting at the given position, with the smallest encoding.
+    pub fn new_from(position: usize, values: &[u64]) -> Self {
+        let plain_size = std::mem::size_of::<AlignedVec<u64, N>>() + N * std::mem::size_of::<u64>();
+        let for_block = ForBlock::new_from(position, values);
+        let delta_block = DeltaBlock::new_from(position, values);
+
+        if plain_size <= for_block.size_in_bytes().min(delta_block.size_in_bytes()) {
+            AdaptiveBlock::Plain(AlignedVec::new_from(position, values.to_vec()))
+        } else if for_block.size_in_bytes() <= delta_block.size_in_bytes() {
+            AdaptiveBlock::FrameOfReference(for_block)
+        } else {
+            AdaptiveBlock::Delta(delta_block)
+        }
+    }
+
+    /// Which encoding was chosen.
+    pub fn encoding(&self) -> BlockEncoding {
+        match self {
+            AdaptiveBlock::Plain(_) => BlockEncoding::Plain,
+            AdaptiveBlock::FrameOfReference(_) => BlockEncoding::FrameOfReference,
+            AdaptiveBlock::Delta(_) => BlockEncoding::Delta,
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> IndexedBlock for AdaptiveBlock<N, K> {
+    type Index = usize;
+    type Item = u64;
+}
+
+impl<const N: usize, const K: usize> AlignedBlock for AdaptiveBlock<N, K> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        match self {
+            AdaptiveBlock::Plain(b) => b.position(),
+            AdaptiveBlock::FrameOfReference(b) => b.position(),
+            AdaptiveBlock::Delta(b) => b.position(),
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> BlockFetch for AdaptiveBlock<N, K> {
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        match self {
+            AdaptiveBlock::Plain(b) => b.fetch(index),
+            AdaptiveBlock::FrameOfReference(b) => b.fetch(index),
+            AdaptiveBlock::Delta(b) => b.fetch(index),
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> BlockSize for AdaptiveBlock<N, K> {
+    fn size_in_bytes(&self) -> usize {
+        match self {
+            AdaptiveBlock::Plain(b) => b.size_in_bytes(),
+            AdaptiveBlock::FrameOfReference(b) => b.size_in_bytes(),
+            AdaptiveBlock::Delta(b) => b.size_in_bytes(),
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> AlignedBlockFromIterator for AdaptiveBlock<N, K> {
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let values: Vec<u64> = iter.take(N).collect();
+        assert_eq!(
+            values.len(),
+            N,
+            "iterator to contain at least as many elements as Self::alignment()"
+        );
+        Self::new_from(position, &values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdaptiveBlock, BlockEncoding};
+    use crate::block::{AlignedBlockFromIterator, BlockCollection, BlockFetch, DenseVec};
+
+    #[test]
+    fn test_chooses_encoding() {
+        let random: Vec<u64> = (0..256_u64)
+            .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .collect();
+        let narrow: Vec<u64> = (0..256).map(|i| 1_000_000 + (i * 37) % 200).collect();
+        let sorted: Vec<u64> = (0..256).map(|i| (1 << 40) + i * i * 1000).collect();
+
+        let v = DenseVec::new_from(vec![
+            AdaptiveBlock::<256>::new_from(0, &random),
+            AdaptiveBlock::<256>::new_from(256, &narrow),
+            AdaptiveBlock::<256>::from_iterator(512, &mut sorted.iter().copied()),
+        ]);
+
+        let encodings: Vec<_> = v.blocks().iter().map(|b| b.encoding()).collect();
+        assert_eq!(
+            encodings,
+            vec![
+                BlockEncoding::Plain,
+                BlockEncoding::FrameOfReference,
+                BlockEncoding::Delta
+            ]
+        );
+
+        for i in 0..256 {
+            assert_eq!(v.fetch(i), random[i]);
+            assert_eq!(v.fetch(256 + i), narrow[i]);
+            assert_eq!(v.fetch(512 + i), sorted[i]);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use super::{BlockCodec, PackedInteger};
+
+/// Frame-of-reference bit-packing: each element is stored as its difference from the smallest element of the block,
+/// using only as many bits as the largest difference needs.
+pub struct BitPack;
+
+/// A block of frame-of-reference bit-packed integers.
+#[derive(Clone)]
+pub struct BitPacked {
+    reference: u64,
+    width: u32,
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitPacked {
+    /// The number of bits used for each element.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get a single element, without decoding the rest of the block.
+    pub fn get<T: PackedInteger>(&self, i: usize) -> T {
+        assert!(i < self.len);
+        T::from_bits(
+            self.reference
+                .wrapping_add(unpack_bits(&self.words, self.width, i)),
+        )
+    }
+}
+
+/// The number of 64-bit words needed to pack `len` values of `width` bits.
+pub(crate) fn packed_words(width: u32, len: usize) -> usize {
+    (width as usize * len).div_ceil(64)
+}
+
+/// Store the low `width` bits of `value` as the `i`th packed value.
+/// The bits being written to must currently be zero.
+pub(crate) fn pack_bits(words: &mut [u64], width: u32, i: usize, value: u64) {
+    if width == 0 {
+        return;
+    }
+    let bit = width as usize * i;
+    let (word, offset) = (bit / 64, (bit % 64) as u32);
+    words[word] |= value << offset;
+    if offset + width > 64 {
+        words[word + 1] |= value >> (64 - offset);
+    }
+}
+
+/// Zero the `i`th packed value of `width` bits.
+pub(crate) fn clear_bits(words: &mut [u64], width: u32, i: usize) {
+    if width == 0 {
+        return;
+    }
+    let mask = if width < 64 {
+        (1 << width) - 1
+    } else {
+        u64::MAX
+    };
+    let bit = width as usize * i;
+    let (word, offset) = (bit / 64, (bit % 64) as u32);
+    words[word] &= !(mask << offset);
+    if offset + width > 64 {
+        words[word + 1] &= !(mask >> (64 - offset));
+    }
+}
+
+/// Load the `i`th packed value of `width` bits.
+pub(crate) fn unpack_bits(words: &[u64], width: u32, i: usize) -> u64 {
+    if width == 0 {
+        return 0;
+    }
+    let bit = width as usize * i;
+    let (word, offset) = (bit / 64, (bit % 64) as u32);
+    let mut value = words[word] >> offset;
+    if offset + width > 64 {
+        value |= words[word + 1] << (64 - offset);
+    }
+    if width < 64 {
+        value &= (1 << width) - 1;
+    }
+    value
+}
+
+impl<T> BlockCodec<T> for BitPack
+where
+    T: PackedInteger,
+{
+    type Encoded = BitPacked;
+
+    fn encode(values: &[T]) -> BitPacked {
+        let reference = values.iter().map(|v| v.to_bits()).min().unwrap_or(0);
+        let max = values.iter().map(|v| v.to_bits()).max().unwrap_or(0);
+        let width = 64 - (max - reference).leading_zeros();
+
+        let mut words = vec![0; packed_words(width, values.len())];
+        for (i, v) in values.iter().enumerate() {
+            pack_bits(&mut words, width, i, v.to_bits() - reference);
+        }
+
+        BitPacked {
+            reference,
+            width,
+            len: values.len(),
+            words,
+        }
+    }
+
+    fn decode(encoded: &BitPacked, out: &mut Vec<T>) {
+        out.extend((0..encoded.len).map(|i| encoded.get::<T>(i)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitPack;
+    use crate::compress::BlockCodec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_width() {
+        let values: Vec<u32> = vec![1000, 1007, 1003, 1001];
+        let encoded = BitPack::encode(&values);
+
+        assert_eq!(encoded.width(), 3);
+        assert_eq!(encoded.get::<u32>(1), 1007);
+    }
+
+    #[test]
+    fn test_constant() {
+        let values: Vec<u64> = vec![u64::MAX; 100];
+        let encoded = BitPack::encode(&values);
+
+        assert_eq!(encoded.width(), 0);
+        let mut decoded = vec![];
+        BitPack::decode(&encoded, &mut decoded);
+        assert_eq!(values, decoded);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_u64(values in proptest::collection::vec(any::<u64>(), 1..100)) {
+            let encoded = BitPack::encode(&values);
+            let mut decoded = vec![];
+            BitPack::decode(&encoded, &mut decoded);
+            assert_eq!(values, decoded);
+        }
+
+        #[test]
+        fn test_round_trip_i16(values in proptest::collection::vec(-300..300_i16, 1..100)) {
+            let encoded = BitPack::encode(&values);
+            let mut decoded = vec![];
+            BitPack::decode(&encoded, &mut decoded);
+            assert_eq!(values, decoded);
+        }
+    }
+}
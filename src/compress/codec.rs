@@ -0,0 +1,174 @@
+use super::delta::{unzigzag, zigzag};
+
+/// A strategy for encoding the contents of a block into a more compact form.
+pub trait BlockCodec<T> {
+    /// The encoded form of a block.
+    type Encoded;
+
+    /// Encode every element of a block.
+    fn encode(values: &[T]) -> Self::Encoded;
+    /// Decode every element of a block, appending them to `out`.
+    fn decode(encoded: &Self::Encoded, out: &mut Vec<T>);
+}
+
+/// An integer that can be converted to and from 64 bits without loss, preserving its order.
+/// Signed integers have their sign bit flipped, so that the smallest value maps to zero.
+pub trait PackedInteger: Copy + Ord {
+    /// Convert to bits, preserving order.
+    fn to_bits(self) -> u64;
+    /// Inverse of `to_bits`.
+    fn from_bits(bits: u64) -> Self;
+    /// Convert to bits so that values near zero have few significant bits, and zero is all zero bits.
+    /// Unsigned integers are unchanged. Signed integers are zig-zag encoded, so 0, -1, 1, -2, ... map to 0, 1, 2, 3, ...
+    fn to_zigzag(self) -> u64 {
+        self.to_bits()
+    }
+    /// Inverse of `to_zigzag`.
+    fn from_zigzag(bits: u64) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+impl PackedInteger for u8 {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as Self
+    }
+}
+
+impl PackedInteger for u16 {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as Self
+    }
+}
+
+impl PackedInteger for u32 {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as Self
+    }
+}
+
+impl PackedInteger for u64 {
+    fn to_bits(self) -> u64 {
+        self
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits
+    }
+}
+
+impl PackedInteger for usize {
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as Self
+    }
+}
+
+impl PackedInteger for i8 {
+    fn to_bits(self) -> u64 {
+        (self as u8 ^ 0x80) as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        (bits as u8 ^ 0x80) as Self
+    }
+
+    fn to_zigzag(self) -> u64 {
+        zigzag(self as i64)
+    }
+
+    fn from_zigzag(bits: u64) -> Self {
+        unzigzag(bits) as Self
+    }
+}
+
+impl PackedInteger for i16 {
+    fn to_bits(self) -> u64 {
+        (self as u16 ^ 0x8000) as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        (bits as u16 ^ 0x8000) as Self
+    }
+
+    fn to_zigzag(self) -> u64 {
+        zigzag(self as i64)
+    }
+
+    fn from_zigzag(bits: u64) -> Self {
+        unzigzag(bits) as Self
+    }
+}
+
+impl PackedInteger for i32 {
+    fn to_bits(self) -> u64 {
+        (self as u32 ^ 0x8000_0000) as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        (bits as u32 ^ 0x8000_0000) as Self
+    }
+
+    fn to_zigzag(self) -> u64 {
+        zigzag(self as i64)
+    }
+
+    fn from_zigzag(bits: u64) -> Self {
+        unzigzag(bits) as Self
+    }
+}
+
+impl PackedInteger for i64 {
+    fn to_bits(self) -> u64 {
+        self as u64 ^ 0x8000_0000_0000_0000
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        (bits ^ 0x8000_0000_0000_0000) as Self
+    }
+
+    fn to_zigzag(self) -> u64 {
+        zigzag(self as i64)
+    }
+
+    fn from_zigzag(bits: u64) -> Self {
+        unzigzag(bits) as Self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PackedInteger;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_i16_order(a: i16, b: i16) {
+            assert_eq!(a.cmp(&b), a.to_bits().cmp(&b.to_bits()));
+            assert_eq!(i16::from_bits(a.to_bits()), a);
+            assert_eq!(i16::from_zigzag(a.to_zigzag()), a);
+            assert!(a.to_zigzag() <= u16::MAX as u64);
+        }
+
+        #[test]
+        fn test_i64_order(a: i64, b: i64) {
+            assert_eq!(a.cmp(&b), a.to_bits().cmp(&b.to_bits()));
+            assert_eq!(i64::from_bits(a.to_bits()), a);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::VecDeque,
+    marker::PhantomData,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::block::{
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockFetchIterator, BlockStore,
+    IndexedBlock,
+};
+
+use super::BlockCodec;
+
+/// The number of decoded blocks that each thread keeps around.
+pub const DECODE_CACHE_BLOCKS: usize = 8;
+
+/// Every distinct encoding of every CompressedBlock gets a unique id, so that the decode cache can never return stale contents.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Recently decoded blocks, most recently used first.
+    static DECODE_CACHE: RefCell<VecDeque<(u64, Rc<dyn Any>)>> = RefCell::new(VecDeque::new());
+}
+
+/// An AlignedBlock that is kept encoded by the codec `C`, and decoded on access.
+/// `B` is the uncompressed block type, such as `AlignedVec<u32, 4096>`, which determines the alignment and item type.
+///
+/// Decoded blocks are kept in a small per-thread cache, so consecutive fetches from the same block only decode it once.
+/// Every store decodes the block, modifies it, and encodes it again, so prefer building blocks with `from_iterator`
+/// over filling them in one element at a time.
+pub struct CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: IndexedBlock,
+{
+    position: usize,
+    id: u64,
+    encoded: C::Encoded,
+    block: PhantomData<B>,
+}
+
+impl<C, B> CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: AlignedBlock<Index = usize>,
+    B::Item: Copy + 'static,
+{
+    /// Compress an uncompressed block.
+    pub fn new_from(block: &B) -> Self
+    where
+        B: BlockFetch,
+    {
+        let values: Vec<B::Item> = BlockFetchIterator::new(block).collect();
+        Self::encode(block.position(), &values)
+    }
+
+    fn encode(position: usize, values: &[B::Item]) -> Self {
+        assert_eq!(values.len(), B::alignment());
+        assert!(position % B::alignment() == 0);
+        CompressedBlock {
+            position,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            encoded: C::encode(values),
+            block: PhantomData,
+        }
+    }
+
+    /// Decompress back into an uncompressed block.
+    pub fn decode(&self) -> B
+    where
+        B: AlignedBlockFromIterator,
+    {
+        let values = self.decoded();
+        B::from_iterator(self.position, &mut values.iter().copied())
+    }
+
+    /// The encoded contents of this block.
+    pub fn encoded(&self) -> &C::Encoded {
+        &self.encoded
+    }
+
+    /// Get the decoded contents of this block, from the cache if possible.
+    fn decoded(&self) -> Rc<Vec<B::Item>> {
+        DECODE_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let entry = match cache.iter().position(|(id, _)| *id == self.id) {
+                Some(k) => cache.remove(k).expect("entry should exist"),
+                None => {
+                    let mut values = Vec::with_capacity(B::alignment());
+                    C::decode(&self.encoded, &mut values);
+                    let values: Rc<dyn Any> = Rc::new(values);
+                    cache.truncate(DECODE_CACHE_BLOCKS - 1);
+                    (self.id, values)
+                }
+            };
+            cache.push_front(entry.clone());
+            entry
+                .1
+                .downcast::<Vec<B::Item>>()
+                .expect("ids are unique, so the cached type should always match")
+        })
+    }
+
+    fn offset_of(&self, index: usize) -> usize {
+        assert!(index >= self.position);
+        let offset = index - self.position;
+        assert!(offset < B::alignment());
+        offset
+    }
+}
+
+impl<C, B> Clone for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    C::Encoded: Clone,
+    B: IndexedBlock,
+{
+    fn clone(&self) -> Self {
+        CompressedBlock {
+            position: self.position,
+            id: self.id,
+            encoded: self.encoded.clone(),
+            block: PhantomData,
+        }
+    }
+}
+
+impl<C, B> IndexedBlock for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: IndexedBlock,
+{
+    type Index = B::Index;
+    type Item = B::Item;
+}
+
+impl<C, B> AlignedBlock for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: AlignedBlock<Index = usize>,
+{
+    fn alignment() -> Self::Index {
+        B::alignment()
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<C, B> BlockFetch for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: AlignedBlock<Index = usize>,
+    B::Item: Copy + 'static,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.decoded()[self.offset_of(index)]
+    }
+}
+
+impl<C, B> BlockStore for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: AlignedBlock<Index = usize>,
+    B::Item: Copy + 'static,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let offset = self.offset_of(index);
+        let mut values = Vec::clone(&self.decoded());
+        values[offset] = item;
+        *self = Self::encode(self.position, &values);
+    }
+}
+
+impl<C, B> AlignedBlockFromIterator for CompressedBlock<C, B>
+where
+    C: BlockCodec<B::Item>,
+    B: AlignedBlock<Index = usize>,
+    B::Item: Copy + 'static,
+{
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let values: Vec<B::Item> = iter.take(B::alignment()).collect();
+        assert_eq!(
+            values.len(),
+            B::alignment(),
+            "iterator to contain at least as many elements as Self::alignment()"
+        );
+        Self::encode(position, &values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompressedBlock;
+    use crate::block::{
+        AlignedBlockFromIterator, AlignedVec, BlockFetch, BlockStore, DefaultValue, SparseVec,
+    };
+    use crate::compress::{BitPack, Delta, RunLength};
+
+    #[test]
+    fn test_fetch_store() {
+        let v = AlignedVec::<u32, 8>::new_from(16, vec![100, 101, 103, 103, 90, 120, 101, 100]);
+        let mut b: CompressedBlock<BitPack, _> = CompressedBlock::new_from(&v);
+
+        assert_eq!(b.encoded().width(), 5);
+        assert_eq!(b.fetch(16), 100);
+        assert_eq!(b.fetch(21), 120);
+
+        b.store(17, 1000);
+        assert_eq!(b.fetch(17), 1000);
+        assert_eq!(b.encoded().width(), 10);
+        assert_eq!(
+            b.decode().into_vec(),
+            vec![100, 1000, 103, 103, 90, 120, 101, 100]
+        );
+    }
+
+    #[test]
+    fn test_clone_is_independent() {
+        let mut a: CompressedBlock<Delta, AlignedVec<i64, 4>> =
+            CompressedBlock::from_iterator(0, &mut (10..).map(|x| x as i64));
+        let b = a.clone();
+        a.store(2, -5);
+
+        assert_eq!(a.fetch(2), -5);
+        assert_eq!(b.fetch(2), 12);
+    }
+
+    #[test]
+    fn test_many_blocks_in_sparse_vec() {
+        let mut v: SparseVec<CompressedBlock<RunLength, AlignedVec<u8, 64>>, DefaultValue> =
+            SparseVec::default();
+        for i in (0..6400).step_by(7) {
+            v.store(i, (i % 3) as u8 + 1);
+        }
+
+        for i in 0..6400 {
+            let expected = if i % 7 == 0 { (i % 3) as u8 + 1 } else { 0 };
+            assert_eq!(v.fetch(i), expected);
+        }
+    }
+}
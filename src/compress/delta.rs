@@ -0,0 +1,134 @@
+use super::{BlockCodec, PackedInteger};
+
+/// Delta encoding: each element is stored as a variable-length difference from the previous element.
+/// Works best for sorted or slowly-changing columns, such as ids and timestamps.
+pub struct Delta;
+
+/// A block of delta-encoded integers.
+#[derive(Clone)]
+pub struct DeltaEncoded {
+    /// The first element, or `None` if the block is empty.
+    first: Option<u64>,
+    /// Zigzag-encoded differences, each as a little-endian base-128 varint.
+    bytes: Vec<u8>,
+}
+
+impl DeltaEncoded {
+    /// The size of the encoded differences, in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+pub(crate) fn zigzag(delta: i64) -> u64 {
+    ((delta << 1) ^ (delta >> 63)) as u64
+}
+
+pub(crate) fn unzigzag(z: u64) -> i64 {
+    ((z >> 1) as i64) ^ -((z & 1) as i64)
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        out.push(x as u8 | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+/// Read a varint starting at `*at`, advancing `*at` past it.
+pub(crate) fn read_varint(bytes: &[u8], at: &mut usize) -> u64 {
+    let mut result = 0;
+    let mut shift = 0;
+    loop {
+        let b = bytes[*at];
+        *at += 1;
+        result |= ((b & 0x7f) as u64) << shift;
+        if b < 0x80 {
+            return result;
+        }
+        shift += 7;
+    }
+}
+
+impl<T> BlockCodec<T> for Delta
+where
+    T: PackedInteger,
+{
+    type Encoded = DeltaEncoded;
+
+    fn encode(values: &[T]) -> DeltaEncoded {
+        let first = values.first().map(|v| v.to_bits());
+        let mut bytes = Vec::new();
+        let mut previous = first.unwrap_or(0);
+        for v in values.iter().skip(1) {
+            let bits = v.to_bits();
+            write_varint(&mut bytes, zigzag(bits.wrapping_sub(previous) as i64));
+            previous = bits;
+        }
+        DeltaEncoded { first, bytes }
+    }
+
+    fn decode(encoded: &DeltaEncoded, out: &mut Vec<T>) {
+        let Some(mut previous) = encoded.first else {
+            return;
+        };
+        let mut at = 0;
+        out.push(T::from_bits(previous));
+        while at < encoded.bytes.len() {
+            let delta = unzigzag(read_varint(&encoded.bytes, &mut at));
+            previous = previous.wrapping_add(delta as u64);
+            out.push(T::from_bits(previous));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{unzigzag, zigzag, Delta};
+    use crate::compress::BlockCodec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_zigzag() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(unzigzag(zigzag(i64::MIN)), i64::MIN);
+        assert_eq!(unzigzag(zigzag(i64::MAX)), i64::MAX);
+    }
+
+    #[test]
+    fn test_sorted_is_small() {
+        let values: Vec<u64> = (1_700_000_000..1_700_001_000).collect();
+        let encoded = <Delta as BlockCodec<u64>>::encode(&values);
+
+        assert_eq!(encoded.encoded_len(), 999);
+    }
+
+    #[test]
+    fn test_empty() {
+        let encoded = <Delta as BlockCodec<u32>>::encode(&[]);
+        let mut decoded: Vec<u32> = vec![];
+        Delta::decode(&encoded, &mut decoded);
+        assert!(decoded.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_i32(values in proptest::collection::vec(any::<i32>(), 0..100)) {
+            let encoded = Delta::encode(&values);
+            let mut decoded = vec![];
+            Delta::decode(&encoded, &mut decoded);
+            assert_eq!(values, decoded);
+        }
+
+        #[test]
+        fn test_round_trip_u64(values in proptest::collection::vec(any::<u64>(), 0..100)) {
+            let encoded = Delta::encode(&values);
+            let mut decoded = vec![];
+            Delta::decode(&encoded, &mut decoded);
+            assert_eq!(values, decoded);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::block::{AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockSize, IndexedBlock};
+
+use super::{read_varint, unzigzag, write_varint, zigzag};
+
+/// An AlignedBlock of N `u64`s, stored as a base value plus variable-length deltas between consecutive elements.
+/// This is very compact for sorted or slowly-changing columns, such as ids and timestamps.
+///
+/// Every K elements, the block records a checkpoint: the full value at that element and where its deltas begin.
+/// Fetching an element decodes forward from the nearest preceding checkpoint, so it takes at most K steps rather than N.
+#[derive(Clone)]
+pub struct DeltaBlock<const N: usize, const K: usize = 64> {
+    position: usize,
+    /// The value of every Kth element, and the offset into `bytes` of the delta that follows it.
+    checkpoints: Vec<(u64, usize)>,
+    /// Zigzag-encoded deltas, each as a varint.
+    bytes: Vec<u8>,
+}
+
+impl<const N: usize, const K: usize> DeltaBlock<N, K> {
+    /// Encode a slice of exactly N values, starting at the given position.
+    pub fn new_from(position: usize, values: &[u64]) -> Self {
+        assert_eq!(values.len(), N);
+        assert!(position % N == 0);
+        assert!(K > 0, "checkpoint interval must not be zero");
+
+        let mut checkpoints = Vec::with_capacity(N.div_ceil(K));
+        let mut bytes = Vec::new();
+        let mut previous = 0;
+        for (i, v) in values.iter().enumerate() {
+            if i % K == 0 {
+                checkpoints.push((*v, bytes.len()));
+            } else {
+                write_varint(&mut bytes, zigzag(v.wrapping_sub(previous) as i64));
+            }
+            previous = *v;
+        }
+
+        DeltaBlock {
+            position,
+            checkpoints,
+            bytes,
+        }
+    }
+
+    /// Iterator over every element of this block, decoding each delta only once.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut at = 0;
+        let mut previous = 0;
+        (0..N).map(move |i| {
+            previous = if i % K == 0 {
+                let (value, offset) = self.checkpoints[i / K];
+                at = offset;
+                value
+            } else {
+                previous.wrapping_add(unzigzag(read_varint(&self.bytes, &mut at)) as u64)
+            };
+            previous
+        })
+    }
+}
+
+/// The checkpoints and deltas are on the heap, so they are counted along with the block itself.
+impl<const N: usize, const K: usize> BlockSize for DeltaBlock<N, K> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.bytes.len()
+            + self.checkpoints.len() * std::mem::size_of::<(u64, usize)>()
+    }
+}
+
+impl<const N: usize, const K: usize> IndexedBlock for DeltaBlock<N, K> {
+    type Index = usize;
+    type Item = u64;
+}
+
+impl<const N: usize, const K: usize> AlignedBlock for DeltaBlock<N, K> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<const N: usize, const K: usize> BlockFetch for DeltaBlock<N, K> {
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        assert!(index >= self.position);
+        let i = index - self.position;
+        assert!(i < N);
+
+        let (mut value, mut at) = self.checkpoints[i / K];
+        for _ in 0..i % K {
+            value = value.wrapping_add(unzigzag(read_varint(&self.bytes, &mut at)) as u64);
+        }
+        value
+    }
+}
+
+impl<const N: usize, const K: usize> AlignedBlockFromIterator for DeltaBlock<N, K> {
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let values: Vec<u64> = iter.take(N).collect();
+        assert_eq!(
+            values.len(),
+            N,
+            "iterator to contain at least as many elements as Self::alignment()"
+        );
+        Self::new_from(position, &values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeltaBlock;
+    use crate::block::{AlignedBlockFromIterator, BlockFetch, BlockSize, DenseVec};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_timestamps() {
+        let values: Vec<u64> = (0..1000).map(|i| 1_700_000_000_000 + i * 15).collect();
+        let b: DeltaBlock<1000, 100> = DeltaBlock::new_from(2000, &values);
+
+        assert_eq!(b.fetch(2000), values[0]);
+        assert_eq!(b.fetch(2099), values[99]);
+        assert_eq!(b.fetch(2100), values[100]);
+        assert_eq!(b.fetch(2999), values[999]);
+        assert!(b.size_in_bytes() < std::mem::size_of::<DeltaBlock<1000, 100>>() + 1000 + 10 * 16);
+    }
+
+    #[test]
+    fn test_dense_vec() {
+        let v = DenseVec::new_from(vec![
+            DeltaBlock::<16>::from_iterator(0, &mut (100..)),
+            DeltaBlock::<16>::from_iterator(16, &mut (0..50).rev()),
+        ]);
+
+        assert_eq!(v.fetch(15), 115);
+        assert_eq!(v.fetch(16), 49);
+        assert_eq!(v.fetch(31), 34);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(values in proptest::collection::vec(any::<u64>(), 50)) {
+            let b: DeltaBlock<50, 7> = DeltaBlock::new_from(0, &values);
+            assert_eq!(b.iter().collect::<Vec<_>>(), values.clone());
+            for (i, v) in values.iter().enumerate() {
+                assert_eq!(b.fetch(i), *v);
+            }
+        }
+    }
+}
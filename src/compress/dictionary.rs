@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use crate::block::{
+    AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockGet, BlockStore, IndexedBlock,
+    PackedBlock,
+};
+
+/// A mapping between distinct values and small integer codes, assigned in order of first appearance.
+/// Values are never removed, even if nothing refers to them any more.
+#[derive(Clone)]
+pub struct Dictionary<V> {
+    values: Vec<V>,
+    codes: BTreeMap<V, u32>,
+}
+
+impl<V> Default for Dictionary<V> {
+    fn default() -> Self {
+        Dictionary {
+            values: vec![],
+            codes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V> Dictionary<V>
+where
+    V: Ord + Clone,
+{
+    /// The code for a value, adding the value to the dictionary if it is not already present.
+    pub fn intern(&mut self, v: V) -> u32 {
+        if let Some(code) = self.codes.get(&v) {
+            return *code;
+        }
+        let code = u32::try_from(self.values.len())
+            .expect("dictionary should have fewer than 2^32 values");
+        self.values.push(v.clone());
+        self.codes.insert(v, code);
+        code
+    }
+
+    /// The code for a value, if the value is present.
+    pub fn code_of(&self, v: &V) -> Option<u32> {
+        self.codes.get(v).copied()
+    }
+}
+
+impl<V> Dictionary<V> {
+    /// The value for a code.
+    pub fn value(&self, code: u32) -> &V {
+        &self.values[code as usize]
+    }
+
+    /// The number of distinct values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Every value, in order by code.
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+}
+
+/// An AlignedBlock of N values, stored as BITS-bit codes into a dictionary that belongs to this block.
+/// A block can hold at most 2^BITS distinct values over its lifetime, since overwritten values are not removed from the dictionary.
+///
+/// Items do not need to be `Copy`. `BlockGet::get` returns a reference to an element, while `fetch` returns a clone.
+/// Elements can't be mutably borrowed, because they are shared through the dictionary.
+#[derive(Clone)]
+pub struct DictionaryBlock<V, const BITS: usize, const N: usize> {
+    codes: PackedBlock<BITS, N, u32>,
+    dictionary: Dictionary<V>,
+}
+
+impl<V, const BITS: usize, const N: usize> DictionaryBlock<V, BITS, N> {
+    /// The dictionary of this block.
+    pub fn dictionary(&self) -> &Dictionary<V> {
+        &self.dictionary
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> DictionaryBlock<V, BITS, N>
+where
+    V: Ord + Clone,
+{
+    fn intern(&mut self, v: V) -> u32 {
+        if let Some(code) = self.dictionary.code_of(&v) {
+            return code;
+        }
+        // Check the width before interning, so that a value that doesn't fit isn't left in the dictionary.
+        assert!(
+            BITS >= 32 || self.dictionary.len() >> BITS == 0,
+            "too many distinct values for the code width"
+        );
+        self.dictionary.intern(v)
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> IndexedBlock for DictionaryBlock<V, BITS, N> {
+    type Index = usize;
+    type Item = V;
+}
+
+impl<V, const BITS: usize, const N: usize> AlignedBlock for DictionaryBlock<V, BITS, N> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        self.codes.position()
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> BlockGet for DictionaryBlock<V, BITS, N> {
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        let offset = index.checked_sub(self.position())?;
+        (offset < N).then(|| self.dictionary.value(self.codes.fetch(index)))
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> BlockFetch for DictionaryBlock<V, BITS, N>
+where
+    V: Clone,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.dictionary.value(self.codes.fetch(index)).clone()
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> BlockStore for DictionaryBlock<V, BITS, N>
+where
+    V: Ord + Clone,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let code = self.intern(item);
+        self.codes.store(index, code);
+    }
+}
+
+impl<V, const BITS: usize, const N: usize> AlignedBlockFromIterator for DictionaryBlock<V, BITS, N>
+where
+    V: Ord + Clone,
+{
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let mut result = DictionaryBlock {
+            codes: PackedBlock::new(position),
+            dictionary: Dictionary::default(),
+        };
+        for offset in 0..N {
+            let item = iter
+                .next()
+                .expect("iterator to contain at least as many elements as Self::alignment()");
+            result.store(position + offset, item);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DictionaryBlock;
+    use crate::block::{
+        AlignedBlockFromIterator, BlockFetch, BlockGet, BlockStore, DefaultValue, DenseVec,
+        SparseVec,
+    };
+
+    #[test]
+    fn test_strings() {
+        let words = ["red", "green", "red", "blue", "red", "green", "red", "red"];
+        let mut b: DictionaryBlock<String, 2, 8> =
+            DictionaryBlock::from_iterator(8, &mut words.iter().map(|s| s.to_string()));
+
+        assert_eq!(b.dictionary().len(), 3);
+        assert_eq!(b.get(9).unwrap(), "green");
+        assert_eq!(b.get(16), None);
+        assert_eq!(b.fetch(11), "blue".to_string());
+
+        b.store(9, "blue".to_string());
+        assert_eq!(b.get(9).unwrap(), "blue");
+        assert_eq!(b.dictionary().len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many distinct values for the code width")]
+    fn test_too_many_values() {
+        let _: DictionaryBlock<u64, 2, 8> = DictionaryBlock::from_iterator(0, &mut (0..));
+    }
+
+    #[test]
+    fn test_collections() {
+        let v = DenseVec::new_from(vec![DictionaryBlock::<String, 4, 4>::from_iterator(
+            0,
+            &mut ["a", "b", "a", "c"].iter().map(|s| s.to_string()),
+        )]);
+        assert_eq!(v.fetch(2), "a");
+
+        let mut v: SparseVec<DictionaryBlock<String, 4, 64>, DefaultValue> = SparseVec::default();
+        v.store(1_000_000, "hello".to_string());
+        assert_eq!(v.fetch(1_000_000), "hello");
+        assert_eq!(v.fetch(1_000_001), "");
+        assert_eq!(v.fetch(5), "");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::block::{BlockFetch, BlockGet, BlockStore, IndexedBlock};
+
+use super::Dictionary;
+
+/// A collection of codes, such as a `DenseVec` or `SparseVec` of `PackedBlock`s, that shares one dictionary across all of its blocks.
+///
+/// Code zero always refers to the default value given at construction, so that absent blocks of a `SparseVec`,
+/// which hold code zero, read back as the default value.
+pub struct DictionaryEncoded<C, V> {
+    codes: C,
+    dictionary: Dictionary<V>,
+}
+
+impl<C, V> DictionaryEncoded<C, V>
+where
+    C: BlockFetch<Item = u32>,
+    V: Ord + Clone,
+{
+    /// Wrap a collection of codes, all of which should be zero.
+    pub fn new(codes: C, default_value: V) -> Self {
+        let mut dictionary = Dictionary::default();
+        dictionary.intern(default_value);
+        DictionaryEncoded { codes, dictionary }
+    }
+
+    /// The underlying collection of codes.
+    pub fn codes(&self) -> &C {
+        &self.codes
+    }
+
+    /// The shared dictionary.
+    pub fn dictionary(&self) -> &Dictionary<V> {
+        &self.dictionary
+    }
+}
+
+impl<C, V> IndexedBlock for DictionaryEncoded<C, V>
+where
+    C: IndexedBlock,
+{
+    type Index = C::Index;
+    type Item = V;
+}
+
+/// Every index has a code, so this always returns `Some`.
+impl<C, V> BlockGet for DictionaryEncoded<C, V>
+where
+    C: BlockFetch<Item = u32>,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        Some(self.dictionary.value(self.codes.fetch(index)))
+    }
+}
+
+impl<C, V> BlockFetch for DictionaryEncoded<C, V>
+where
+    C: BlockFetch<Item = u32>,
+    V: Clone,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.dictionary.value(self.codes.fetch(index)).clone()
+    }
+}
+
+impl<C, V> BlockStore for DictionaryEncoded<C, V>
+where
+    C: BlockFetch<Item = u32> + BlockStore,
+    V: Ord + Clone,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        if let Some(code) = self.dictionary.code_of(&item) {
+            self.codes.store(index, code);
+            return;
+        }
+        // Store the new code before interning, so that a code the collection can't hold doesn't leave a dangling value in the dictionary.
+        let code = u32::try_from(self.dictionary.len())
+            .expect("dictionary should have fewer than 2^32 values");
+        self.codes.store(index, code);
+        self.dictionary.intern(item);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DictionaryEncoded;
+    use crate::block::{BlockFetch, BlockGet, BlockStore, DefaultValue, PackedBlock, SparseVec};
+
+    #[test]
+    fn test_shared_dictionary() {
+        let codes: SparseVec<PackedBlock<3, 16>, DefaultValue> = SparseVec::default();
+        let mut v = DictionaryEncoded::new(codes, "unknown".to_string());
+
+        v.store(3, "US".to_string());
+        v.store(100, "FR".to_string());
+        v.store(1_000, "US".to_string());
+
+        assert_eq!(v.get(3).unwrap(), "US");
+        assert_eq!(v.get(4).unwrap(), "unknown");
+        assert_eq!(v.fetch(100), "FR");
+        assert_eq!(v.get(1_000).unwrap(), "US");
+        assert_eq!(v.get(5_000).unwrap(), "unknown");
+        assert_eq!(v.dictionary().len(), 3);
+        assert_eq!(v.codes().fetch(1_000), 1);
+    }
+
+    #[test]
+    fn test_code_too_wide() {
+        let codes: SparseVec<PackedBlock<1, 16>, DefaultValue> = SparseVec::default();
+        let mut v = DictionaryEncoded::new(codes, 'a');
+        v.store(0, 'b');
+
+        let stored = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| v.store(1, 'c')));
+        assert!(stored.is_err());
+        assert_eq!(v.dictionary().len(), 2);
+        assert_eq!(v.dictionary().code_of(&'c'), None);
+        assert_eq!(v.fetch(1), 'a');
+    }
+}
@@ -0,0 +1,91 @@
+use crate::block::{AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockSize, IndexedBlock};
+
+use super::{BitPack, BitPacked, BlockCodec};
+
+/// An AlignedBlock of N `u64`s, stored with frame-of-reference encoding:
+/// the smallest element, plus each element's difference from it packed into as few bits as possible.
+/// Every element can be fetched directly, without decoding the rest of the block.
+#[derive(Clone)]
+pub struct ForBlock<const N: usize> {
+    position: usize,
+    packed: BitPacked,
+}
+
+impl<const N: usize> ForBlock<N> {
+    /// Encode a slice of exactly N values, starting at the given position.
+    pub fn new_from(position: usize, values: &[u64]) -> Self {
+        assert_eq!(values.len(), N);
+        assert!(position % N == 0);
+        ForBlock {
+            position,
+            packed: BitPack::encode(values),
+        }
+    }
+
+    /// The number of bits used for each element.
+    pub fn width(&self) -> u32 {
+        self.packed.width()
+    }
+}
+
+/// The packed words are on the heap, so they are counted along with the block itself.
+impl<const N: usize> BlockSize for ForBlock<N> {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + std::mem::size_of::<u64>() * (self.width() as usize * N).div_ceil(64)
+    }
+}
+
+impl<const N: usize> IndexedBlock for ForBlock<N> {
+    type Index = usize;
+    type Item = u64;
+}
+
+impl<const N: usize> AlignedBlock for ForBlock<N> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<const N: usize> BlockFetch for ForBlock<N> {
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        assert!(index >= self.position);
+        self.packed.get(index - self.position)
+    }
+}
+
+impl<const N: usize> AlignedBlockFromIterator for ForBlock<N> {
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let values: Vec<u64> = iter.take(N).collect();
+        assert_eq!(
+            values.len(),
+            N,
+            "iterator to contain at least as many elements as Self::alignment()"
+        );
+        Self::new_from(position, &values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ForBlock;
+    use crate::block::{BlockFetch, BlockSize};
+
+    #[test]
+    fn test_fetch() {
+        let values: Vec<u64> = vec![5000, 5003, 5001, 5255];
+        let b: ForBlock<4> = ForBlock::new_from(8, &values);
+
+        assert_eq!(b.width(), 8);
+        assert_eq!(b.size_in_bytes(), std::mem::size_of::<ForBlock<4>>() + 8);
+        assert_eq!(b.fetch(8), 5000);
+        assert_eq!(b.fetch(11), 5255);
+    }
+}
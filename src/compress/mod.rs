@@ -0,0 +1,21 @@
+mod adaptive_block;
+mod bit_pack;
+mod codec;
+mod compressed_block;
+mod delta;
+mod delta_block;
+mod dictionary;
+mod dictionary_encoded;
+mod for_block;
+mod run_length;
+
+pub use adaptive_block::*;
+pub use bit_pack::*;
+pub use codec::*;
+pub use compressed_block::*;
+pub use delta::*;
+pub use delta_block::*;
+pub use dictionary::*;
+pub use dictionary_encoded::*;
+pub use for_block::*;
+pub use run_length::*;
@@ -0,0 +1,41 @@
+use crate::rle::Rle;
+
+use super::BlockCodec;
+
+/// Run-length encoding: consecutive repetitions of a value are stored once, with a count.
+/// Works for any element type, not only integers.
+pub struct RunLength;
+
+impl<T> BlockCodec<T> for RunLength
+where
+    T: Copy + Eq,
+{
+    type Encoded = Rle<T>;
+
+    fn encode(values: &[T]) -> Rle<T> {
+        let mut rle = Rle::default();
+        rle.extend(values.iter().copied());
+        rle
+    }
+
+    fn decode(encoded: &Rle<T>, out: &mut Vec<T>) {
+        out.extend(encoded.iterator().copied());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RunLength;
+    use crate::compress::BlockCodec;
+
+    #[test]
+    fn test_round_trip() {
+        let values = vec!['a', 'a', 'a', 'b', 'a', 'c', 'c'];
+        let encoded = RunLength::encode(&values);
+        let mut decoded = vec![];
+        RunLength::decode(&encoded, &mut decoded);
+
+        assert_eq!(encoded.run_iterator().count(), 4);
+        assert_eq!(values, decoded);
+    }
+}
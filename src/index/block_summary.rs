@@ -0,0 +1,257 @@
+use std::ops::RangeInclusive;
+
+use crate::block::{AlignedBlock, BlockFetch};
+
+/// The smallest and largest element of a single block.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BlockBounds<V> {
+    /// Position of the block.
+    pub position: usize,
+    /// The smallest element of the block.
+    pub min: V,
+    /// The largest element of the block.
+    pub max: V,
+}
+
+/// The minimum and maximum of every block of a collection, in the same order as the blocks.
+///
+/// When the elements of the blocks are sorted, the summary supports searches that binary search the block bounds first,
+/// and then binary search inside of a single block. The blocks are passed to each query, and must be the same blocks that
+/// the summary was built from, such as `DenseVec::blocks` or `SparseVec::blocks`.
+/// For a `SparseVec`, only stored blocks are searched. Elements of absent blocks are never returned.
+pub struct BlockSummary<V> {
+    bounds: Vec<BlockBounds<V>>,
+}
+
+impl<V> BlockSummary<V>
+where
+    V: Copy + Ord,
+{
+    /// Summarize each of the given blocks.
+    pub fn new<B>(blocks: &[B]) -> Self
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        BlockSummary {
+            bounds: blocks.iter().map(Self::summarize).collect(),
+        }
+    }
+
+    fn summarize<B>(block: &B) -> BlockBounds<V>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        let position = block.position();
+        let mut min = block.fetch(position);
+        let mut max = min;
+        for offset in 1..B::alignment() {
+            let v = block.fetch(position + offset);
+            min = min.min(v);
+            max = max.max(v);
+        }
+        BlockBounds { position, min, max }
+    }
+
+    /// The bounds of every block, in order by position.
+    pub fn bounds(&self) -> &[BlockBounds<V>] {
+        &self.bounds
+    }
+
+    /// Recompute the bounds of a single block after it has been modified or newly inserted.
+    pub fn update_block<B>(&mut self, block: &B)
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        let bounds = Self::summarize(block);
+        match self
+            .bounds
+            .binary_search_by_key(&bounds.position, |b| b.position)
+        {
+            Ok(k) => self.bounds[k] = bounds,
+            Err(k) => self.bounds.insert(k, bounds),
+        }
+    }
+
+    /// Position of the first element that is not less than `v`.
+    pub fn lower_bound<B>(&self, blocks: &[B], v: &V) -> Option<usize>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        self.first_where(blocks, |x| x >= v)
+    }
+
+    /// Position of the first element that is greater than `v`.
+    pub fn upper_bound<B>(&self, blocks: &[B], v: &V) -> Option<usize>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        self.first_where(blocks, |x| x > v)
+    }
+
+    /// Positions of the first and last elements that are equal to `v`.
+    pub fn equal_range<B>(&self, blocks: &[B], v: &V) -> Option<RangeInclusive<usize>>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        let first = self.lower_bound(blocks, v)?;
+        let last = self.last_where(blocks, |x| x <= v)?;
+        if blocks[self.block_of(first)].fetch(first) == *v {
+            Some(first..=last)
+        } else {
+            None
+        }
+    }
+
+    /// Position and value of the last element that is less than `v`.
+    pub fn predecessor<B>(&self, blocks: &[B], v: &V) -> Option<(usize, V)>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        let i = self.last_where(blocks, |x| x < v)?;
+        Some((i, blocks[self.block_of(i)].fetch(i)))
+    }
+
+    /// Position and value of the first element that is greater than `v`.
+    pub fn successor<B>(&self, blocks: &[B], v: &V) -> Option<(usize, V)>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        let i = self.upper_bound(blocks, v)?;
+        Some((i, blocks[self.block_of(i)].fetch(i)))
+    }
+
+    /// Which block (by offset into the block slice) contains the given position.
+    fn block_of(&self, i: usize) -> usize {
+        self.bounds.partition_point(|b| b.position <= i) - 1
+    }
+
+    /// Position of the first element satisfying `pred`, where `pred` must be false for a prefix of the elements and true for the rest.
+    fn first_where<B>(&self, blocks: &[B], pred: impl Fn(&V) -> bool) -> Option<usize>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        assert_eq!(blocks.len(), self.bounds.len(), "summary must match blocks");
+        let k = self.bounds.partition_point(|b| !pred(&b.max));
+        let block = blocks.get(k)?;
+        let position = block.position();
+        let j = partition_point(B::alignment(), |j| !pred(&block.fetch(position + j)));
+        Some(position + j)
+    }
+
+    /// Position of the last element satisfying `pred`, where `pred` must be true for a prefix of the elements and false for the rest.
+    fn last_where<B>(&self, blocks: &[B], pred: impl Fn(&V) -> bool) -> Option<usize>
+    where
+        B: AlignedBlock<Index = usize, Item = V> + BlockFetch,
+    {
+        assert_eq!(blocks.len(), self.bounds.len(), "summary must match blocks");
+        let k = self
+            .bounds
+            .partition_point(|b| pred(&b.min))
+            .checked_sub(1)?;
+        let block = &blocks[k];
+        let position = block.position();
+        let j = partition_point(B::alignment(), |j| pred(&block.fetch(position + j)));
+        Some(position + (j - 1))
+    }
+}
+
+/// Like `slice::partition_point`, over the offsets `0..len`.
+fn partition_point(len: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockSummary;
+    use crate::block::{
+        AlignedVec, BlockCollection, BlockStore, DefaultValue, DenseVec, SparseVec,
+    };
+    use proptest::prelude::*;
+
+    fn dense(values: &[u64]) -> DenseVec<AlignedVec<u64, 4>> {
+        DenseVec::new_from(
+            values
+                .chunks_exact(4)
+                .enumerate()
+                .map(|(i, chunk)| AlignedVec::new_from(i * 4, chunk.to_vec()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_queries() {
+        let v = dense(&[1, 3, 3, 3, 3, 3, 5, 8, 8, 9, 12, 12]);
+        let summary = BlockSummary::new(v.blocks());
+
+        assert_eq!(summary.bounds()[1].min, 3);
+        assert_eq!(summary.bounds()[1].max, 8);
+
+        assert_eq!(summary.lower_bound(v.blocks(), &0), Some(0));
+        assert_eq!(summary.lower_bound(v.blocks(), &3), Some(1));
+        assert_eq!(summary.upper_bound(v.blocks(), &3), Some(6));
+        assert_eq!(summary.lower_bound(v.blocks(), &13), None);
+        assert_eq!(summary.upper_bound(v.blocks(), &12), None);
+
+        assert_eq!(summary.equal_range(v.blocks(), &3), Some(1..=5));
+        assert_eq!(summary.equal_range(v.blocks(), &12), Some(10..=11));
+        assert_eq!(summary.equal_range(v.blocks(), &4), None);
+
+        assert_eq!(summary.predecessor(v.blocks(), &8), Some((6, 5)));
+        assert_eq!(summary.predecessor(v.blocks(), &1), None);
+        assert_eq!(summary.successor(v.blocks(), &8), Some((9, 9)));
+        assert_eq!(summary.successor(v.blocks(), &12), None);
+    }
+
+    #[test]
+    fn test_sparse_vec() {
+        let mut v: SparseVec<AlignedVec<u64, 4>, DefaultValue> = SparseVec::default();
+        for (i, t) in [(400, 10), (401, 11), (402, 12), (403, 13), (11, 1)] {
+            v.store(i, t);
+        }
+        let summary = BlockSummary::new(v.blocks());
+
+        assert_eq!(summary.lower_bound(v.blocks(), &2), Some(400));
+        assert_eq!(summary.predecessor(v.blocks(), &10), Some((11, 1)));
+        assert_eq!(summary.equal_range(v.blocks(), &0), Some(8..=10));
+    }
+
+    #[test]
+    fn test_update_block() {
+        let mut v = dense(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut summary = BlockSummary::new(v.blocks());
+
+        v.store(7, 100);
+        summary.update_block(&v.blocks()[1]);
+
+        assert_eq!(summary.bounds()[1].max, 100);
+        assert_eq!(summary.lower_bound(v.blocks(), &9), Some(7));
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_slice(mut values in proptest::collection::vec(0..32_u64, 0..64), probe in 0..34_u64) {
+            values.truncate(values.len() / 4 * 4);
+            values.sort();
+            let v = dense(&values);
+            let summary = BlockSummary::new(v.blocks());
+
+            let lower = values.partition_point(|x| *x < probe);
+            let upper = values.partition_point(|x| *x <= probe);
+
+            assert_eq!(summary.lower_bound(v.blocks(), &probe), Some(lower).filter(|i| *i < values.len()));
+            assert_eq!(summary.upper_bound(v.blocks(), &probe), Some(upper).filter(|i| *i < values.len()));
+            assert_eq!(summary.equal_range(v.blocks(), &probe), Some(lower..=upper.wrapping_sub(1)).filter(|_| lower < upper));
+            assert_eq!(summary.predecessor(v.blocks(), &probe), lower.checked_sub(1).map(|i| (i, values[i])));
+            assert_eq!(summary.successor(v.blocks(), &probe), values.get(upper).map(|x| (upper, *x)));
+        }
+    }
+}
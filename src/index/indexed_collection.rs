@@ -0,0 +1,179 @@
+use crate::{
+    bitset::SparseBitset,
+    block::{
+        AlignedBlock, BlockCollection, BlockEnumerate, BlockFetch, BlockGet, BlockStore,
+        IndexedBlock,
+    },
+    numerical_index::NumericalIndex,
+};
+
+use super::ValueIndex;
+
+/// A collection that keeps a `ValueIndex` up to date as elements are stored.
+pub struct IndexedCollection<C>
+where
+    C: IndexedBlock,
+{
+    collection: C,
+    index: ValueIndex<C::Index, C::Item>,
+}
+
+impl<C> IndexedCollection<C>
+where
+    C: BlockEnumerate + BlockFetch + BlockStore,
+    C::Index: Copy + Ord,
+    C::Item: Copy + Ord,
+    SparseBitset<C::Index>: Default + BlockFetch<Index = C::Index, Item = bool> + BlockStore,
+{
+    /// Wrap a collection, building an index over all of its elements.
+    /// For a `SparseVec`, only elements of stored blocks are indexed.
+    pub fn new(collection: C) -> Self {
+        let index = ValueIndex::from_collection(&collection);
+        IndexedCollection { collection, index }
+    }
+
+    /// The underlying collection.
+    pub fn collection(&self) -> &C {
+        &self.collection
+    }
+
+    /// The index over the underlying collection.
+    pub fn index(&self) -> &ValueIndex<C::Index, C::Item> {
+        &self.index
+    }
+
+    /// Unwrap back into the underlying collection, discarding the index.
+    pub fn into_inner(self) -> C {
+        self.collection
+    }
+}
+
+impl<C> IndexedBlock for IndexedCollection<C>
+where
+    C: IndexedBlock,
+{
+    type Index = C::Index;
+    type Item = C::Item;
+}
+
+impl<C> BlockFetch for IndexedCollection<C>
+where
+    C: BlockFetch,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.collection.fetch(index)
+    }
+}
+
+impl<C> BlockGet for IndexedCollection<C>
+where
+    C: BlockGet,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.collection.get(index)
+    }
+}
+
+/// Storing an element moves its position from the index entry of the old value to the entry of the new value.
+/// When a store materializes a block of a `SparseVec`, every element of that block is indexed, as `ValueIndex::from_collection` would.
+impl<C> BlockStore for IndexedCollection<C>
+where
+    C: BlockCollection + BlockEnumerate + BlockFetch + BlockStore,
+    C::Index: NumericalIndex,
+    C::Item: Copy + Ord,
+    SparseBitset<C::Index>: Default + BlockFetch<Index = C::Index, Item = bool> + BlockStore,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let old = self.collection.fetch(index);
+        self.index.remove(index, &old);
+        let block_count = self.collection.blocks().len();
+        self.collection.store(index, item);
+        if self.collection.blocks().len() > block_count {
+            let alignment = C::Block::alignment();
+            for i in index.block(alignment).range(alignment) {
+                self.index.insert(i, self.collection.fetch(i));
+            }
+        } else {
+            self.index.insert(index, item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexedCollection;
+    use crate::block::{AlignedVec, BlockFetch, BlockStore, DefaultValue, DenseVec, SparseVec};
+    use crate::index::ValueIndex;
+
+    #[test]
+    fn test_store_updates_index() {
+        let v = DenseVec::new_from(vec![
+            AlignedVec::<u32, 4>::new_from(0, vec![5, 5, 6, 6]),
+            AlignedVec::<u32, 4>::new_from(4, vec![7, 5, 6, 7]),
+        ]);
+        let mut indexed = IndexedCollection::new(v);
+
+        indexed.store(1, 7);
+        indexed.store(4, 8);
+
+        assert_eq!(indexed.fetch(1), 7);
+        assert_eq!(
+            indexed
+                .index()
+                .positions(&5)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![0, 5]
+        );
+        assert_eq!(
+            indexed
+                .index()
+                .positions(&7)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![1, 7]
+        );
+        assert_eq!(
+            indexed
+                .index()
+                .positions(&8)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![4]
+        );
+    }
+
+    #[test]
+    fn test_store_materializes_block() {
+        let v: SparseVec<AlignedVec<u8, 4>, DefaultValue> = SparseVec::default();
+        let mut indexed = IndexedCollection::new(v);
+        indexed.store(9, 7);
+        indexed.store(10, 7);
+        indexed.store(11, 0);
+
+        let rebuilt = ValueIndex::from_collection(indexed.collection());
+        for v in [0, 7] {
+            assert_eq!(
+                indexed
+                    .index()
+                    .positions(&v)
+                    .unwrap()
+                    .iter()
+                    .collect::<Vec<_>>(),
+                rebuilt.positions(&v).unwrap().iter().collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(
+            indexed
+                .index()
+                .positions(&0)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![8, 11]
+        );
+    }
+}
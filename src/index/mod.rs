@@ -0,0 +1,11 @@
+mod block_summary;
+mod indexed_collection;
+mod segment_tree;
+mod value_index;
+mod zone_map;
+
+pub use block_summary::*;
+pub use indexed_collection::*;
+pub use segment_tree::*;
+pub use value_index::*;
+pub use zone_map::*;
@@ -0,0 +1,375 @@
+use std::{
+    marker::PhantomData,
+    ops::{Add, Bound, RangeBounds},
+};
+
+use crate::block::{
+    AlignedBlock, BlockCollection, BlockFetch, BlockFetchIterator, BlockGet, BlockStore, DenseVec,
+    IndexedBlock,
+};
+
+/// An associative operation with an identity, used to aggregate ranges of elements.
+/// The operation does not need to be commutative.
+pub trait Monoid {
+    /// Type of the elements being aggregated.
+    type Item;
+    /// Type of the aggregate.
+    type Summary: Copy;
+
+    /// The aggregate of an empty range.
+    fn identity(&self) -> Self::Summary;
+    /// The aggregate of a single element.
+    fn summarize(&self, item: Self::Item) -> Self::Summary;
+    /// Combine the aggregates of two adjacent ranges, `a` being on the left.
+    fn combine(&self, a: Self::Summary, b: Self::Summary) -> Self::Summary;
+}
+
+/// Sum of elements.
+pub struct Sum<T>(PhantomData<T>);
+
+/// Minimum element, or `None` for an empty range.
+pub struct Min<T>(PhantomData<T>);
+
+/// Maximum element, or `None` for an empty range.
+pub struct Max<T>(PhantomData<T>);
+
+impl<T> Default for Sum<T> {
+    fn default() -> Self {
+        Sum(PhantomData)
+    }
+}
+
+impl<T> Default for Min<T> {
+    fn default() -> Self {
+        Min(PhantomData)
+    }
+}
+
+impl<T> Default for Max<T> {
+    fn default() -> Self {
+        Max(PhantomData)
+    }
+}
+
+impl<T> Monoid for Sum<T>
+where
+    T: Copy + Default + Add<Output = T>,
+{
+    type Item = T;
+    type Summary = T;
+
+    fn identity(&self) -> T {
+        T::default()
+    }
+
+    fn summarize(&self, item: T) -> T {
+        item
+    }
+
+    fn combine(&self, a: T, b: T) -> T {
+        a + b
+    }
+}
+
+impl<T> Monoid for Min<T>
+where
+    T: Copy + Ord,
+{
+    type Item = T;
+    type Summary = Option<T>;
+
+    fn identity(&self) -> Option<T> {
+        None
+    }
+
+    fn summarize(&self, item: T) -> Option<T> {
+        Some(item)
+    }
+
+    fn combine(&self, a: Option<T>, b: Option<T>) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+impl<T> Monoid for Max<T>
+where
+    T: Copy + Ord,
+{
+    type Item = T;
+    type Summary = Option<T>;
+
+    fn identity(&self) -> Option<T> {
+        None
+    }
+
+    fn summarize(&self, item: T) -> Option<T> {
+        Some(item)
+    }
+
+    fn combine(&self, a: Option<T>, b: Option<T>) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// A segment tree over the blocks of a `DenseVec`, for aggregating arbitrary ranges of elements.
+///
+/// Each block contributes a single leaf to the tree, so the tree is small compared to the collection.
+/// A query combines whole blocks through the tree and scans only the partial blocks at either end of the range.
+/// Storing an element rescans its block and then updates the path from that block's leaf to the root.
+pub struct SegmentTree<B, M>
+where
+    M: Monoid,
+{
+    vec: DenseVec<B>,
+    monoid: M,
+    /// Node `k` has children `2k` and `2k+1`. Leaves begin at `blocks`. Node 0 is unused.
+    tree: Vec<M::Summary>,
+}
+
+impl<B, M> SegmentTree<B, M>
+where
+    B: AlignedBlock<Index = usize> + BlockFetch,
+    M: Monoid<Item = B::Item>,
+{
+    /// Build a segment tree over a DenseVec.
+    pub fn new(vec: DenseVec<B>, monoid: M) -> Self {
+        let blocks = vec.blocks().len();
+        let mut tree = vec![monoid.identity(); 2 * blocks];
+        for (k, block) in vec.blocks().iter().enumerate() {
+            tree[blocks + k] = Self::summarize_block(&monoid, block);
+        }
+        for k in (1..blocks).rev() {
+            tree[k] = monoid.combine(tree[2 * k], tree[2 * k + 1]);
+        }
+        SegmentTree { vec, monoid, tree }
+    }
+
+    /// Unwrap back into the underlying DenseVec, discarding the tree.
+    pub fn into_inner(self) -> DenseVec<B> {
+        self.vec
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.vec.blocks().len() * B::alignment()
+    }
+
+    /// Aggregate every element in the given range.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> M::Summary {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(end <= self.len(), "range end out of bounds");
+        if start >= end {
+            return self.monoid.identity();
+        }
+
+        let n = B::alignment();
+        let (first, last) = (start / n, (end - 1) / n);
+        if first == last {
+            return self.scan(start, end);
+        }
+
+        let left = self.scan(start, (first + 1) * n);
+        let middle = self.query_blocks(first + 1, last);
+        let right = self.scan(last * n, end);
+        self.monoid
+            .combine(self.monoid.combine(left, middle), right)
+    }
+
+    /// Aggregate the elements `start..end`, one at a time.
+    fn scan(&self, start: usize, end: usize) -> M::Summary {
+        (start..end).fold(self.monoid.identity(), |acc, i| {
+            self.monoid
+                .combine(acc, self.monoid.summarize(self.vec.fetch(i)))
+        })
+    }
+
+    /// Aggregate whole blocks `first..last` using the tree.
+    fn query_blocks(&self, first: usize, last: usize) -> M::Summary {
+        let blocks = self.vec.blocks().len();
+        let (mut lo, mut hi) = (first + blocks, last + blocks);
+        let (mut left, mut right) = (self.monoid.identity(), self.monoid.identity());
+        while lo < hi {
+            if lo % 2 == 1 {
+                left = self.monoid.combine(left, self.tree[lo]);
+                lo += 1;
+            }
+            if hi % 2 == 1 {
+                hi -= 1;
+                right = self.monoid.combine(self.tree[hi], right);
+            }
+            lo /= 2;
+            hi /= 2;
+        }
+        self.monoid.combine(left, right)
+    }
+
+    fn summarize_block(monoid: &M, block: &B) -> M::Summary {
+        BlockFetchIterator::new(block).fold(monoid.identity(), |acc, v| {
+            monoid.combine(acc, monoid.summarize(v))
+        })
+    }
+}
+
+impl<B, M> IndexedBlock for SegmentTree<B, M>
+where
+    B: AlignedBlock<Index = usize>,
+    M: Monoid,
+{
+    type Index = usize;
+    type Item = B::Item;
+}
+
+impl<B, M> BlockFetch for SegmentTree<B, M>
+where
+    B: AlignedBlock<Index = usize> + BlockFetch,
+    M: Monoid,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.vec.fetch(index)
+    }
+}
+
+impl<B, M> BlockGet for SegmentTree<B, M>
+where
+    B: AlignedBlock<Index = usize> + BlockGet,
+    M: Monoid,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.vec.get(index)
+    }
+}
+
+impl<B, M> BlockStore for SegmentTree<B, M>
+where
+    B: AlignedBlock<Index = usize> + BlockFetch + BlockStore,
+    M: Monoid<Item = B::Item>,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        self.vec.store(index, item);
+
+        let blocks = self.vec.blocks().len();
+        let block = index / B::alignment();
+        let mut k = blocks + block;
+        self.tree[k] = Self::summarize_block(&self.monoid, &self.vec.blocks()[block]);
+        while k > 1 {
+            k /= 2;
+            self.tree[k] = self.monoid.combine(self.tree[2 * k], self.tree[2 * k + 1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Max, Min, Monoid, SegmentTree, Sum};
+    use crate::block::{AlignedVec, BlockStore, DenseVec};
+    use proptest::prelude::*;
+
+    fn dense(values: &[i64]) -> DenseVec<AlignedVec<i64, 4>> {
+        DenseVec::new_from(
+            values
+                .chunks_exact(4)
+                .enumerate()
+                .map(|(i, chunk)| AlignedVec::new_from(i * 4, chunk.to_vec()))
+                .collect(),
+        )
+    }
+
+    /// Concatenation of decimal digits is associative but not commutative.
+    struct Concat;
+
+    impl Monoid for Concat {
+        type Item = i64;
+        type Summary = (u64, u32);
+
+        fn identity(&self) -> (u64, u32) {
+            (0, 0)
+        }
+
+        fn summarize(&self, item: i64) -> (u64, u32) {
+            (item as u64 % 10, 1)
+        }
+
+        fn combine(&self, a: (u64, u32), b: (u64, u32)) -> (u64, u32) {
+            // Wrap, because the tree also combines long ranges that no query reads in full.
+            let shift = 10_u64.wrapping_pow(b.1);
+            (a.0.wrapping_mul(shift).wrapping_add(b.0), a.1 + b.1)
+        }
+    }
+
+    #[test]
+    fn test_sum() {
+        let mut tree = SegmentTree::new(dense(&(0..40).collect::<Vec<_>>()), Sum::default());
+
+        assert_eq!(tree.query(..), (0..40).sum());
+        assert_eq!(tree.query(3..29), (3..29).sum());
+        assert_eq!(tree.query(5..=6), 11);
+        assert_eq!(tree.query(7..7), 0);
+
+        tree.store(20, 1000);
+        assert_eq!(tree.query(3..29), (3..29).sum::<i64>() - 20 + 1000);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut tree = SegmentTree::new(dense(&[5, 3, 9, 1, 4, 4, 8, 2]), Min::default());
+        assert_eq!(tree.query(0..3), Some(3));
+        assert_eq!(tree.query(4..), Some(2));
+        assert_eq!(tree.query(4..4), None);
+
+        tree.store(7, 10);
+        assert_eq!(tree.query(4..), Some(4));
+
+        let tree = SegmentTree::new(tree.into_inner(), Max::default());
+        assert_eq!(tree.query(..), Some(10));
+    }
+
+    #[test]
+    fn test_not_commutative() {
+        let tree = SegmentTree::new(dense(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 1, 2, 3]), Concat);
+
+        assert_eq!(tree.query(1..11), (2345678912, 10));
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_slice(
+            values in proptest::collection::vec(-100..100_i64, 0..100),
+            updates in proptest::collection::vec((0..100_usize, -100..100_i64), 0..10),
+            a in 0..100_usize,
+            b in 0..100_usize,
+        ) {
+            let mut values = values;
+            values.truncate(values.len() / 4 * 4);
+            let mut tree = SegmentTree::new(dense(&values), Concat);
+
+            for (i, v) in updates {
+                if i < values.len() {
+                    values[i] = v;
+                    tree.store(i, v);
+                }
+            }
+
+            let (a, b) = (a.min(values.len()), b.min(values.len()));
+            let (a, b) = (a.min(b), a.max(b).min(a + 15));
+            let expected = values[a..b]
+                .iter()
+                .fold(Concat.identity(), |acc, v| Concat.combine(acc, Concat.summarize(*v)));
+            assert_eq!(tree.query(a..b), expected);
+        }
+    }
+}
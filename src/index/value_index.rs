@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    bitset::SparseBitset,
+    block::{
+        AlignedBitfield, AlignedBlock, AlignedBlockFromIterator, BitSearch, BlockEnumerate,
+        BlockFetch, BlockStore,
+    },
+    interval::RangeSet,
+    numerical_index::NumericalIndex,
+    rle::Rle,
+};
+
+/// Values that occur at more than this many positions are promoted from a sorted list to a bitset.
+pub const DEFAULT_LIST_LIMIT: usize = 64;
+
+/// The set of positions at which a single value occurs.
+pub struct Positions<I> {
+    count: usize,
+    repr: PositionsRepr<I>,
+}
+
+/// Rare values keep a sorted list of positions. Common values keep a bitset.
+enum PositionsRepr<I> {
+    List(Vec<I>),
+    Bitset(SparseBitset<I>),
+}
+
+impl<I> Positions<I>
+where
+    I: Copy + Ord,
+    SparseBitset<I>: Default + BlockFetch<Index = I, Item = bool> + BlockStore,
+{
+    fn new() -> Self {
+        Positions {
+            count: 0,
+            repr: PositionsRepr::List(vec![]),
+        }
+    }
+
+    /// The number of positions.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// True iff the value occurs at the given position.
+    pub fn contains(&self, i: I) -> bool {
+        match &self.repr {
+            PositionsRepr::List(list) => list.binary_search(&i).is_ok(),
+            PositionsRepr::Bitset(bitset) => bitset.fetch(i),
+        }
+    }
+
+    /// True iff the positions are stored as a bitset rather than a sorted list.
+    pub fn is_bitset(&self) -> bool {
+        matches!(self.repr, PositionsRepr::Bitset(_))
+    }
+
+    /// Add a position. Returns false if the position was already present.
+    fn insert(&mut self, i: I, list_limit: usize) -> bool {
+        match &mut self.repr {
+            PositionsRepr::List(list) => match list.binary_search(&i) {
+                Ok(_) => return false,
+                Err(at) => list.insert(at, i),
+            },
+            PositionsRepr::Bitset(bitset) => {
+                if bitset.fetch(i) {
+                    return false;
+                }
+                bitset.store(i, true);
+            }
+        }
+        self.count += 1;
+
+        if let PositionsRepr::List(list) = &self.repr {
+            if list.len() > list_limit {
+                let mut bitset = SparseBitset::default();
+                for i in list.iter() {
+                    bitset.store(*i, true);
+                }
+                self.repr = PositionsRepr::Bitset(bitset);
+            }
+        }
+        true
+    }
+
+    /// Remove a position. Returns false if the position was not present.
+    fn remove(&mut self, i: I) -> bool {
+        match &mut self.repr {
+            PositionsRepr::List(list) => match list.binary_search(&i) {
+                Ok(at) => {
+                    list.remove(at);
+                }
+                Err(_) => return false,
+            },
+            PositionsRepr::Bitset(bitset) => {
+                if !bitset.fetch(i) {
+                    return false;
+                }
+                bitset.store(i, false);
+            }
+        }
+        self.count -= 1;
+        true
+    }
+}
+
+impl<I> Positions<I>
+where
+    I: NumericalIndex,
+    AlignedBitfield<I>: BitSearch<Index = I>,
+{
+    /// Iterate over all positions, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = I> + '_ {
+        let (list, bitset) = match &self.repr {
+            PositionsRepr::List(list) => (Some(list.iter().copied()), None),
+            PositionsRepr::Bitset(bitset) => (None, Some(bitset.iter())),
+        };
+        list.into_iter()
+            .flatten()
+            .chain(bitset.into_iter().flatten())
+    }
+}
+
+impl<I> Positions<I>
+where
+    I: NumericalIndex,
+    AlignedBitfield<I>: AlignedBlock<Index = I, Item = bool> + AlignedBlockFromIterator,
+{
+    /// Every position in a set of ranges. A set with more than `list_limit` positions is built as a bitset
+    /// one block at a time, rather than by storing each position.
+    fn from_ranges(ranges: &RangeSet<I>, list_limit: usize) -> Self {
+        let count = ranges.iter().fold(0_usize, |count, r| {
+            count
+                .saturating_add(r.end().offset_from(*r.start()))
+                .saturating_add(1)
+        });
+        if count > list_limit {
+            return Positions {
+                count,
+                repr: PositionsRepr::Bitset(ranges.to_bitset()),
+            };
+        }
+        let mut list = Vec::with_capacity(count);
+        for r in ranges.iter() {
+            let mut i = *r.start();
+            list.push(i);
+            while i < *r.end() {
+                i = i.next();
+                list.push(i);
+            }
+        }
+        Positions {
+            count,
+            repr: PositionsRepr::List(list),
+        }
+    }
+}
+
+/// A secondary index from each distinct value of a collection to the positions holding that value.
+///
+/// Positions of rare values are kept in a sorted list. Once a value occurs at more than `list_limit` positions,
+/// its positions are promoted to a `SparseBitset`. Positions are never demoted back to a list.
+pub struct ValueIndex<I, V> {
+    list_limit: usize,
+    map: BTreeMap<V, Positions<I>>,
+}
+
+impl<I, V> Default for ValueIndex<I, V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIST_LIMIT)
+    }
+}
+
+impl<I, V> ValueIndex<I, V> {
+    /// Construct a new, empty ValueIndex.
+    /// Values that occur at more than `list_limit` positions will have their positions stored as a bitset.
+    pub fn new(list_limit: usize) -> Self {
+        ValueIndex {
+            list_limit,
+            map: BTreeMap::new(),
+        }
+    }
+}
+
+impl<I, V> ValueIndex<I, V>
+where
+    I: Copy + Ord,
+    V: Ord,
+    SparseBitset<I>: Default + BlockFetch<Index = I, Item = bool> + BlockStore,
+{
+    /// Build an index over every enumerable element of a collection.
+    /// For a `SparseVec`, only elements of stored blocks are indexed.
+    pub fn from_collection<C>(collection: &C) -> Self
+    where
+        C: BlockEnumerate<Index = I, Item = V>,
+    {
+        let mut result = Self::default();
+        for (i, v) in collection.enumerate_items() {
+            result.insert(i, v);
+        }
+        result
+    }
+
+    /// Record that the given value occurs at the given position.
+    /// Returns false if this was already recorded.
+    pub fn insert(&mut self, i: I, v: V) -> bool {
+        let list_limit = self.list_limit;
+        self.map
+            .entry(v)
+            .or_insert_with(Positions::new)
+            .insert(i, list_limit)
+    }
+
+    /// Record that the given value no longer occurs at the given position.
+    /// Returns false if the value was not recorded at that position.
+    pub fn remove(&mut self, i: I, v: &V) -> bool {
+        let Some(positions) = self.map.get_mut(v) else {
+            return false;
+        };
+        let removed = positions.remove(i);
+        if positions.len() == 0 {
+            self.map.remove(v);
+        }
+        removed
+    }
+
+    /// All positions where the given value occurs, if it occurs anywhere.
+    pub fn positions(&self, v: &V) -> Option<&Positions<I>> {
+        self.map.get(v)
+    }
+
+    /// The number of positions where the given value occurs.
+    pub fn count(&self, v: &V) -> usize {
+        self.map.get(v).map(|p| p.len()).unwrap_or(0)
+    }
+
+    /// Iterate over every distinct value, in sorted order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.map.keys()
+    }
+}
+
+impl<V> ValueIndex<u128, V>
+where
+    V: Ord + Clone,
+{
+    /// Build an index over every element of a run-length-encoded vector.
+    /// Each run is added as a single range, so values with few positions take time proportional to the number of runs.
+    pub fn from_rle(rle: &Rle<V>) -> Self {
+        let mut ranges: BTreeMap<V, RangeSet<u128>> = BTreeMap::new();
+        let mut offset: u128 = 0;
+        for (v, length) in rle.run_iterator() {
+            if length > 0 {
+                ranges
+                    .entry(v.clone())
+                    .or_default()
+                    .insert(offset..=offset + (length - 1));
+            }
+            offset += length;
+        }
+
+        let mut result = Self::default();
+        for (v, ranges) in ranges {
+            let positions = Positions::from_ranges(&ranges, result.list_limit);
+            result.map.insert(v, positions);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ValueIndex;
+    use crate::block::{AlignedVec, BlockStore, DefaultValue, DenseVec, SparseVec};
+    use crate::rle::Rle;
+
+    #[test]
+    fn test_from_dense_vec() {
+        let v = DenseVec::new_from(vec![
+            AlignedVec::<u8, 4>::new_from(0, vec![1, 2, 1, 3]),
+            AlignedVec::<u8, 4>::new_from(4, vec![3, 3, 1, 2]),
+        ]);
+        let index = ValueIndex::from_collection(&v);
+
+        assert_eq!(index.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(
+            index.positions(&1).unwrap().iter().collect::<Vec<_>>(),
+            vec![0, 2, 6]
+        );
+        assert_eq!(
+            index.positions(&3).unwrap().iter().collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert_eq!(index.count(&2), 2);
+        assert!(index.positions(&4).is_none());
+    }
+
+    #[test]
+    fn test_from_sparse_vec() {
+        let mut v: SparseVec<AlignedVec<u8, 4>, DefaultValue> = SparseVec::default();
+        v.store(1_000_001, 7);
+        v.store(9, 7);
+        let index = ValueIndex::from_collection(&v);
+
+        assert_eq!(
+            index.positions(&7).unwrap().iter().collect::<Vec<_>>(),
+            vec![9, 1_000_001]
+        );
+        assert_eq!(index.count(&0), 6);
+    }
+
+    #[test]
+    fn test_from_rle() {
+        let mut rle = Rle::default();
+        rle.extend(vec!["a", "a", "b", "a", "c", "c"]);
+        let index = ValueIndex::from_rle(&rle);
+
+        assert_eq!(
+            index.positions(&"a").unwrap().iter().collect::<Vec<_>>(),
+            vec![0, 1, 3]
+        );
+        assert_eq!(
+            index.positions(&"c").unwrap().iter().collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+    }
+
+    #[test]
+    fn test_from_rle_long_runs() {
+        let mut rle = Rle::default();
+        rle.append_run(("a", 1 << 20));
+        rle.append_run(("b", 3));
+        rle.append_run(("a", 10));
+        let index = ValueIndex::from_rle(&rle);
+
+        let a = index.positions(&"a").unwrap();
+        assert!(a.is_bitset());
+        assert_eq!(a.len(), (1 << 20) + 10);
+        assert!(a.contains(0));
+        assert!(!a.contains(1 << 20));
+        assert!(a.contains((1 << 20) + 3));
+
+        let b = index.positions(&"b").unwrap();
+        assert!(!b.is_bitset());
+        assert_eq!(
+            b.iter().collect::<Vec<_>>(),
+            vec![1 << 20, (1 << 20) + 1, (1 << 20) + 2]
+        );
+    }
+
+    #[test]
+    fn test_promote_to_bitset() {
+        let mut index: ValueIndex<u64, bool> = ValueIndex::new(4);
+        for i in 0..4 {
+            index.insert(i * 100, true);
+        }
+        assert!(!index.positions(&true).unwrap().is_bitset());
+
+        index.insert(50, true);
+        let positions = index.positions(&true).unwrap();
+        assert!(positions.is_bitset());
+        assert_eq!(positions.len(), 5);
+        assert!(positions.contains(50));
+        assert!(!positions.contains(51));
+        assert_eq!(
+            positions.iter().collect::<Vec<_>>(),
+            vec![0, 50, 100, 200, 300]
+        );
+
+        assert!(index.remove(100, &true));
+        assert!(!index.remove(100, &true));
+        assert_eq!(index.count(&true), 4);
+    }
+
+    #[test]
+    fn test_remove_last_position() {
+        let mut index: ValueIndex<usize, char> = ValueIndex::default();
+        assert!(index.insert(3, 'x'));
+        assert!(!index.insert(3, 'x'));
+        assert!(index.remove(3, &'x'));
+
+        assert!(index.positions(&'x').is_none());
+        assert_eq!(index.values().count(), 0);
+    }
+}
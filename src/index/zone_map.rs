@@ -0,0 +1,398 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::{RangeBounds, RangeInclusive},
+};
+
+use crate::{
+    block::{
+        AlignedBlock, BlockCollection, BlockFetch, BlockFetchIterator, BlockGet,
+        BlockIndexIterator, BlockStore, DefaultPerIndex, IndexedBlock,
+    },
+    numerical_index::NumericalIndex,
+};
+
+/// Statistics about the elements of a single block.
+///
+/// Statistics are updated incrementally as elements are stored. The minimum and maximum only ever widen,
+/// so after elements are overwritten they are conservative rather than exact.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BlockStatistics<I, V> {
+    /// Position of the block.
+    pub position: I,
+    /// No element of the block is smaller than this.
+    pub min: V,
+    /// No element of the block is larger than this.
+    pub max: V,
+    /// The number of elements of the block that are equal to `V::default()`.
+    pub default_count: usize,
+    /// One bit per hash bucket of each value that has been stored into the block.
+    distinct_sketch: u64,
+}
+
+impl<I, V> BlockStatistics<I, V>
+where
+    V: Copy + Ord + Default + Hash,
+{
+    fn new<B>(block: &B) -> Self
+    where
+        B: AlignedBlock<Index = I, Item = V> + BlockFetch,
+        I: NumericalIndex,
+    {
+        let mut values = BlockFetchIterator::new(block);
+        let first = values
+            .next()
+            .expect("blocks should contain at least one element");
+        let mut result = BlockStatistics {
+            position: block.position(),
+            min: first,
+            max: first,
+            default_count: 0,
+            distinct_sketch: 0,
+        };
+        result.add(first);
+        for v in values {
+            result.add(v);
+        }
+        result
+    }
+
+    /// Statistics of a block whose elements are all `v`, such as an absent block of a `SparseVec`.
+    fn constant(position: I, alignment: I, v: V) -> Self
+    where
+        I: NumericalIndex,
+    {
+        let mut result = BlockStatistics {
+            position,
+            min: v,
+            max: v,
+            default_count: 0,
+            distinct_sketch: 0,
+        };
+        result.add(v);
+        if v == V::default() {
+            result.default_count = position.last_in_block(alignment).offset_from(position) + 1;
+        }
+        result
+    }
+
+    fn add(&mut self, v: V) {
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        if v == V::default() {
+            self.default_count += 1;
+        }
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        self.distinct_sketch |= 1 << (hasher.finish() % 64);
+    }
+
+    fn replace(&mut self, old: V, new: V) {
+        if old == V::default() {
+            self.default_count -= 1;
+        }
+        self.add(new);
+    }
+
+    /// A rough estimate of the number of distinct values in the block, by linear counting.
+    /// Values that have been overwritten are still counted.
+    pub fn distinct_estimate(&self) -> usize {
+        let zeros = self.distinct_sketch.count_zeros() as f64;
+        if zeros == 0.0 {
+            // The sketch is saturated, so report the largest estimate it can express (one empty bucket).
+            return (64.0 * 64.0_f64.ln()).round() as usize;
+        }
+        (-64.0 * (zeros / 64.0).ln()).round() as usize
+    }
+
+    /// True unless the statistics prove that no element of the block falls within the given range.
+    pub fn may_contain_in(&self, range: &impl RangeBounds<V>) -> bool {
+        use std::ops::Bound::*;
+        let above_start = match range.start_bound() {
+            Included(start) => self.max >= *start,
+            Excluded(start) => self.max > *start,
+            Unbounded => true,
+        };
+        let below_end = match range.end_bound() {
+            Included(end) => self.min <= *end,
+            Excluded(end) => self.min < *end,
+            Unbounded => true,
+        };
+        above_start && below_end
+    }
+}
+
+/// A collection that keeps per-block statistics (a zone map) up to date as elements are stored.
+/// Scans can use the statistics to skip whole blocks that cannot contain a match.
+///
+/// Only stored blocks are tracked and scanned. For a `SparseVec`, the ranges of absent blocks whose default value
+/// may match are reported separately, by `gaps_where` and `gaps_in_range`.
+pub struct ZoneMap<C>
+where
+    C: IndexedBlock,
+{
+    collection: C,
+    statistics: Vec<BlockStatistics<C::Index, C::Item>>,
+}
+
+impl<C> ZoneMap<C>
+where
+    C: BlockCollection + BlockFetch + BlockStore,
+    C::Block: BlockFetch,
+    C::Index: NumericalIndex,
+    C::Item: Copy + Ord + Default + Hash,
+{
+    /// Wrap a collection, computing statistics for each of its blocks.
+    pub fn new(collection: C) -> Self {
+        let statistics = collection
+            .blocks()
+            .iter()
+            .map(BlockStatistics::new)
+            .collect();
+        ZoneMap {
+            collection,
+            statistics,
+        }
+    }
+
+    /// The underlying collection.
+    pub fn collection(&self) -> &C {
+        &self.collection
+    }
+
+    /// Unwrap back into the underlying collection, discarding the statistics.
+    pub fn into_inner(self) -> C {
+        self.collection
+    }
+
+    /// Statistics for every stored block, in order by position.
+    pub fn statistics(&self) -> &[BlockStatistics<C::Index, C::Item>] {
+        &self.statistics
+    }
+
+    /// Iterate over every element (with its index) of every stored block that `may_match` accepts.
+    /// Blocks whose statistics are rejected are skipped without being read.
+    /// The caller is still responsible for filtering the individual elements.
+    pub fn scan_where<'a>(
+        &'a self,
+        may_match: impl Fn(&BlockStatistics<C::Index, C::Item>) -> bool + 'a,
+    ) -> impl Iterator<Item = (C::Index, C::Item)> + 'a {
+        self.collection
+            .blocks()
+            .iter()
+            .zip(self.statistics.iter())
+            .filter(move |(_, statistics)| may_match(statistics))
+            .flat_map(|(b, _)| BlockIndexIterator::new(b).zip(BlockFetchIterator::new(b)))
+    }
+
+    /// Iterate over every element (with its index) whose value falls within the given range.
+    pub fn scan_range<'a>(
+        &'a self,
+        range: impl RangeBounds<C::Item> + Clone + 'a,
+    ) -> impl Iterator<Item = (C::Index, C::Item)> + 'a {
+        let filter = range.clone();
+        self.scan_where(move |statistics| statistics.may_contain_in(&range))
+            .filter(move |(_, v)| filter.contains(v))
+    }
+}
+
+impl<C> ZoneMap<C>
+where
+    C: BlockCollection + BlockFetch + BlockStore + DefaultPerIndex<C::Index, C::Item>,
+    C::Block: BlockFetch,
+    C::Index: NumericalIndex,
+    C::Item: Copy + Ord + Default + Hash,
+{
+    /// Iterate over the ranges of indices that are not covered by any stored block, and whose elements `may_match` accepts.
+    /// Each gap is judged by the statistics of its first absent block, so the default value is assumed to be the same
+    /// at every absent index, as it is with `DefaultValue` and `SharedDefault`.
+    pub fn gaps_where<'a>(
+        &'a self,
+        may_match: impl Fn(&BlockStatistics<C::Index, C::Item>) -> bool + 'a,
+    ) -> impl Iterator<Item = RangeInclusive<C::Index>> + 'a {
+        let alignment = C::Block::alignment();
+        let mut blocks = self.collection.blocks().iter();
+        let mut next = Some(C::Index::MIN);
+        std::iter::from_fn(move || loop {
+            let start = next?;
+            let end = match blocks.next() {
+                Some(b) => {
+                    next = b.position().last_in_block(alignment).checked_next();
+                    match b.position().checked_prev() {
+                        Some(end) if end >= start => end,
+                        _ => continue,
+                    }
+                }
+                None => {
+                    next = None;
+                    C::Index::MAX
+                }
+            };
+            let default = self.collection.default_at_index(start);
+            if may_match(&BlockStatistics::constant(start, alignment, default)) {
+                return Some(start..=end);
+            }
+        })
+    }
+
+    /// Iterate over the ranges of indices that are not covered by any stored block, whose default value falls within the given range.
+    pub fn gaps_in_range<'a>(
+        &'a self,
+        range: impl RangeBounds<C::Item> + 'a,
+    ) -> impl Iterator<Item = RangeInclusive<C::Index>> + 'a {
+        self.gaps_where(move |statistics| statistics.may_contain_in(&range))
+    }
+}
+
+impl<C> IndexedBlock for ZoneMap<C>
+where
+    C: IndexedBlock,
+{
+    type Index = C::Index;
+    type Item = C::Item;
+}
+
+impl<C> BlockFetch for ZoneMap<C>
+where
+    C: BlockFetch,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.collection.fetch(index)
+    }
+}
+
+impl<C> BlockGet for ZoneMap<C>
+where
+    C: BlockGet,
+{
+    fn get(&self, index: Self::Index) -> Option<&Self::Item> {
+        self.collection.get(index)
+    }
+}
+
+impl<C> BlockStore for ZoneMap<C>
+where
+    C: BlockCollection + BlockFetch + BlockStore,
+    C::Block: BlockFetch,
+    C::Index: NumericalIndex,
+    C::Item: Copy + Ord + Default + Hash,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        let position = index.block(C::Block::alignment());
+        match self
+            .statistics
+            .binary_search_by_key(&position, |s| s.position)
+        {
+            Ok(k) => {
+                let old = self.collection.fetch(index);
+                self.collection.store(index, item);
+                self.statistics[k].replace(old, item);
+            }
+            Err(k) => {
+                // The store materializes a new block, so summarize the whole thing.
+                self.collection.store(index, item);
+                let blocks = self.collection.blocks();
+                let b = blocks
+                    .binary_search_by_key(&position, |b| b.position())
+                    .expect("storing an element should materialize its block");
+                self.statistics.insert(k, BlockStatistics::new(&blocks[b]));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ZoneMap;
+    use crate::block::{AlignedVec, BlockFetch, BlockStore, DefaultValue, DenseVec, SparseVec};
+
+    #[test]
+    fn test_statistics() {
+        let v = DenseVec::new_from(vec![
+            AlignedVec::<i32, 4>::new_from(0, vec![0, 5, 0, -3]),
+            AlignedVec::<i32, 4>::new_from(4, vec![10, 11, 12, 13]),
+        ]);
+        let mut zones = ZoneMap::new(v);
+
+        let s = zones.statistics()[0];
+        assert_eq!((s.position, s.min, s.max, s.default_count), (0, -3, 5, 2));
+        assert!((2..=4).contains(&s.distinct_estimate()));
+
+        zones.store(0, 7);
+        zones.store(6, 0);
+        assert_eq!(zones.fetch(6), 0);
+
+        let s = zones.statistics()[0];
+        assert_eq!((s.min, s.max, s.default_count), (-3, 7, 1));
+        let s = zones.statistics()[1];
+        assert_eq!((s.min, s.max, s.default_count), (0, 13, 1));
+    }
+
+    #[test]
+    fn test_scan_skips_blocks() {
+        let v = DenseVec::new_from(
+            (0..100)
+                .map(|b| {
+                    AlignedVec::<u64, 8>::new_from(
+                        b * 8,
+                        (0..8).map(|i| (b * 8 + i) as u64).collect(),
+                    )
+                })
+                .collect(),
+        );
+        let zones = ZoneMap::new(v);
+
+        let visited = zones.scan_where(|s| s.may_contain_in(&(403..405))).count();
+        assert_eq!(visited, 8);
+
+        assert_eq!(
+            zones.scan_range(403..405).collect::<Vec<_>>(),
+            vec![(403, 403), (404, 404)]
+        );
+    }
+
+    #[test]
+    fn test_sparse_vec_new_block() {
+        let v: SparseVec<AlignedVec<u32, 4>, DefaultValue> = SparseVec::default();
+        let mut zones = ZoneMap::new(v);
+
+        zones.store(1_000_002, 9);
+        zones.store(5, 3);
+
+        assert_eq!(zones.statistics().len(), 2);
+        let s = zones.statistics()[0];
+        assert_eq!((s.position, s.min, s.max, s.default_count), (4, 0, 3, 3));
+        let s = zones.statistics()[1];
+        assert_eq!(
+            (s.position, s.min, s.max, s.default_count),
+            (1_000_000, 0, 9, 3)
+        );
+
+        assert_eq!(
+            zones.scan_range(1..).collect::<Vec<_>>(),
+            vec![(5, 3), (1_000_002, 9)]
+        );
+        assert_eq!(zones.gaps_in_range(1..).count(), 0);
+    }
+
+    #[test]
+    fn test_sparse_vec_gaps() {
+        let mut v: SparseVec<AlignedVec<u32, 4>, DefaultValue> = SparseVec::default();
+        v.store(0, 3);
+        v.store(9, 5);
+        v.store(12, 7);
+        let zones = ZoneMap::new(v);
+
+        // The stored blocks hold nine defaults, and the absent blocks are reported as gaps.
+        assert_eq!(zones.scan_range(..=4).filter(|(_, v)| *v == 0).count(), 9);
+        assert_eq!(
+            zones.gaps_in_range(..=4).collect::<Vec<_>>(),
+            vec![4..=7, 16..=usize::MAX]
+        );
+        assert_eq!(zones.gaps_in_range(0..1).count(), 2);
+        assert_eq!(zones.gaps_in_range(3..=5).count(), 0);
+
+        assert!(zones
+            .gaps_where(|s| s.default_count == 4)
+            .eq([4..=7, 16..=usize::MAX]));
+    }
+}
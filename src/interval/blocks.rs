@@ -0,0 +1,68 @@
+use crate::{block::AlignedBlockFromIterator, numerical_index::NumericalIndex};
+
+/// The positions of every block that overlaps one of the ranges, in increasing order.
+/// The ranges must be sorted and disjoint.
+pub(crate) fn block_positions<I>(
+    ranges: impl Iterator<Item = (I, I)>,
+    alignment: I,
+) -> impl Iterator<Item = I>
+where
+    I: NumericalIndex,
+{
+    let mut previous = None;
+    ranges
+        .flat_map(move |(start, end)| {
+            std::iter::successors(Some(start.block(alignment)), move |position: &I| {
+                let last = position.last_in_block(alignment);
+                (last < end).then(|| last.next())
+            })
+        })
+        // Consecutive ranges can share a block.
+        .filter(move |position| previous.replace(*position) != Some(*position))
+}
+
+/// Build every block that overlaps one of the ranges, which must be sorted and disjoint.
+/// `bounds` gives the first and last index of a range, and `item` gives the element at an index from the range that
+/// contains it, if any. The ranges are walked alongside the elements, rather than searched for each element.
+pub(crate) fn blocks_over_ranges<B, R>(
+    ranges: &[R],
+    bounds: impl Fn(&R) -> (B::Index, B::Index),
+    item: impl Fn(Option<&R>) -> B::Item,
+) -> Vec<B>
+where
+    B: AlignedBlockFromIterator,
+    B::Index: NumericalIndex,
+{
+    let alignment = B::alignment();
+    // The first range that does not end before the next element to fill.
+    let mut k = 0;
+    block_positions(ranges.iter().map(&bounds), alignment)
+        .map(|position| {
+            let mut elements = position.range(alignment).map(|i| {
+                while k < ranges.len() && bounds(&ranges[k]).1 < i {
+                    k += 1;
+                }
+                item(ranges.get(k).filter(|range| bounds(range).0 <= i))
+            });
+            B::from_iterator(position, &mut elements)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::block_positions;
+
+    #[test]
+    fn test_block_positions() {
+        let ranges = [(3_u8, 5), (7, 20), (30, 31), (250, 255)];
+        assert_eq!(
+            block_positions(ranges.into_iter(), 8).collect::<Vec<_>>(),
+            vec![0, 8, 16, 24, 248]
+        );
+        assert_eq!(
+            block_positions([(-3_i8, 2)].into_iter(), 4).collect::<Vec<_>>(),
+            vec![-4, 0]
+        );
+    }
+}
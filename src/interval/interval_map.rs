@@ -0,0 +1,382 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    block::{
+        AlignedBlock, AlignedBlockFromIterator, BlockEnumerate, BlockFetch, DefaultValue, SparseVec,
+    },
+    numerical_index::NumericalIndex,
+    rle::Rle,
+};
+
+use super::blocks::blocks_over_ranges;
+
+/// A map from disjoint ranges of indices to values, keyed by absolute position.
+///
+/// Inserting a range overwrites whatever the overlapping parts of existing ranges held.
+/// Adjacent ranges with equal values are merged, as `Rle::append_run` does, so every stored range is maximal.
+/// Ranges are inclusive, so that they can reach the largest index. Lookups are binary searches over the ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalMap<I, V> {
+    intervals: Vec<(I, I, V)>,
+}
+
+impl<I, V> Default for IntervalMap<I, V> {
+    fn default() -> Self {
+        IntervalMap { intervals: vec![] }
+    }
+}
+
+impl<I, V> IntervalMap<I, V>
+where
+    I: NumericalIndex,
+{
+    /// True iff no index has a value.
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// The number of maximal ranges in the map.
+    pub fn interval_count(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// The value at an index, if any.
+    pub fn get(&self, index: I) -> Option<&V> {
+        let i = self.intervals.partition_point(|(_, end, _)| *end < index);
+        self.intervals
+            .get(i)
+            .filter(|(start, _, _)| *start <= index)
+            .map(|(_, _, value)| value)
+    }
+
+    /// Iterate over every range and its value, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = (RangeInclusive<I>, &V)> + '_ {
+        self.intervals
+            .iter()
+            .map(|(start, end, value)| (*start..=*end, value))
+    }
+
+    /// Iterate over the ranges that overlap the given range, cut down to the part inside of it.
+    pub fn range(
+        &self,
+        range: RangeInclusive<I>,
+    ) -> impl Iterator<Item = (RangeInclusive<I>, &V)> + '_ {
+        let (start, end) = range.into_inner();
+        let first = if start <= end {
+            self.intervals.partition_point(|(_, e, _)| *e < start)
+        } else {
+            self.intervals.len()
+        };
+        self.intervals[first..]
+            .iter()
+            .take_while(move |(s, _, _)| *s <= end)
+            .map(move |(s, e, value)| (*s.max(&start)..=*e.min(&end), value))
+    }
+
+    /// Remove the values of every index of the range.
+    pub fn remove(&mut self, range: RangeInclusive<I>)
+    where
+        V: Clone,
+    {
+        let (start, end) = range.into_inner();
+        if start <= end {
+            self.replace(start, end, None);
+        }
+    }
+
+    /// Set every index of the range to the value, overwriting the overlapping parts of existing ranges.
+    pub fn insert(&mut self, range: RangeInclusive<I>, value: V)
+    where
+        V: Clone + Eq,
+    {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        let i = self.replace(start, end, Some(value));
+        self.coalesce(i);
+        if i > 0 {
+            self.coalesce(i - 1);
+        }
+    }
+
+    /// Replace everything from `start` to `end` with a single range holding `value`, or with nothing,
+    /// keeping the parts of overlapping ranges that stick out. Returns where the new range is (or would be).
+    fn replace(&mut self, start: I, end: I, value: Option<V>) -> usize
+    where
+        V: Clone,
+    {
+        let first = self.intervals.partition_point(|(_, e, _)| *e < start);
+        let last = self.intervals.partition_point(|(s, _, _)| *s <= end);
+        let mut before = None;
+        let mut after = None;
+        if first < last {
+            let (s, _, v) = &self.intervals[first];
+            if *s < start {
+                let stop = start.checked_prev().expect("start is after another index");
+                before = Some((*s, stop, v.clone()));
+            }
+            let (_, e, v) = &self.intervals[last - 1];
+            if end < *e {
+                after = Some((end.next(), *e, v.clone()));
+            }
+        }
+        let i = first + before.is_some() as usize;
+        let new = value.map(|value| (start, end, value));
+        self.intervals
+            .splice(first..last, before.into_iter().chain(new).chain(after));
+        i
+    }
+
+    /// Merge the range at `i` with the next one, if they touch and have equal values.
+    fn coalesce(&mut self, i: usize)
+    where
+        V: Eq,
+    {
+        let (Some(left), Some(right)) = (self.intervals.get(i), self.intervals.get(i + 1)) else {
+            return;
+        };
+        if left.1.checked_next() == Some(right.0) && left.2 == right.2 {
+            let (_, end, _) = self.intervals.remove(i + 1);
+            self.intervals[i].1 = end;
+        }
+    }
+
+    /// Append a value at an index after every stored range, extending the last range if possible.
+    fn push(&mut self, index: I, value: V)
+    where
+        V: Eq,
+    {
+        if let Some((_, end, last)) = self.intervals.last_mut() {
+            if end.checked_next() == Some(index) && *last == value {
+                *end = index;
+                return;
+            }
+        }
+        self.intervals.push((index, index, value));
+    }
+
+    /// The map of every element of a `SparseVec` that differs from the default value.
+    pub fn from_sparse_vec<T>(sparse_vec: &SparseVec<T, DefaultValue>) -> Self
+    where
+        T: AlignedBlock<Index = I, Item = V> + BlockFetch,
+        V: Default + Eq,
+    {
+        let mut result = IntervalMap::default();
+        for (index, value) in sparse_vec.enumerate_items() {
+            if value != V::default() {
+                result.push(index, value);
+            }
+        }
+        result
+    }
+
+    /// A `SparseVec` with the values of the map, and the default value everywhere else.
+    /// Every block that overlaps a range is stored.
+    pub fn to_sparse_vec<T>(&self) -> SparseVec<T, DefaultValue>
+    where
+        T: AlignedBlock<Index = I, Item = V> + AlignedBlockFromIterator,
+        V: Clone + Default,
+    {
+        let blocks = blocks_over_ranges(
+            &self.intervals,
+            |(start, end, _)| (*start, *end),
+            |interval| interval.map_or_else(V::default, |(_, _, value)| value.clone()),
+        );
+        SparseVec::new_from(DefaultValue, blocks)
+    }
+}
+
+/// An `Rle` counts its elements from zero, so it corresponds to a map over `u128` indices.
+impl<V> IntervalMap<u128, V> {
+    /// The map of every run of an `Rle` whose value differs from the default value.
+    pub fn from_rle(rle: &Rle<V>) -> Self
+    where
+        V: Clone + Default + Eq,
+    {
+        let mut result = IntervalMap::default();
+        let mut start: u128 = 0;
+        for (value, length) in rle.run_iterator() {
+            if length == 0 {
+                continue;
+            }
+            let end = start + (length - 1);
+            if *value != V::default() {
+                result.insert(start..=end, value.clone());
+            }
+            start = end.wrapping_add(1);
+        }
+        result
+    }
+
+    /// An `Rle` with the values of the map, and the default value in the gaps between ranges.
+    /// The `Rle` ends with the last range. Panics if a range covers every `u128` index, which is too long for a run.
+    pub fn to_rle(&self) -> Rle<V>
+    where
+        V: Clone + Default + Eq,
+    {
+        let mut result = Rle::default();
+        let mut next = 0_u128;
+        for (start, end, value) in self.intervals.iter() {
+            if *start > next {
+                result.append_run((V::default(), start - next));
+            }
+            let length = (end - start)
+                .checked_add(1)
+                .expect("a run should not cover every u128 index");
+            result.append_run((value.clone(), length));
+            next = end.wrapping_add(1);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IntervalMap;
+    use crate::block::{
+        AlignedVec, BlockCollection, BlockFetch, BlockStore, DefaultValue, SparseVec,
+    };
+    use crate::rle::Rle;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_insert_overwrite() {
+        let mut map = IntervalMap::default();
+        map.insert(10..=19_u64, "a");
+        map.insert(15..=24, "b");
+        map.insert(30..=39, "b");
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(10..=14, &"a"), (15..=24, &"b"), (30..=39, &"b")]
+        );
+
+        map.insert(25..=29, "b");
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(10..=14, &"a"), (15..=39, &"b")]
+        );
+
+        map.insert(20..=21, "c");
+        assert_eq!(map.interval_count(), 4);
+        assert_eq!(map.get(9), None);
+        assert_eq!(map.get(14), Some(&"a"));
+        assert_eq!(map.get(21), Some(&"c"));
+        assert_eq!(map.get(22), Some(&"b"));
+
+        map.insert(20..=21, "b");
+        map.remove(0..=12);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(13..=14, &"a"), (15..=39, &"b")]
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        let map: IntervalMap<i32, char> = {
+            let mut map = IntervalMap::default();
+            map.insert(-10..=-1, 'n');
+            map.insert(1..=10, 'p');
+            map.insert(i32::MAX..=i32::MAX, 'm');
+            map
+        };
+        assert_eq!(
+            map.range(-5..=5).collect::<Vec<_>>(),
+            vec![(-5..=-1, &'n'), (1..=5, &'p')]
+        );
+        assert_eq!(map.range(0..=0).count(), 0);
+        assert_eq!(map.range(std::ops::RangeInclusive::new(5, 2)).count(), 0);
+        assert_eq!(
+            map.range(100..=i32::MAX).collect::<Vec<_>>(),
+            vec![(i32::MAX..=i32::MAX, &'m')]
+        );
+    }
+
+    #[test]
+    fn test_sparse_vec() {
+        let mut v: SparseVec<AlignedVec<u8, 8>, DefaultValue> = SparseVec::default();
+        for i in 3..=12 {
+            v.store(i, 7);
+        }
+        v.store(5, 1);
+        v.store(100, 7);
+
+        let map = IntervalMap::from_sparse_vec(&v);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(3..=4, &7), (5..=5, &1), (6..=12, &7), (100..=100, &7)]
+        );
+
+        let round_trip: SparseVec<AlignedVec<u8, 8>, DefaultValue> = map.to_sparse_vec();
+        assert_eq!(round_trip.blocks().len(), 3);
+        assert!((0..120).all(|i| round_trip.fetch(i) == v.fetch(i)));
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut rle = Rle::default();
+        rle.append_run((0, 5));
+        rle.append_run((3, 1_000_000_000_000));
+        rle.append_run((0, 1));
+        rle.append_run((4, 2));
+
+        let map = IntervalMap::from_rle(&rle);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![
+                (5..=1_000_000_000_004, &3),
+                (1_000_000_000_006..=1_000_000_000_007, &4)
+            ]
+        );
+        assert_eq!(
+            map.to_rle()
+                .run_iterator()
+                .map(|(v, n)| (*v, n))
+                .collect::<Vec<_>>(),
+            rle.run_iterator().map(|(v, n)| (*v, n)).collect::<Vec<_>>()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_map(
+            ops in proptest::collection::vec((0..256_usize, 0..30_usize, 0..4_u8), 0..12),
+            probe in 0..300_usize,
+            window in (0..300_usize, 0..300_usize),
+        ) {
+            let mut map = IntervalMap::default();
+            let mut expected = BTreeMap::new();
+            for (start, length, value) in ops {
+                let range = start..=start + length;
+                if value == 0 {
+                    map.remove(range.clone());
+                    range.for_each(|i| { expected.remove(&i); });
+                } else {
+                    map.insert(range.clone(), value);
+                    range.for_each(|i| { expected.insert(i, value); });
+                }
+            }
+
+            assert_eq!(map.get(probe), expected.get(&probe));
+            let flattened: BTreeMap<usize, u8> = map.iter().flat_map(|(r, v)| r.map(move |i| (i, *v))).collect();
+            assert_eq!(&flattened, &expected);
+
+            // Stored ranges are maximal.
+            let ranges: Vec<_> = map.iter().collect();
+            for pair in ranges.windows(2) {
+                assert!(pair[0].0.end() < pair[1].0.start());
+                assert!(pair[0].0.end() + 1 < *pair[1].0.start() || pair[0].1 != pair[1].1);
+            }
+
+            let (low, high) = (window.0.min(window.1), window.0.max(window.1));
+            let in_window: BTreeMap<usize, u8> = map.range(low..=high).flat_map(|(r, v)| r.map(move |i| (i, *v))).collect();
+            assert_eq!(in_window, expected.range(low..=high).map(|(i, v)| (*i, *v)).collect());
+
+            let v: SparseVec<AlignedVec<u8, 16>, DefaultValue> = map.to_sparse_vec();
+            assert!((0..300).all(|i| v.fetch(i) == expected.get(&i).copied().unwrap_or_default()));
+            assert_eq!(IntervalMap::from_sparse_vec(&v), map);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+mod blocks;
+mod interval_map;
+mod range_set;
+
+pub use interval_map::*;
+pub use range_set::*;
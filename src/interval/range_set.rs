@@ -0,0 +1,353 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    bitset::SparseBitset,
+    block::{AlignedBlock, AlignedBlockFromIterator, BitSearch},
+    numerical_index::NumericalIndex,
+};
+
+use super::blocks::blocks_over_ranges;
+
+/// A set of indices, stored as sorted, disjoint ranges.
+///
+/// Overlapping and adjacent ranges are merged, so every stored range is maximal and each set has exactly one representation.
+/// Ranges are inclusive, so that they can reach the largest index.
+/// Set operations walk the ranges of both sets together, taking time linear in the number of ranges rather than indices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeSet<I> {
+    ranges: Vec<(I, I)>,
+}
+
+impl<I> Default for RangeSet<I> {
+    fn default() -> Self {
+        RangeSet { ranges: vec![] }
+    }
+}
+
+impl<I> RangeSet<I>
+where
+    I: NumericalIndex,
+{
+    /// The set of every index.
+    pub fn full() -> Self {
+        RangeSet {
+            ranges: vec![(I::MIN, I::MAX)],
+        }
+    }
+
+    /// True iff the set has no indices.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The number of maximal ranges in the set.
+    pub fn range_count(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// True iff the index is in the set.
+    pub fn contains(&self, index: I) -> bool {
+        let i = self.ranges.partition_point(|(_, end)| *end < index);
+        self.ranges.get(i).is_some_and(|(start, _)| *start <= index)
+    }
+
+    /// Iterate over the maximal ranges of the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = RangeInclusive<I>> + '_ {
+        self.ranges.iter().map(|(start, end)| *start..=*end)
+    }
+
+    /// Add every index of the range to the set.
+    pub fn insert(&mut self, range: RangeInclusive<I>) {
+        let (mut start, mut end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        // Stored ranges from `first` to `last` overlap or touch the new one.
+        let first = self
+            .ranges
+            .partition_point(|(_, e)| e.checked_next().is_some_and(|after| after < start));
+        let last = self
+            .ranges
+            .partition_point(|(s, _)| s.checked_prev().map_or(true, |before| before <= end));
+        if first < last {
+            start = start.min(self.ranges[first].0);
+            end = end.max(self.ranges[last - 1].1);
+        }
+        self.ranges.splice(first..last, [(start, end)]);
+    }
+
+    /// Remove every index of the range from the set.
+    pub fn remove(&mut self, range: RangeInclusive<I>) {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        // Stored ranges from `first` to `last` overlap the removed one.
+        let first = self.ranges.partition_point(|(_, e)| *e < start);
+        let last = self.ranges.partition_point(|(s, _)| *s <= end);
+        if first >= last {
+            return;
+        }
+        let (first_start, _) = self.ranges[first];
+        let (_, last_end) = self.ranges[last - 1];
+        let before = (first_start < start).then(|| {
+            (
+                first_start,
+                start.checked_prev().expect("start is after another index"),
+            )
+        });
+        let after = (end < last_end).then(|| (end.next(), last_end));
+        self.ranges
+            .splice(first..last, before.into_iter().chain(after));
+    }
+
+    /// The first gap in the set after `index`: the range of missing indices that begins with the smallest missing index
+    /// greater than `index`. Returns `None` if every greater index is in the set.
+    pub fn next_gap_after(&self, index: I) -> Option<RangeInclusive<I>> {
+        let from = index.checked_next()?;
+        let i = self.ranges.partition_point(|(_, end)| *end < from);
+        let (start, next) = match self.ranges.get(i) {
+            Some((s, e)) if *s <= from => (e.checked_next()?, i + 1),
+            _ => (from, i),
+        };
+        let end = match self.ranges.get(next) {
+            Some((s, _)) => s
+                .checked_prev()
+                .expect("a range after a gap should not start at the smallest index"),
+            None => I::MAX,
+        };
+        Some(start..=end)
+    }
+
+    /// The indices in either set.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = RangeSet::default();
+        let (mut a, mut b) = (
+            self.ranges.iter().peekable(),
+            other.ranges.iter().peekable(),
+        );
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.0 <= y.0 => a.next(),
+                (Some(_), Some(_)) => b.next(),
+                (Some(_), None) => a.next(),
+                (None, _) => b.next(),
+            };
+            match next {
+                Some(range) => result.push(*range),
+                None => return result,
+            }
+        }
+    }
+
+    /// The indices in both sets.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = RangeSet::default();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(x), Some(y)) = (self.ranges.get(i), other.ranges.get(j)) {
+            let (start, end) = (x.0.max(y.0), x.1.min(y.1));
+            if start <= end {
+                result.ranges.push((start, end));
+            }
+            if x.1 < y.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        result
+    }
+
+    /// The indices in this set but not in the other.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.complement())
+    }
+
+    /// The indices that are not in the set.
+    pub fn complement(&self) -> Self {
+        let mut result = RangeSet::default();
+        let mut from = Some(I::MIN);
+        for (start, end) in self.ranges.iter() {
+            let gap_start = from.expect("only the last range can end at the largest index");
+            if let Some(before) = start.checked_prev() {
+                result.ranges.push((gap_start, before));
+            }
+            from = end.checked_next();
+        }
+        if let Some(gap_start) = from {
+            result.ranges.push((gap_start, I::MAX));
+        }
+        result
+    }
+
+    /// The set of the indices of every set bit of a bitset.
+    pub fn from_bitset<B>(bitset: &SparseBitset<I, B>) -> Self
+    where
+        B: BitSearch<Index = I>,
+    {
+        let mut result = RangeSet::default();
+        for index in bitset.iter() {
+            result.push((index, index));
+        }
+        result
+    }
+
+    /// A bitset with exactly the indices of this set. Every block that overlaps a range is stored.
+    pub fn to_bitset<B>(&self) -> SparseBitset<I, B>
+    where
+        B: AlignedBlock<Index = I, Item = bool> + AlignedBlockFromIterator,
+    {
+        SparseBitset::new_from(blocks_over_ranges(
+            &self.ranges,
+            |range| *range,
+            |range| range.is_some(),
+        ))
+    }
+
+    /// Append a range that starts no earlier than every stored range, merging it with the last range if they touch.
+    fn push(&mut self, (start, end): (I, I)) {
+        if let Some(last) = self.ranges.last_mut() {
+            if last.1.checked_next().map_or(true, |after| after >= start) {
+                last.1 = last.1.max(end);
+                return;
+            }
+        }
+        self.ranges.push((start, end));
+    }
+}
+
+impl<I> FromIterator<RangeInclusive<I>> for RangeSet<I>
+where
+    I: NumericalIndex,
+{
+    fn from_iter<T: IntoIterator<Item = RangeInclusive<I>>>(iter: T) -> Self {
+        let mut result = RangeSet::default();
+        for range in iter {
+            result.insert(range);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RangeSet;
+    use crate::bitset::SparseBitset;
+    use crate::block::{AlignedBitArray, BlockCollection, BlockStore};
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+    use std::ops::RangeInclusive;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut set: RangeSet<u64> = [10..=19, 30..=39, 20..=22].into_iter().collect();
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![10..=22, 30..=39]);
+
+        set.insert(23..=29);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![10..=39]);
+
+        set.remove(15..=16);
+        set.remove(39..=100);
+        set.remove(5..=5);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![10..=14, 17..=38]);
+        assert!(set.contains(10));
+        assert!(!set.contains(15));
+        assert!(!set.contains(39));
+    }
+
+    #[test]
+    fn test_index_space() {
+        let mut set: RangeSet<i8> = RangeSet::full();
+        set.remove(-1..=1);
+        assert_eq!(set.range_count(), 2);
+        assert_eq!(set.complement().iter().collect::<Vec<_>>(), vec![-1..=1]);
+        assert_eq!(set.next_gap_after(i8::MIN), Some(-1..=1));
+        assert_eq!(set.next_gap_after(0), Some(1..=1));
+        assert_eq!(set.next_gap_after(1), None);
+        assert_eq!(set.next_gap_after(i8::MAX), None);
+
+        set.insert(i8::MIN..=i8::MAX);
+        assert_eq!(set, RangeSet::full());
+        assert!(set.complement().is_empty());
+    }
+
+    #[test]
+    fn test_allocate_ids() {
+        let mut allocated: RangeSet<u64> = [0..=99, 200..=299].into_iter().collect();
+        let free = allocated.next_gap_after(0).unwrap();
+        assert_eq!(free, 100..=199);
+
+        allocated.insert(*free.start()..=*free.start() + 9);
+        assert_eq!(allocated.next_gap_after(150), Some(151..=199));
+        assert_eq!(allocated.next_gap_after(299), Some(300..=u64::MAX));
+    }
+
+    #[test]
+    fn test_bitset() {
+        let mut bitset: SparseBitset<usize, AlignedBitArray<2>> = SparseBitset::default();
+        for i in [3, 4, 5, 127, 128, 129, 1000] {
+            bitset.store(i, true);
+        }
+        let set = RangeSet::from_bitset(&bitset);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![3..=5, 127..=129, 1000..=1000]
+        );
+
+        let round_trip: SparseBitset<usize, AlignedBitArray<2>> = set.to_bitset();
+        assert_eq!(round_trip.blocks().len(), 3);
+        assert_eq!(
+            round_trip.iter().collect::<Vec<_>>(),
+            bitset.iter().collect::<Vec<_>>()
+        );
+    }
+
+    fn ranges() -> impl Strategy<Value = Vec<RangeInclusive<u8>>> {
+        proptest::collection::vec((any::<u8>(), 0..40_u8), 0..6).prop_map(|v| {
+            v.into_iter()
+                .map(|(s, l)| s..=s.saturating_add(l))
+                .collect()
+        })
+    }
+
+    fn indices(ranges: &[RangeInclusive<u8>]) -> BTreeSet<u8> {
+        ranges.iter().cloned().flatten().collect()
+    }
+
+    fn set_of(set: &RangeSet<u8>) -> BTreeSet<u8> {
+        set.iter().flatten().collect()
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_sets(a in ranges(), b in ranges(), removed in ranges(), probe: u8) {
+            let (mut sa, sb) = (indices(&a), indices(&b));
+            let mut ra: RangeSet<u8> = a.into_iter().collect();
+            let rb: RangeSet<u8> = b.into_iter().collect();
+            for r in removed {
+                sa.retain(|i| !r.contains(i));
+                ra.remove(r);
+            }
+
+            assert_eq!(set_of(&ra), sa.clone());
+            assert_eq!(ra.contains(probe), sa.contains(&probe));
+            assert_eq!(set_of(&ra.union(&rb)), &sa | &sb);
+            assert_eq!(set_of(&ra.intersection(&rb)), &sa & &sb);
+            assert_eq!(set_of(&ra.difference(&rb)), &sa - &sb);
+            assert_eq!(set_of(&ra.complement()), (0..=255).filter(|i| !sa.contains(i)).collect());
+            assert_eq!(ra.union(&rb), rb.union(&ra));
+
+            let gap_start = (probe..=255).skip(1).find(|i| !sa.contains(i));
+            let gap = ra.next_gap_after(probe);
+            assert_eq!(gap.as_ref().map(|g| *g.start()), gap_start);
+            if let Some(gap) = gap {
+                assert!(gap.clone().all(|i| !sa.contains(&i)));
+                assert!(*gap.end() == 255 || sa.contains(&(gap.end() + 1)));
+            }
+
+            let bitset: SparseBitset<u8> = ra.to_bitset();
+            assert_eq!(bitset.iter().collect::<BTreeSet<_>>(), sa);
+            assert_eq!(RangeSet::from_bitset(&bitset), ra);
+        }
+    }
+}
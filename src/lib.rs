@@ -8,13 +8,31 @@
 
 //! Very large collections.
 
+// Lets the derive macros refer to this crate by name in its own tests.
+#[cfg(test)]
+extern crate self as very_large_collections;
+
 /// Bitsets
 pub mod bitset;
 /// Utilities for working with blocks of data.
 pub mod block;
+/// Compressed blocks.
+pub mod compress;
+/// Secondary indexes over collections.
+pub mod index;
+/// Sets and maps over ranges of indices.
+pub mod interval;
+/// Memory-mapped, file-backed collections.
+pub mod mapped;
+/// Lazy set operations over sorted index iterators.
+pub mod merge;
 /// Index types
 pub mod numerical_index;
 /// Run-length encoding.
 pub mod rle;
+/// SIMD-accelerated kernels behind the bulk operations of blocks.
+pub mod simd;
 /// Utilities for sorting.
 pub mod sort;
+/// Collections of variable-size elements.
+pub mod varsize;
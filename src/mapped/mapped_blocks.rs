@@ -0,0 +1,228 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    marker::PhantomData,
+    ops::Range,
+    path::Path,
+};
+
+use bytemuck::Pod;
+use memmap2::MmapMut;
+
+/// Identifies a file of blocks, and the version of its format.
+const MAGIC: &[u8; 8] = b"VLCBLK01";
+
+/// Size of the header. Also the offset of the first block, so it must be a multiple of the alignment of any item type.
+const HEADER_BYTES: usize = 64;
+
+/// Size of one entry of the block directory: the position of the block, and the slot where its data is stored.
+const DIRECTORY_ENTRY_BYTES: usize = 16;
+
+/// Storage for the blocks of a file-backed collection, and the file format that they are persisted in.
+///
+/// The file is laid out as a header, then a region of fixed-size slots (one per block, in the order the blocks were added),
+/// then the block directory. The header records the size and alignment of items, the alignment of blocks, the number of blocks,
+/// and where the directory begins. The directory lists the position and slot of every block, in order by position.
+///
+/// Slots are written in place through the memory map. The slot region is reserved ahead of time, doubling as it fills,
+/// and the directory is written just past it, so new slots never overwrite the directory on disk. When the slot region grows
+/// over the old directory, the directory and header are first rewritten past the new region and flushed.
+/// The directory is kept in memory, and rewritten by `flush`. Until `flush` is called, the directory in the file may not
+/// describe every block.
+pub(crate) struct MappedBlocks<T, const N: usize> {
+    file: File,
+    map: MmapMut,
+    /// The number of slots that fit before the directory.
+    capacity: usize,
+    /// Position and slot of every block, in order by position.
+    directory: Vec<(u64, u64)>,
+    _items: PhantomData<T>,
+}
+
+impl<T, const N: usize> MappedBlocks<T, N>
+where
+    T: Pod,
+{
+    const BLOCK_BYTES: usize = N * std::mem::size_of::<T>();
+
+    /// Create a new, empty file, replacing any existing file.
+    pub(crate) fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        assert!(std::mem::align_of::<T>() <= HEADER_BYTES);
+        assert!(Self::BLOCK_BYTES > 0, "blocks must not be empty");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(HEADER_BYTES as u64)?;
+        let mut result = MappedBlocks {
+            map: map(&file)?,
+            file,
+            capacity: 0,
+            directory: vec![],
+            _items: PhantomData,
+        };
+        result.flush()?;
+        Ok(result)
+    }
+
+    /// Open an existing file, checking that its header matches this type.
+    pub(crate) fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        assert!(Self::BLOCK_BYTES > 0, "blocks must not be empty");
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let map = map(&file)?;
+        if map.len() < HEADER_BYTES || &map[0..8] != MAGIC {
+            return Err(invalid_data("not a file of blocks"));
+        }
+
+        let header = |i: usize| u64::from_le_bytes(map[8 + 8 * i..16 + 8 * i].try_into().unwrap());
+        let (item_size, item_align, alignment) = (header(0), header(1), header(2));
+        let (block_count, directory_offset) = (header(3), header(4));
+        if item_size != std::mem::size_of::<T>() as u64
+            || item_align != std::mem::align_of::<T>() as u64
+        {
+            return Err(invalid_data("item size or alignment does not match"));
+        }
+        if alignment != N as u64 {
+            return Err(invalid_data("block alignment does not match"));
+        }
+
+        let capacity = directory_offset
+            .checked_sub(HEADER_BYTES as u64)
+            .filter(|slot_bytes| slot_bytes % Self::BLOCK_BYTES as u64 == 0)
+            .map(|slot_bytes| slot_bytes / Self::BLOCK_BYTES as u64)
+            .filter(|capacity| *capacity >= block_count)
+            .ok_or_else(|| invalid_data("block directory is not where it should be"))?;
+        let directory_end = block_count
+            .checked_mul(DIRECTORY_ENTRY_BYTES as u64)
+            .and_then(|directory_bytes| directory_bytes.checked_add(directory_offset))
+            .filter(|end| *end <= map.len() as u64)
+            .ok_or_else(|| invalid_data("block directory is truncated"))?;
+        // Both fit in a usize, because they are within the map.
+        let (capacity, directory_start) = (capacity as usize, directory_offset as usize);
+        let directory: Vec<(u64, u64)> = map[directory_start..directory_end as usize]
+            .chunks_exact(DIRECTORY_ENTRY_BYTES)
+            .map(|entry| {
+                (
+                    u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                    u64::from_le_bytes(entry[8..16].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        // Every slot must belong to exactly one block, or writing one block would change another.
+        let mut slots: Vec<u64> = directory.iter().map(|(_, slot)| *slot).collect();
+        slots.sort_unstable();
+        let well_formed = directory.windows(2).all(|w| w[0].0 < w[1].0)
+            && directory
+                .iter()
+                .all(|(position, _)| position % N as u64 == 0)
+            && slots.into_iter().eq(0..block_count);
+        if !well_formed {
+            return Err(invalid_data("block directory is malformed"));
+        }
+
+        Ok(MappedBlocks {
+            file,
+            map,
+            capacity,
+            directory,
+            _items: PhantomData,
+        })
+    }
+
+    /// Position and slot of every block, in order by position.
+    pub(crate) fn directory(&self) -> &[(u64, u64)] {
+        &self.directory
+    }
+
+    /// The items of the block stored in the given slot.
+    pub(crate) fn slot(&self, slot: u64) -> &[T] {
+        assert!(slot < self.directory.len() as u64, "slot out of bounds");
+        bytemuck::cast_slice(&self.map[Self::slot_bytes(slot)])
+    }
+
+    /// The items of the block stored in the given slot, mutably.
+    pub(crate) fn slot_mut(&mut self, slot: u64) -> &mut [T] {
+        assert!(slot < self.directory.len() as u64, "slot out of bounds");
+        bytemuck::cast_slice_mut(&mut self.map[Self::slot_bytes(slot)])
+    }
+
+    /// Where the given slot is in the file.
+    fn slot_bytes(slot: u64) -> Range<usize> {
+        let start = HEADER_BYTES + slot as usize * Self::BLOCK_BYTES;
+        start..start + Self::BLOCK_BYTES
+    }
+
+    /// Add a new block at the given position, which must not already have a block, and return its slot.
+    /// The slot region grows as needed, doubling its capacity each time.
+    pub(crate) fn insert(&mut self, position: u64, items: &[T]) -> io::Result<u64> {
+        assert_eq!(items.len(), N);
+        assert!(position % N as u64 == 0, "blocks must be aligned");
+        let k = match self.directory.binary_search_by_key(&position, |e| e.0) {
+            Ok(_) => panic!("a block already exists at this position"),
+            Err(k) => k,
+        };
+
+        let slot = self.directory.len();
+        if slot == self.capacity {
+            // Move the directory out of the way before the new slot can overwrite it.
+            self.capacity = (2 * self.capacity).max(1);
+            self.flush()?;
+        }
+        bytemuck::cast_slice_mut(&mut self.map[Self::slot_bytes(slot as u64)])
+            .copy_from_slice(items);
+        self.directory.insert(k, (position, slot as u64));
+        Ok(slot as u64)
+    }
+
+    /// Make sure the file and the map are at least the given length.
+    fn reserve(&mut self, len: usize) -> io::Result<()> {
+        if self.map.len() < len {
+            self.map.flush()?;
+            self.file.set_len(len as u64)?;
+            self.map = map(&self.file)?;
+        }
+        Ok(())
+    }
+
+    /// Write the header and the directory, and flush every change to the file.
+    /// The directory is written just past the reserved slots, leaving room for it to grow to one entry per slot.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        let block_count = self.directory.len();
+        let directory_offset = HEADER_BYTES + self.capacity * Self::BLOCK_BYTES;
+        self.reserve(directory_offset + self.capacity * DIRECTORY_ENTRY_BYTES)?;
+
+        for (k, (position, slot)) in self.directory.iter().enumerate() {
+            let entry = directory_offset + k * DIRECTORY_ENTRY_BYTES;
+            self.map[entry..entry + 8].copy_from_slice(&position.to_le_bytes());
+            self.map[entry + 8..entry + 16].copy_from_slice(&slot.to_le_bytes());
+        }
+
+        let header = [
+            std::mem::size_of::<T>() as u64,
+            std::mem::align_of::<T>() as u64,
+            N as u64,
+            block_count as u64,
+            directory_offset as u64,
+        ];
+        self.map[0..8].copy_from_slice(MAGIC);
+        for (i, field) in header.iter().enumerate() {
+            self.map[8 + 8 * i..16 + 8 * i].copy_from_slice(&field.to_le_bytes());
+        }
+        self.map.flush()
+    }
+}
+
+/// Map an entire file into memory.
+#[allow(unsafe_code)]
+fn map(file: &File) -> io::Result<MmapMut> {
+    // SAFETY: The file is opened for exclusive use by one collection. Changing or truncating it from outside
+    // of that collection (including from another process) while it is mapped is not supported.
+    unsafe { MmapMut::map_mut(file) }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
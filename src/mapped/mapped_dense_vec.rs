@@ -0,0 +1,225 @@
+use std::{io, path::Path};
+
+use bytemuck::Pod;
+
+use crate::block::{AlignedBlock, AlignedVec, BlockFetch, BlockStore, IndexedBlock};
+
+use super::mapped_blocks::MappedBlocks;
+
+/// A DenseVec of `AlignedVec<T, N>` blocks, stored in a memory-mapped file.
+///
+/// Items must be plain old data, so that they can be read and written directly as bytes.
+/// Changes are written to the file in place, but the file is only guaranteed to be complete and readable by `open`
+/// after `flush`.
+pub struct MappedDenseVec<T, const N: usize> {
+    blocks: MappedBlocks<T, N>,
+}
+
+impl<T, const N: usize> MappedDenseVec<T, N>
+where
+    T: Pod,
+{
+    /// Create a new, empty file, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(MappedDenseVec {
+            blocks: MappedBlocks::create(path)?,
+        })
+    }
+
+    /// Open a file that was previously written by a MappedDenseVec with the same item type and alignment.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let blocks = MappedBlocks::open(path)?;
+        let dense = blocks
+            .directory()
+            .iter()
+            .enumerate()
+            .all(|(k, (position, slot))| *position == (k * N) as u64 && *slot == k as u64);
+        if !dense {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "blocks must be densely packed and aligned",
+            ));
+        }
+        Ok(MappedDenseVec { blocks })
+    }
+
+    /// The number of blocks.
+    pub fn block_count(&self) -> usize {
+        self.blocks.directory().len()
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.block_count() * N
+    }
+
+    /// Push an entire block onto the end of this MappedDenseVec.
+    pub fn push_block(&mut self, block: &AlignedVec<T, N>) -> io::Result<()> {
+        assert_eq!(
+            self.len(),
+            block.position(),
+            "blocks must be densely packed and aligned"
+        );
+        let items: Vec<T> = block.iter().copied().collect();
+        self.blocks.insert(block.position() as u64, &items)?;
+        Ok(())
+    }
+
+    /// Grow by whole blocks of zeroes until there are at least `len` elements.
+    pub fn grow_to(&mut self, len: usize) -> io::Result<()> {
+        let zeroes = vec![T::zeroed(); N];
+        while self.len() < len {
+            self.blocks.insert(self.len() as u64, &zeroes)?;
+        }
+        Ok(())
+    }
+
+    /// Borrow the elements of one block, by block number.
+    pub fn block(&self, k: usize) -> &[T] {
+        assert!(k < self.block_count(), "block out of bounds");
+        self.blocks.slot(k as u64)
+    }
+
+    /// Copy one block, by block number, into memory.
+    pub fn to_aligned_vec(&self, k: usize) -> AlignedVec<T, N> {
+        AlignedVec::new_from(k * N, self.block(k).to_vec())
+    }
+
+    /// Iterate over every element.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.block_count()).flat_map(|k| self.block(k).iter().copied())
+    }
+
+    /// Write every change to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.blocks.flush()
+    }
+}
+
+impl<T, const N: usize> IndexedBlock for MappedDenseVec<T, N> {
+    type Index = usize;
+    type Item = T;
+}
+
+impl<T, const N: usize> BlockFetch for MappedDenseVec<T, N>
+where
+    T: Pod,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.block(index / N)[index % N]
+    }
+}
+
+impl<T, const N: usize> BlockStore for MappedDenseVec<T, N>
+where
+    T: Pod,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        assert!(index < self.len(), "index out of bounds");
+        self.blocks.slot_mut((index / N) as u64)[index % N] = item;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MappedDenseVec;
+    use crate::block::{AlignedVec, BlockFetch, BlockStore};
+
+    #[test]
+    fn test_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dense");
+
+        {
+            let mut v: MappedDenseVec<u64, 4> = MappedDenseVec::create(&path).unwrap();
+            v.push_block(&AlignedVec::new_from(0, vec![1, 2, 3, 4]))
+                .unwrap();
+            v.grow_to(1000).unwrap();
+            v.store(999, 42);
+            v.store(2, 30);
+            assert_eq!(v.len(), 1000);
+            v.flush().unwrap();
+        }
+
+        let mut v: MappedDenseVec<u64, 4> = MappedDenseVec::open(&path).unwrap();
+        assert_eq!(v.len(), 1000);
+        assert_eq!(v.fetch(0), 1);
+        assert_eq!(v.fetch(2), 30);
+        assert_eq!(v.fetch(500), 0);
+        assert_eq!(v.fetch(999), 42);
+        assert_eq!(v.to_aligned_vec(0).into_vec(), vec![1, 2, 30, 4]);
+
+        v.push_block(&AlignedVec::new_from(1000, vec![5, 6, 7, 8]))
+            .unwrap();
+        v.flush().unwrap();
+        drop(v);
+
+        let v: MappedDenseVec<u64, 4> = MappedDenseVec::open(&path).unwrap();
+        assert_eq!(v.iter().skip(999).collect::<Vec<_>>(), vec![42, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dense");
+        let mut v: MappedDenseVec<u32, 4> = MappedDenseVec::create(&path).unwrap();
+        v.grow_to(8).unwrap();
+        v.flush().unwrap();
+
+        assert!(MappedDenseVec::<u64, 4>::open(&path).is_err());
+        assert!(MappedDenseVec::<u32, 8>::open(&path).is_err());
+        assert!(MappedDenseVec::<u32, 4>::open(&path).is_ok());
+        assert!(MappedDenseVec::<u32, 4>::open(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_grow_after_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dense");
+        {
+            let mut v: MappedDenseVec<u32, 4> = MappedDenseVec::create(&path).unwrap();
+            v.grow_to(8).unwrap();
+            v.store(7, 70);
+            v.flush().unwrap();
+            // Not flushed, so this block is lost, but it must not overwrite the directory.
+            v.push_block(&AlignedVec::new_from(8, vec![1, 2, 3, 4]))
+                .unwrap();
+        }
+
+        let v: MappedDenseVec<u32, 4> = MappedDenseVec::open(&path).unwrap();
+        assert_eq!(v.len(), 8);
+        assert_eq!(v.fetch(7), 70);
+    }
+
+    #[test]
+    fn test_corrupt_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dense");
+        let mut v: MappedDenseVec<u32, 4> = MappedDenseVec::create(&path).unwrap();
+        v.grow_to(8).unwrap();
+        v.flush().unwrap();
+        drop(v);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let error = MappedDenseVec::<u32, 4>::open(&path).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        bytes[32..40].copy_from_slice(&2_u64.to_le_bytes());
+        bytes[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let error = MappedDenseVec::<u32, 4>::open(&path).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[should_panic(expected = "block out of bounds")]
+    fn test_fetch_out_of_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut v: MappedDenseVec<u32, 4> =
+            MappedDenseVec::create(dir.path().join("dense")).unwrap();
+        v.grow_to(8).unwrap();
+        v.fetch(8);
+    }
+}
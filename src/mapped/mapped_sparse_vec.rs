@@ -0,0 +1,269 @@
+use std::{io, path::Path};
+
+use bytemuck::Pod;
+
+use crate::block::{
+    AlignedBlock, AlignedVec, BlockFetch, BlockSource, BlockStore, DefaultPerIndex, IndexedBlock,
+};
+
+use super::mapped_blocks::MappedBlocks;
+
+/// A SparseVec of `AlignedVec<T, N>` blocks, stored in a memory-mapped file.
+///
+/// Blocks are stored in the file in the order that they were created, and located through a block directory.
+/// Every element of an absent block has its default value, and storing into an absent block fills it in with default values first.
+/// Changes are written to the file in place, but the file is only guaranteed to be complete and readable by `open`
+/// after `flush`.
+pub struct MappedSparseVec<T, const N: usize, D> {
+    default_value: D,
+    blocks: MappedBlocks<T, N>,
+}
+
+impl<T, const N: usize, D> MappedSparseVec<T, N, D>
+where
+    T: Pod,
+    D: DefaultPerIndex<usize, T>,
+{
+    /// Create a new, empty file, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>, default_value: D) -> io::Result<Self> {
+        Ok(MappedSparseVec {
+            default_value,
+            blocks: MappedBlocks::create(path)?,
+        })
+    }
+
+    /// Open a file that was previously written by a MappedSparseVec with the same item type and alignment.
+    /// The default value is not stored in the file, so it must be provided again.
+    pub fn open(path: impl AsRef<Path>, default_value: D) -> io::Result<Self> {
+        Ok(MappedSparseVec {
+            default_value,
+            blocks: MappedBlocks::open(path)?,
+        })
+    }
+
+    /// The number of blocks that are actually stored.
+    pub fn block_count(&self) -> usize {
+        self.blocks.directory().len()
+    }
+
+    /// Positions of every stored block, in order.
+    pub fn block_positions(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks
+            .directory()
+            .iter()
+            .map(|(position, _)| *position as usize)
+    }
+
+    /// Borrow the elements of the block containing the given index, if it is stored.
+    pub fn block_containing(&self, index: usize) -> Option<&[T]> {
+        let slot = self.slot_of(index)?;
+        Some(self.blocks.slot(slot))
+    }
+
+    fn slot_of(&self, index: usize) -> Option<u64> {
+        let position = (index - index % N) as u64;
+        let k = self
+            .blocks
+            .directory()
+            .binary_search_by_key(&position, |e| e.0)
+            .ok()?;
+        Some(self.blocks.directory()[k].1)
+    }
+
+    /// Store an element, reporting any error from growing the file.
+    pub fn try_store(&mut self, index: usize, item: T) -> io::Result<()> {
+        let slot = match self.slot_of(index) {
+            Some(slot) => slot,
+            None => {
+                let position = index - index % N;
+                let defaults: Vec<T> = (0..N)
+                    .map(|offset| self.default_value.default_at_index(position + offset))
+                    .collect();
+                self.blocks.insert(position as u64, &defaults)?
+            }
+        };
+        self.blocks.slot_mut(slot)[index % N] = item;
+        Ok(())
+    }
+
+    /// Store an entire block, replacing any block at the same position.
+    pub fn store_block(&mut self, block: &AlignedVec<T, N>) -> io::Result<()> {
+        let items: Vec<T> = block.iter().copied().collect();
+        match self.slot_of(block.position()) {
+            Some(slot) => self.blocks.slot_mut(slot).copy_from_slice(&items),
+            None => {
+                self.blocks.insert(block.position() as u64, &items)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write every change to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.blocks.flush()
+    }
+}
+
+impl<T, const N: usize, D> IndexedBlock for MappedSparseVec<T, N, D> {
+    type Index = usize;
+    type Item = T;
+}
+
+impl<T, const N: usize, D> BlockFetch for MappedSparseVec<T, N, D>
+where
+    T: Pod,
+    D: DefaultPerIndex<usize, T>,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        match self.block_containing(index) {
+            Some(block) => block[index % N],
+            None => self.default_value.default_at_index(index),
+        }
+    }
+}
+
+/// Panics if the file can't be grown. Use `try_store` to handle the error instead.
+impl<T, const N: usize, D> BlockStore for MappedSparseVec<T, N, D>
+where
+    T: Pod,
+    D: DefaultPerIndex<usize, T>,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        self.try_store(index, item)
+            .expect("file should be able to grow");
+    }
+}
+
+/// Blocks are copied in and out of the file, for use behind a `BlockCache`.
+impl<T, const N: usize, D> BlockSource for MappedSparseVec<T, N, D>
+where
+    T: Pod,
+    D: DefaultPerIndex<usize, T>,
+{
+    type Block = AlignedVec<T, N>;
+
+    fn load(&mut self, position: usize) -> io::Result<Option<AlignedVec<T, N>>> {
+        Ok(self
+            .block_containing(position)
+            .map(|items| AlignedVec::new_from(position, items.to_vec())))
+    }
+
+    fn save(&mut self, block: &AlignedVec<T, N>) -> io::Result<()> {
+        self.store_block(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MappedSparseVec;
+    use crate::block::{AlignedVec, BlockCache, BlockFetch, BlockSize, BlockStore, DefaultValue};
+
+    #[test]
+    fn test_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sparse");
+
+        {
+            let mut v: MappedSparseVec<u32, 64, DefaultValue> =
+                MappedSparseVec::create(&path, DefaultValue).unwrap();
+            v.store(1_000_000_000, 7);
+            v.store(5, 3);
+            v.store(70, 4);
+            v.store(6, 2);
+            assert_eq!(v.block_count(), 3);
+            v.flush().unwrap();
+        }
+
+        let mut v: MappedSparseVec<u32, 64, DefaultValue> =
+            MappedSparseVec::open(&path, DefaultValue).unwrap();
+        assert_eq!(
+            v.block_positions().collect::<Vec<_>>(),
+            vec![0, 64, 1_000_000_000]
+        );
+        assert_eq!(v.fetch(5), 3);
+        assert_eq!(v.fetch(6), 2);
+        assert_eq!(v.fetch(70), 4);
+        assert_eq!(v.fetch(1_000_000_000), 7);
+        assert_eq!(v.fetch(500), 0);
+        assert!(v.block_containing(500).is_none());
+
+        v.store(500, 9);
+        v.flush().unwrap();
+        drop(v);
+
+        let v: MappedSparseVec<u32, 64, DefaultValue> =
+            MappedSparseVec::open(&path, DefaultValue).unwrap();
+        assert_eq!(v.block_count(), 4);
+        assert_eq!(v.fetch(500), 9);
+        assert_eq!(v.fetch(70), 4);
+    }
+
+    #[test]
+    fn test_many_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sparse");
+
+        let mut v: MappedSparseVec<u16, 8, DefaultValue> =
+            MappedSparseVec::create(&path, DefaultValue).unwrap();
+        for i in (0..2000_usize).rev() {
+            v.store(i * 37, i as u16);
+        }
+        v.flush().unwrap();
+        drop(v);
+
+        let v: MappedSparseVec<u16, 8, DefaultValue> =
+            MappedSparseVec::open(&path, DefaultValue).unwrap();
+        for i in 0..2000_usize {
+            assert_eq!(v.fetch(i * 37), i as u16);
+            assert_eq!(v.fetch(i * 37 + 1), 0);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sparse");
+        let mut v: MappedSparseVec<u32, 4, DefaultValue> =
+            MappedSparseVec::create(&path, DefaultValue).unwrap();
+        v.store(0, 1);
+        v.store(4, 2);
+        v.flush().unwrap();
+        drop(v);
+
+        // Point the second block at the slot of the first.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let directory = u64::from_le_bytes(bytes[40..48].try_into().unwrap()) as usize;
+        bytes[directory + 24..directory + 32].copy_from_slice(&0_u64.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let error = MappedSparseVec::<u32, 4, DefaultValue>::open(&path, DefaultValue)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_block_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sparse");
+
+        let v: MappedSparseVec<u64, 16, DefaultValue> =
+            MappedSparseVec::create(&path, DefaultValue).unwrap();
+        let block_bytes = AlignedVec::<u64, 16>::new_from(0, vec![0; 16]).size_in_bytes();
+        let mut cache = BlockCache::new(v, 4 * block_bytes, DefaultValue);
+        for i in 0..1000 {
+            cache.store(i * 3, i as u64);
+        }
+        assert!(cache.statistics().write_backs > 0);
+        assert_eq!(cache.loaded_blocks(), 4);
+
+        let mut v = cache.into_source().unwrap();
+        v.flush().unwrap();
+        drop(v);
+
+        let v: MappedSparseVec<u64, 16, DefaultValue> =
+            MappedSparseVec::open(&path, DefaultValue).unwrap();
+        for i in 0..1000 {
+            assert_eq!(v.fetch(i * 3), i as u64);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+mod mapped_blocks;
+mod mapped_dense_vec;
+mod mapped_sparse_vec;
+
+pub use mapped_dense_vec::*;
+pub use mapped_sparse_vec::*;
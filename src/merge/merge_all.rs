@@ -0,0 +1,228 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use super::SkipTo;
+
+/// The items of any of several sorted iterators, in increasing order and without duplicates.
+///
+/// The sources are kept in a heap by their next item, so each item costs `O(log k)` for `k` sources.
+/// Sources of different types can be merged as `Box<dyn SkipTo<Item = T>>`.
+pub struct UnionAll<I>
+where
+    I: Iterator,
+{
+    sources: Vec<I>,
+    heap: BinaryHeap<Reverse<(I::Item, usize)>>,
+}
+
+impl<I> UnionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    /// The union of every source. The first item of each is read right away.
+    pub fn new(sources: impl IntoIterator<Item = I>) -> Self {
+        let mut sources: Vec<I> = sources.into_iter().collect();
+        let heap = sources
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(s, source)| Some(Reverse((source.next()?, s))))
+            .collect();
+        UnionAll { sources, heap }
+    }
+}
+
+impl<I> Iterator for UnionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((item, _)) = *self.heap.peek()?;
+        while let Some(Reverse((next, s))) = self.heap.peek().copied() {
+            if next != item {
+                break;
+            }
+            self.heap.pop();
+            if let Some(next) = self.sources[s].next() {
+                self.heap.push(Reverse((next, s)));
+            }
+        }
+        Some(item)
+    }
+}
+
+impl<I> SkipTo for UnionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        while let Some(Reverse((next, s))) = self.heap.peek().copied() {
+            if next >= target {
+                break;
+            }
+            self.heap.pop();
+            if let Some(next) = self.sources[s].skip_to(target) {
+                self.heap.push(Reverse((next, s)));
+            }
+        }
+        self.next()
+    }
+}
+
+/// The items of every one of several sorted iterators, in increasing order.
+///
+/// This is a leapfrog join: each source in turn skips ahead to the largest candidate seen so far,
+/// until every source agrees on it. The intersection of no sources is empty.
+pub struct IntersectionAll<I> {
+    sources: Vec<I>,
+}
+
+impl<I> IntersectionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    /// The intersection of every source. Putting the sparsest source first keeps the number of candidates low.
+    pub fn new(sources: impl IntoIterator<Item = I>) -> Self {
+        IntersectionAll {
+            sources: sources.into_iter().collect(),
+        }
+    }
+
+    /// Leapfrog from a candidate that was just read from the first source.
+    fn leapfrog(&mut self, mut candidate: I::Item) -> Option<I::Item> {
+        let k = self.sources.len();
+        let mut agreeing = 1;
+        let mut s = 1;
+        while agreeing < k {
+            let next = self.sources[s % k].skip_to(candidate)?;
+            if next == candidate {
+                agreeing += 1;
+            } else {
+                candidate = next;
+                agreeing = 1;
+            }
+            s += 1;
+        }
+        Some(candidate)
+    }
+}
+
+impl<I> Iterator for IntersectionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let candidate = self.sources.first_mut()?.next()?;
+        self.leapfrog(candidate)
+    }
+}
+
+impl<I> SkipTo for IntersectionAll<I>
+where
+    I: SkipTo,
+    I::Item: Copy + Ord,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        let candidate = self.sources.first_mut()?.skip_to(target)?;
+        self.leapfrog(candidate)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IntersectionAll, UnionAll};
+    use crate::bitset::SparseBitset;
+    use crate::block::{BlockStore, DefaultValue, SparseVec};
+    use crate::merge::{SkipTo, Sorted, SortedSlice};
+    use crate::rle::Rle;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_mixed_sources() {
+        let a = [2_u32, 3, 5, 7, 11, 13];
+        let sources: Vec<Box<dyn SkipTo<Item = u32>>> = vec![
+            Box::new(SortedSlice::new(&a)),
+            Box::new(Sorted::new((0..20).step_by(2))),
+            Box::new(Sorted::new((0..20).step_by(3))),
+        ];
+        let mut union = UnionAll::new(sources);
+        assert_eq!(union.next(), Some(0));
+        assert_eq!(union.skip_to(8), Some(8));
+        assert_eq!(
+            union.collect::<Vec<_>>(),
+            vec![9, 10, 11, 12, 13, 14, 15, 16, 18]
+        );
+
+        let sources = vec![
+            Sorted::new((0..100).step_by(2)),
+            Sorted::new((0..100).step_by(3)),
+            Sorted::new((0..100).step_by(5)),
+        ];
+        assert_eq!(
+            IntersectionAll::new(sources).collect::<Vec<_>>(),
+            vec![0, 30, 60, 90]
+        );
+        assert_eq!(
+            IntersectionAll::<Sorted<std::ops::Range<u8>>>::new([]).next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_crate_sources() {
+        let mut published: SparseBitset<u128> = SparseBitset::default();
+        let mut ratings: SparseVec<(u128, u8), DefaultValue> = SparseVec::default();
+        let mut in_stock = Rle::default();
+        for i in [3, 10, 11, 500, 1 << 40] {
+            published.store(i, true);
+        }
+        for i in [10, 11, 12, 500, 1 << 40] {
+            ratings.store(i, 5);
+        }
+        in_stock.append_run((false, 11));
+        in_stock.append_run((true, 1 << 41));
+
+        let sources: Vec<Box<dyn SkipTo<Item = u128>>> = vec![
+            Box::new(published.iter()),
+            Box::new(ratings.occupied_indices()),
+            Box::new(in_stock.ones()),
+        ];
+        assert_eq!(
+            IntersectionAll::new(sources).collect::<Vec<_>>(),
+            vec![11, 500, 1 << 40]
+        );
+
+        let mut any = UnionAll::new([published.iter(), published.iter()]);
+        assert_eq!(any.skip_to(4), Some(10));
+        assert_eq!(any.count(), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_sets(
+            sets in proptest::collection::vec(proptest::collection::btree_set(0..200_u8, 0..120), 1..5),
+            target in 0..220_u8,
+        ) {
+            let slices: Vec<Vec<u8>> = sets.iter().map(|s| s.iter().copied().collect()).collect();
+
+            let union: BTreeSet<u8> = sets.iter().flatten().copied().collect();
+            let mut merged = UnionAll::new(slices.iter().map(|s| SortedSlice::new(s)));
+            assert_eq!(merged.skip_to(target), union.range(target..).next().copied());
+            assert_eq!(merged.collect::<Vec<_>>(), union.range(target..).skip(1).copied().collect::<Vec<_>>());
+
+            let intersection: BTreeSet<u8> = union.iter().copied().filter(|i| sets.iter().all(|s| s.contains(i))).collect();
+            let joined = IntersectionAll::new(slices.iter().map(|s| SortedSlice::new(s)));
+            assert_eq!(joined.collect::<BTreeSet<_>>(), intersection.clone());
+            let mut joined = IntersectionAll::new(slices.iter().map(|s| SortedSlice::new(s)));
+            assert_eq!(joined.skip_to(target), intersection.range(target..).next().copied());
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod merge_all;
+mod set_ops;
+mod skip_to;
+
+pub use merge_all::*;
+pub use set_ops::*;
+pub use skip_to::*;
@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+
+use super::SkipTo;
+
+/// The items of either of two sorted iterators, in increasing order and without duplicates.
+pub struct Union<A, B>
+where
+    A: Iterator,
+{
+    a: A,
+    b: B,
+    next_a: Option<A::Item>,
+    next_b: Option<A::Item>,
+}
+
+impl<A, B> Union<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    /// The union of two sorted iterators. The first item of each is read right away.
+    pub fn new(mut a: A, mut b: B) -> Self {
+        Union {
+            next_a: a.next(),
+            next_b: b.next(),
+            a,
+            b,
+        }
+    }
+}
+
+impl<A, B> Iterator for Union<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let order = match (self.next_a, self.next_b) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(a), Some(b)) => a.cmp(&b),
+        };
+        match order {
+            Ordering::Less => std::mem::replace(&mut self.next_a, self.a.next()),
+            Ordering::Greater => std::mem::replace(&mut self.next_b, self.b.next()),
+            Ordering::Equal => {
+                self.next_b = self.b.next();
+                std::mem::replace(&mut self.next_a, self.a.next())
+            }
+        }
+    }
+}
+
+impl<A, B> SkipTo for Union<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        if self.next_a.is_some_and(|a| a < target) {
+            self.next_a = self.a.skip_to(target);
+        }
+        if self.next_b.is_some_and(|b| b < target) {
+            self.next_b = self.b.skip_to(target);
+        }
+        self.next()
+    }
+}
+
+/// The items of both of two sorted iterators, in increasing order.
+///
+/// Each iterator skips ahead to the last item of the other ("leapfrogging"),
+/// so the work done is proportional to the smaller of the two when both can skip efficiently.
+pub struct Intersection<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> Intersection<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    /// The intersection of two sorted iterators. Put the sparser iterator first.
+    pub fn new(a: A, b: B) -> Self {
+        Intersection { a, b }
+    }
+
+    /// Leapfrog from a candidate that was just read from `a`.
+    fn leapfrog(&mut self, mut candidate: A::Item) -> Option<A::Item> {
+        loop {
+            let b = self.b.skip_to(candidate)?;
+            if b == candidate {
+                return Some(b);
+            }
+            candidate = self.a.skip_to(b)?;
+            if candidate == b {
+                return Some(b);
+            }
+        }
+    }
+}
+
+impl<A, B> Iterator for Intersection<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let candidate = self.a.next()?;
+        self.leapfrog(candidate)
+    }
+}
+
+impl<A, B> SkipTo for Intersection<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        let candidate = self.a.skip_to(target)?;
+        self.leapfrog(candidate)
+    }
+}
+
+/// The items of a sorted iterator that are not items of another sorted iterator, in increasing order.
+pub struct Difference<A, B>
+where
+    A: Iterator,
+{
+    a: A,
+    b: B,
+    next_b: Option<A::Item>,
+}
+
+impl<A, B> Difference<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    /// The items of `a` that are not in `b`. The first item of `b` is read right away.
+    pub fn new(a: A, mut b: B) -> Self {
+        Difference {
+            next_b: b.next(),
+            a,
+            b,
+        }
+    }
+
+    /// Whether an item of `a` is missing from `b`. Items must be checked in increasing order.
+    fn keep(&mut self, item: A::Item) -> bool {
+        if self.next_b.is_some_and(|b| b < item) {
+            self.next_b = self.b.skip_to(item);
+        }
+        self.next_b != Some(item)
+    }
+}
+
+impl<A, B> Iterator for Difference<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.a.next()?;
+            if self.keep(item) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+impl<A, B> SkipTo for Difference<A, B>
+where
+    A: SkipTo,
+    B: SkipTo<Item = A::Item>,
+    A::Item: Copy + Ord,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item> {
+        let item = self.a.skip_to(target)?;
+        if self.keep(item) {
+            Some(item)
+        } else {
+            self.next()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Difference, Intersection, Union};
+    use crate::merge::{SkipTo, SortedSlice};
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_query() {
+        let rust = [1, 4, 7, 9, 12];
+        let collections = [2, 4, 9, 10, 12, 15];
+        let deprecated = [9];
+
+        // rust AND collections AND NOT deprecated
+        let hits = Difference::new(
+            Intersection::new(SortedSlice::new(&rust), SortedSlice::new(&collections)),
+            SortedSlice::new(&deprecated),
+        );
+        assert_eq!(hits.collect::<Vec<_>>(), vec![4, 12]);
+
+        let mut any = Union::new(SortedSlice::new(&rust), SortedSlice::new(&collections));
+        assert_eq!(any.skip_to(5), Some(7));
+        assert_eq!(any.collect::<Vec<_>>(), vec![9, 10, 12, 15]);
+    }
+
+    fn sets() -> impl Strategy<Value = (Vec<u16>, Vec<u16>, Vec<u16>)> {
+        let set = || proptest::collection::btree_set(0..300_u16, 0..60);
+        (set(), set(), proptest::collection::vec(0..320_u16, 0..8))
+            .prop_map(|(a, b, t)| (a.into_iter().collect(), b.into_iter().collect(), t))
+    }
+
+    /// Collect an iterator, skipping to each of the targets along the way.
+    fn skipping(mut iter: impl SkipTo<Item = u16>, targets: &[u16]) -> Vec<u16> {
+        let mut result = vec![];
+        for target in targets {
+            result.extend(iter.skip_to(*target));
+        }
+        result.extend(iter);
+        result
+    }
+
+    /// The items that `skipping` should find, given the materialized result.
+    fn expected(items: BTreeSet<u16>, targets: &[u16]) -> Vec<u16> {
+        let mut result = vec![];
+        let mut rest = items.into_iter();
+        for target in targets {
+            result.extend(rest.find(|i| i >= target));
+        }
+        result.extend(rest);
+        result
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_sets((a, b, targets) in sets()) {
+            let (sa, sb): (BTreeSet<_>, BTreeSet<_>) = (a.iter().copied().collect(), b.iter().copied().collect());
+
+            let union = Union::new(SortedSlice::new(&a), SortedSlice::new(&b));
+            assert_eq!(skipping(union, &targets), expected(&sa | &sb, &targets));
+
+            let intersection = Intersection::new(SortedSlice::new(&a), SortedSlice::new(&b));
+            assert_eq!(skipping(intersection, &targets), expected(&sa & &sb, &targets));
+
+            let difference = Difference::new(SortedSlice::new(&a), SortedSlice::new(&b));
+            assert_eq!(skipping(difference, &targets), expected(&sa - &sb, &targets));
+        }
+    }
+}
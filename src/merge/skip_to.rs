@@ -0,0 +1,160 @@
+/// An iterator over items in strictly increasing order, which can skip ahead to a target.
+///
+/// `skip_to` behaves like calling `next` until it returns an item that is at least `target`, but implementations
+/// can get there without visiting every item in between, for example by binary searching blocks or skipping whole runs.
+/// This is what lets an intersection of a small set with a large set do work proportional to the small one.
+pub trait SkipTo: Iterator {
+    /// Advance past every item that is less than `target`, and return the next item.
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item>
+    where
+        Self::Item: Ord,
+    {
+        while let Some(item) = self.next() {
+            if item >= target {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+impl<S> SkipTo for Box<S>
+where
+    S: SkipTo + ?Sized,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item>
+    where
+        Self::Item: Ord,
+    {
+        (**self).skip_to(target)
+    }
+}
+
+impl<S> SkipTo for &mut S
+where
+    S: SkipTo + ?Sized,
+{
+    fn skip_to(&mut self, target: Self::Item) -> Option<Self::Item>
+    where
+        Self::Item: Ord,
+    {
+        (**self).skip_to(target)
+    }
+}
+
+/// Adapts any iterator that is already in strictly increasing order, skipping ahead one item at a time.
+pub struct Sorted<I> {
+    iter: I,
+}
+
+impl<I> Sorted<I>
+where
+    I: Iterator,
+{
+    /// Wrap an iterator. The caller promises that its items are in strictly increasing order.
+    pub fn new(iter: I) -> Self {
+        Sorted { iter }
+    }
+}
+
+impl<I> Iterator for Sorted<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl<I> SkipTo for Sorted<I> where I: Iterator {}
+
+/// An iterator over a sorted slice, such as a posting list, which skips ahead by galloping.
+pub struct SortedSlice<'a, T> {
+    slice: &'a [T],
+}
+
+impl<'a, T> SortedSlice<'a, T>
+where
+    T: Ord,
+{
+    /// Iterate over a slice, which must be in strictly increasing order.
+    pub fn new(slice: &'a [T]) -> Self {
+        debug_assert!(
+            slice.windows(2).all(|w| w[0] < w[1]),
+            "slice must be in strictly increasing order"
+        );
+        SortedSlice { slice }
+    }
+}
+
+impl<'a, T> Iterator for SortedSlice<'a, T>
+where
+    T: Copy,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let (first, rest) = self.slice.split_first()?;
+        self.slice = rest;
+        Some(*first)
+    }
+}
+
+impl<'a, T> SkipTo for SortedSlice<'a, T>
+where
+    T: Copy + Ord,
+{
+    /// Gallops ahead in steps of 1, 2, 4, ... until it passes `target`, then binary searches the last step,
+    /// so skipping `n` items costs `O(log n)` comparisons.
+    fn skip_to(&mut self, target: T) -> Option<T> {
+        let mut end = 1;
+        while end < self.slice.len() && self.slice[end - 1] < target {
+            end *= 2;
+        }
+        let start = end / 2;
+        let end = end.min(self.slice.len());
+        let skip = start + self.slice[start..end].partition_point(|item| *item < target);
+        self.slice = &self.slice[skip..];
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SkipTo, Sorted, SortedSlice};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_sorted() {
+        let mut s = Sorted::new([1, 4, 9, 16].into_iter());
+        assert_eq!(s.skip_to(2), Some(4));
+        assert_eq!(s.skip_to(4), Some(9));
+        assert_eq!(s.next(), Some(16));
+        assert_eq!(s.skip_to(0), None);
+    }
+
+    #[test]
+    fn test_boxed() {
+        let mut s: Box<dyn SkipTo<Item = u64>> = Box::new(SortedSlice::new(&[3, 5, 7]));
+        assert_eq!(s.skip_to(6), Some(7));
+        assert_eq!(s.next(), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_slice_skip_to(
+            items in proptest::collection::btree_set(0..1000_u32, 0..100),
+            targets in proptest::collection::vec(0..1100_u32, 0..10),
+        ) {
+            let items: Vec<_> = items.into_iter().collect();
+            let mut slice = SortedSlice::new(&items);
+            let mut sorted = Sorted::new(items.iter().copied());
+            for target in targets {
+                assert_eq!(slice.skip_to(target), sorted.skip_to(target));
+                assert_eq!(slice.next(), sorted.next());
+            }
+        }
+    }
+}
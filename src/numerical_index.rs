@@ -1,174 +1,391 @@
+pub use very_large_collections_derive::NumericalIndex;
+
+use std::num::{NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize};
+
 /// A NumericalIndex, needed to index many kinds of sparse collections.
+///
+/// Every index has an offset, which is its distance from the smallest index of its type.
+/// For unsigned integers the offset is the index itself. Signed integers are offset by their minimum,
+/// so `i8::MIN` has offset 0 and `0_i8` has offset 128. `NonZero` integers are offset by one.
+/// Blocks are aligned by offset, and `modulo` and `divide` work on offsets and map their result back to an index.
+/// Alignments and divisors are lengths rather than indices, and are always taken at face value.
+///
+/// Newtypes over an index can implement this trait with `#[derive(NumericalIndex)]`, which delegates to the wrapped index.
+///
+/// ```
+/// use very_large_collections::numerical_index::NumericalIndex;
+///
+/// #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, NumericalIndex)]
+/// struct UserId(u64);
+///
+/// assert_eq!(UserId(70).block(UserId(64)), UserId(64));
+/// ```
 pub trait NumericalIndex: Copy + Eq + Ord {
-    /// True iff the index is zero.
-    fn is_zero(self) -> bool;
-    /// Modulo division of an index.
+    /// One, as a length. This is the alignment of blocks that hold a single element.
+    const ONE: Self;
+    /// The smallest index, which has offset zero.
+    const MIN: Self;
+    /// The largest index.
+    const MAX: Self;
+    /// True iff the index is the smallest index, `Self::MIN`, which has offset zero.
+    /// For unsigned integers this is zero, but for signed integers it is the most negative value.
+    fn is_min(self) -> bool;
+    /// Modulo division of an index's offset, as the index with that offset.
     fn modulo(self, divisor: Self) -> Self;
-    /// Division of an index.
+    /// Division of an index's offset, as the index with that offset.
     fn divide(self, divisor: Self) -> Self;
     /// Get the beginning of the index's block, given an alignment.
     fn block(self, alignment: Self) -> Self;
-    /// Next index after this one
-    fn next(self) -> Self;
-    /// Range from the beginning of a block over it's length
-    /// TODO: might go away with the block/next technique (start=block, next=next, until initial block != current block)
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self>;
-}
-
-impl NumericalIndex for usize {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
-
+    /// Get the last index of the index's block, given an alignment.
+    /// If the alignment does not evenly divide the index space, the final block is cut short at the largest index.
+    fn last_in_block(self, alignment: Self) -> Self;
+    /// Sum of two indices, or `None` if the sum is past the end of the index space.
+    fn checked_add(self, other: Self) -> Option<Self>;
+    /// Next index after this one, or `None` if this is the largest index.
+    fn checked_next(self) -> Option<Self>;
+    /// Index just before this one, or `None` if this is the smallest index.
+    fn checked_prev(self) -> Option<Self>;
+    /// Next index after this one. Panics if this is the largest index.
     fn next(self) -> Self {
-        self + 1
+        self.checked_next()
+            .expect("next index should not be past the end of the index space")
     }
+    /// Range from the beginning of a block over its length.
+    /// Stops early at the largest index, rather than overflowing.
+    fn range(self, alignment: Self) -> impl Iterator<Item = Self>;
+    /// The number of indices from `origin` up to this index, which must not be smaller.
+    /// This is how far into a block an element is, so it is expected to fit in a `usize`.
+    fn offset_from(self, origin: Self) -> usize;
 }
 
-impl NumericalIndex for u64 {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
-
-    fn next(self) -> Self {
-        self + 1
-    }
+macro_rules! unsigned_numerical_index {
+    ($($t:ty),*) => {
+        $(
+            impl NumericalIndex for $t {
+                const ONE: Self = 1;
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn is_min(self) -> bool {
+                    self == 0
+                }
+
+                fn modulo(self, divisor: Self) -> Self {
+                    self % divisor
+                }
+
+                fn divide(self, divisor: Self) -> Self {
+                    self / divisor
+                }
+
+                fn block(self, alignment: Self) -> Self {
+                    (self / alignment) * alignment
+                }
+
+                fn last_in_block(self, alignment: Self) -> Self {
+                    self.block(alignment).saturating_add(alignment - 1)
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_next(self) -> Option<Self> {
+                    <$t>::checked_add(self, 1)
+                }
+
+                fn checked_prev(self) -> Option<Self> {
+                    <$t>::checked_sub(self, 1)
+                }
+
+                fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
+                    self..=self.saturating_add(alignment - 1)
+                }
+
+                fn offset_from(self, origin: Self) -> usize {
+                    (self - origin) as usize
+                }
+            }
+        )*
+    };
 }
 
-impl NumericalIndex for u128 {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
-
-    fn next(self) -> Self {
-        self + 1
-    }
+unsigned_numerical_index!(usize, u64, u128, u32, u16, u8);
+
+/// Signed indices are offset by their minimum, which flips the sign bit of their unsigned representation.
+/// The minimum is a multiple of every power of two that fits, so with those alignments a block of signed indices
+/// begins at a multiple of the alignment, just like a block of unsigned ones.
+macro_rules! signed_numerical_index {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl NumericalIndex for $t {
+                const ONE: Self = 1;
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn is_min(self) -> bool {
+                    self == <$t>::MIN
+                }
+
+                fn modulo(self, divisor: Self) -> Self {
+                    from_offset!($t, offset!(self, $t, $u) % divisor as $u)
+                }
+
+                fn divide(self, divisor: Self) -> Self {
+                    from_offset!($t, offset!(self, $t, $u) / divisor as $u)
+                }
+
+                fn block(self, alignment: Self) -> Self {
+                    let alignment = alignment as $u;
+                    from_offset!($t, (offset!(self, $t, $u) / alignment) * alignment)
+                }
+
+                fn last_in_block(self, alignment: Self) -> Self {
+                    self.block(alignment).saturating_add(alignment - 1)
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_next(self) -> Option<Self> {
+                    <$t>::checked_add(self, 1)
+                }
+
+                fn checked_prev(self) -> Option<Self> {
+                    <$t>::checked_sub(self, 1)
+                }
+
+                fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
+                    self..=self.saturating_add(alignment - 1)
+                }
+
+                fn offset_from(self, origin: Self) -> usize {
+                    assert!(self >= origin, "index should not be before the origin");
+                    self.wrapping_sub(origin) as $u as usize
+                }
+            }
+        )*
+    };
 }
 
-impl NumericalIndex for u32 {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
-
-    fn next(self) -> Self {
-        self + 1
-    }
+/// The offset of a signed index, as its unsigned counterpart.
+macro_rules! offset {
+    ($i:expr, $t:ty, $u:ty) => {
+        $i.wrapping_sub(<$t>::MIN) as $u
+    };
 }
 
-impl NumericalIndex for u16 {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
-
-    fn next(self) -> Self {
-        self + 1
-    }
+/// The signed index with the given offset.
+macro_rules! from_offset {
+    ($t:ty, $offset:expr) => {
+        ($offset as $t).wrapping_add(<$t>::MIN)
+    };
 }
 
-impl NumericalIndex for u8 {
-    fn is_zero(self) -> bool {
-        self == 0
-    }
-
-    fn modulo(self, divisor: Self) -> Self {
-        self % divisor
-    }
-
-    fn divide(self, divisor: Self) -> Self {
-        self / divisor
-    }
-
-    fn block(self, alignment: Self) -> Self {
-        (self / alignment) * alignment
-    }
-
-    fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
-        self..self + alignment
-    }
+signed_numerical_index!(isize => usize, i64 => u64, i128 => u128, i32 => u32, i16 => u16, i8 => u8);
+
+/// `NonZero` indices are offset by one, so the smallest index is one and blocks begin just after a multiple of the alignment.
+macro_rules! nonzero_numerical_index {
+    ($($t:ty),*) => {
+        $(
+            impl NumericalIndex for $t {
+                const ONE: Self = <$t>::MIN;
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn is_min(self) -> bool {
+                    self == <$t>::MIN
+                }
+
+                fn modulo(self, divisor: Self) -> Self {
+                    <$t>::MIN.saturating_add((self.get() - 1) % divisor.get())
+                }
+
+                fn divide(self, divisor: Self) -> Self {
+                    <$t>::MIN.saturating_add((self.get() - 1) / divisor.get())
+                }
+
+                fn block(self, alignment: Self) -> Self {
+                    let alignment = alignment.get();
+                    <$t>::MIN.saturating_add((self.get() - 1) / alignment * alignment)
+                }
+
+                fn last_in_block(self, alignment: Self) -> Self {
+                    self.block(alignment).saturating_add(alignment.get() - 1)
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other.get())
+                }
+
+                fn checked_next(self) -> Option<Self> {
+                    <$t>::checked_add(self, 1)
+                }
+
+                fn checked_prev(self) -> Option<Self> {
+                    <$t>::new(self.get() - 1)
+                }
+
+                fn range(self, alignment: Self) -> impl Iterator<Item = Self> {
+                    (self.get()..=self.get().saturating_add(alignment.get() - 1)).filter_map(<$t>::new)
+                }
+
+                fn offset_from(self, origin: Self) -> usize {
+                    (self.get() - origin.get()) as usize
+                }
+            }
+        )*
+    };
+}
 
-    fn next(self) -> Self {
-        self + 1
+nonzero_numerical_index!(
+    NonZeroUsize,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroU32,
+    NonZeroU16,
+    NonZeroU8
+);
+
+#[cfg(test)]
+mod test {
+    use super::NumericalIndex;
+    use proptest::prelude::*;
+    use std::num::NonZeroU8;
+
+    macro_rules! test_top_of_index_space {
+        ($name:ident, $t:ty) => {
+            proptest! {
+                #[test]
+                fn $name(i in any::<$t>(), shift in 0..<$t>::BITS) {
+                    let alignment: $t = 1 << shift;
+                    let block = i.block(alignment);
+                    let last = i.last_in_block(alignment);
+
+                    assert!(block <= i && i <= last);
+                    assert_eq!(last - block, alignment - 1);
+                    assert_eq!(last.checked_next(), last.checked_add(1));
+                    assert_eq!(NumericalIndex::checked_add(last, 1).is_none(), last == <$t>::MAX);
+
+                    if shift < 8 {
+                        let range: Vec<$t> = block.range(alignment).collect();
+                        assert_eq!(range.len(), alignment as usize);
+                        assert_eq!(range.last(), Some(&last));
+                    }
+                }
+            }
+        };
+    }
+
+    test_top_of_index_space!(test_usize, usize);
+    test_top_of_index_space!(test_u64, u64);
+    test_top_of_index_space!(test_u128, u128);
+    test_top_of_index_space!(test_u32, u32);
+    test_top_of_index_space!(test_u16, u16);
+    test_top_of_index_space!(test_u8, u8);
+
+    macro_rules! test_signed_index_space {
+        ($name:ident, $t:ty) => {
+            proptest! {
+                #[test]
+                fn $name(i in any::<$t>(), shift in 0..<$t>::BITS - 1) {
+                    let alignment: $t = 1 << shift;
+                    let block = i.block(alignment);
+                    let last = i.last_in_block(alignment);
+
+                    assert!(block <= i && i <= last);
+                    assert_eq!(last.wrapping_sub(block), alignment - 1);
+                    assert_eq!(block, i.div_euclid(alignment) * alignment);
+                    assert_eq!(block.modulo(alignment).is_min(), true);
+                    assert_eq!(NumericalIndex::checked_add(last, 1).is_none(), last == <$t>::MAX);
+                }
+            }
+        };
+    }
+
+    test_signed_index_space!(test_isize, isize);
+    test_signed_index_space!(test_i64, i64);
+    test_signed_index_space!(test_i128, i128);
+    test_signed_index_space!(test_i32, i32);
+    test_signed_index_space!(test_i16, i16);
+    test_signed_index_space!(test_i8, i8);
+
+    #[test]
+    fn test_signed_offsets() {
+        assert!(i8::MIN.is_min());
+        assert!(!0_i8.is_min());
+        assert_eq!((-1_i8).block(16), -16);
+        assert_eq!((-1_i8).last_in_block(16), -1);
+        assert_eq!(5_i8.modulo(4), i8::MIN + 1);
+        assert_eq!(i8::MIN.divide(4), i8::MIN);
+        assert_eq!((-3_i8).range(4).collect::<Vec<_>>(), vec![-3, -2, -1, 0]);
+        assert_eq!((-100_i8).block(100), -128);
+        assert_eq!(120_i8.last_in_block(100), 127);
+        assert_eq!(127_i8.offset_from(-128), 255);
+        assert!(<i8 as NumericalIndex>::MIN.is_min());
+        assert_eq!(NumericalIndex::checked_prev(i8::MIN), None);
+        assert_eq!(NumericalIndex::checked_prev(0_i8), Some(-1));
+    }
+
+    #[test]
+    fn test_nonzero_offsets() {
+        let n = |i: u8| NonZeroU8::new(i).unwrap();
+        let four = n(4);
+
+        assert!(n(1).is_min());
+        assert_eq!(n(4).block(four), n(1));
+        assert_eq!(n(5).block(four), n(5));
+        assert_eq!(n(6).last_in_block(four), n(8));
+        assert!(n(9).modulo(four).is_min());
+        assert_eq!(n(9).divide(four), n(3));
+        assert_eq!(n(254).last_in_block(four), n(255));
+        assert_eq!(n(253).range(four).count(), 3);
+        assert_eq!(NumericalIndex::checked_next(n(255)), None);
+        assert_eq!(NumericalIndex::checked_prev(n(1)), None);
+        assert_eq!(NumericalIndex::checked_prev(n(2)), Some(n(1)));
+        assert_eq!(<NonZeroU8 as NumericalIndex>::MIN, n(1));
+        assert_eq!(NumericalIndex::checked_add(n(250), n(5)), Some(n(255)));
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, NumericalIndex)]
+    struct UserId(u64);
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, NumericalIndex)]
+    struct Signed {
+        id: i32,
+    }
+
+    #[test]
+    fn test_derive() {
+        assert_eq!(Signed::ONE, Signed { id: 1 });
+        assert_eq!(Signed { id: -3 }.block(Signed { id: 8 }), Signed { id: -8 });
+        assert_eq!(Signed { id: 5 }.offset_from(Signed { id: -5 }), 10);
+        assert_eq!(Signed { id: i32::MAX }.checked_next(), None);
+        assert_eq!(Signed::MIN.checked_prev(), None);
+        assert_eq!(Signed::MAX, Signed { id: i32::MAX });
+        assert_eq!(Signed { id: -1 }.range(Signed { id: 2 }).count(), 2);
+    }
+
+    #[test]
+    fn test_newtype() {
+        assert_eq!(UserId::ONE, UserId(1));
+        assert_eq!(UserId(70).block(UserId(64)), UserId(64));
+        assert_eq!(UserId(70).last_in_block(UserId(64)), UserId(127));
+        assert_eq!(UserId(u64::MAX).checked_next(), None);
+        assert_eq!(UserId(1).checked_prev(), Some(UserId::MIN));
+        assert_eq!(
+            UserId(6).range(UserId(3)).collect::<Vec<_>>(),
+            vec![UserId(6), UserId(7), UserId(8)]
+        );
+    }
+
+    #[test]
+    fn test_partial_final_block() {
+        assert_eq!(250_u8.last_in_block(100), 255);
+        assert_eq!(200_u8.range(100).count(), 56);
+        assert_eq!(u8::MAX.checked_next(), None);
+        assert_eq!(254_u8.next(), 255);
+        assert_eq!(NumericalIndex::checked_prev(0_u8), None);
     }
 }
-
-
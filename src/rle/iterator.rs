@@ -1,3 +1,5 @@
+use crate::merge::SkipTo;
+
 use super::Rle;
 
 /// An iterator over the runs of a Rle. It returns one result per run.
@@ -52,3 +54,59 @@ impl<'a, Value> Iterator for RleRunIterator<'a, Value> {
         Some((value, length))
     }
 }
+
+/// An iterator over the indices of the `true` elements of a `Rle<bool>`, in increasing order.
+/// It skips ahead a run at a time (see `SkipTo`).
+pub struct RleOnesIterator<'a> {
+    runs: RleRunIterator<'a, bool>,
+    /// The index of the first element after the runs read so far.
+    end: u128,
+    /// The next index of the current run of `true`, which ends at `end`.
+    next: u128,
+}
+
+impl<'a> RleOnesIterator<'a> {
+    pub(super) fn new(rle: &'a Rle<bool>) -> Self {
+        RleOnesIterator {
+            runs: RleRunIterator::new(rle),
+            end: 0,
+            next: 0,
+        }
+    }
+
+    /// Read runs until the current run of `true` contains `target`, or comes after it.
+    fn seek(&mut self, target: u128) -> Option<()> {
+        while self.end <= target.max(self.next) {
+            let Some((value, length)) = self.runs.next() else {
+                self.next = self.end;
+                return None;
+            };
+            if *value {
+                self.next = self.end;
+            }
+            self.end += length;
+            if !*value {
+                self.next = self.end;
+            }
+        }
+        self.next = self.next.max(target);
+        Some(())
+    }
+}
+
+impl<'a> Iterator for RleOnesIterator<'a> {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        self.seek(0)?;
+        self.next += 1;
+        Some(self.next - 1)
+    }
+}
+
+impl<'a> SkipTo for RleOnesIterator<'a> {
+    fn skip_to(&mut self, target: u128) -> Option<u128> {
+        self.seek(target)?;
+        self.next()
+    }
+}
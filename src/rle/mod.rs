@@ -2,8 +2,11 @@ mod decode_consecutive_runs;
 mod encode_consecutive_runs;
 mod instruction;
 mod iterator;
+mod rle_bitset;
 mod run_length_encoding;
 
 pub use decode_consecutive_runs::*;
 pub use encode_consecutive_runs::*;
+pub use iterator::*;
+pub use rle_bitset::*;
 pub use run_length_encoding::*;
@@ -0,0 +1,374 @@
+use smallvec::SmallVec;
+
+use crate::{
+    bitset::SparseBitset,
+    block::{AlignedBlock, AlignedBlockFromIterator, BitSearch},
+    interval::RangeSet,
+};
+
+use super::{instruction::RleInstruction, EncodeConsecutiveRuns, Rle};
+
+/// A run-length-encoded sequence of booleans.
+///
+/// Runs of booleans must alternate, so unlike an `Rle<bool>` only the value of the first run is stored, followed by the packed run lengths.
+/// Runs are never empty, and adjacent runs never have the same value, so each sequence has exactly one representation.
+/// Bulk operations walk the runs of both operands together, taking time linear in the number of runs rather than elements.
+/// For masks made of long runs this is orders of magnitude smaller than a bitmap.
+///
+/// Elements are counted from zero, as in an `Rle`. A sequence has a length, and is treated as `false` past its end.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RleBitset {
+    first: bool,
+    lengths: SmallVec<[RleInstruction; std::mem::size_of::<usize>()]>,
+}
+
+impl RleBitset {
+    /// Construct a sequence from runs of `(value, length)`. Empty runs are skipped and equal runs are merged.
+    pub fn from_runs<I>(runs: I) -> Self
+    where
+        I: IntoIterator<Item = (bool, u128)>,
+    {
+        let mut builder = RunBuilder::default();
+        for run in runs {
+            builder.push(run);
+        }
+        builder.finish()
+    }
+
+    /// Iterate over every run, as `(value, length)`.
+    pub fn runs(&self) -> impl Iterator<Item = (bool, u128)> + '_ {
+        let mut value = !self.first;
+        let mut i = 0;
+        std::iter::from_fn(move || {
+            let instruction = self.lengths.get(i)?;
+            assert!(
+                instruction.is_next_value(),
+                "should always begin a run on a next-value token"
+            );
+            i += 1;
+            let mut length = 0;
+            while let Some(instruction) = self.lengths.get(i).filter(|x| !x.is_next_value()) {
+                length += instruction.unpack();
+                i += 1;
+            }
+            value = !value;
+            Some((value, length))
+        })
+    }
+
+    /// The number of runs.
+    pub fn run_count(&self) -> usize {
+        self.lengths.iter().filter(|x| x.is_next_value()).count()
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> u128 {
+        self.runs().map(|(_, length)| length).sum()
+    }
+
+    /// True iff there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// The element at an index, which is `false` past the end.
+    pub fn fetch(&self, index: u128) -> bool {
+        let mut start = 0;
+        for (value, length) in self.runs() {
+            if index - start < length {
+                return value;
+            }
+            start += length;
+        }
+        false
+    }
+
+    /// The number of `true` elements.
+    pub fn count_ones(&self) -> u128 {
+        self.runs()
+            .filter(|(value, _)| *value)
+            .map(|(_, length)| length)
+            .sum()
+    }
+
+    /// The number of `true` elements before `index`.
+    pub fn rank(&self, index: u128) -> u128 {
+        let mut start = 0;
+        let mut ones = 0;
+        for (value, length) in self.runs() {
+            if start >= index {
+                break;
+            }
+            if value {
+                ones += length.min(index - start);
+            }
+            start += length;
+        }
+        ones
+    }
+
+    /// The index of the `true` element with rank `rank` (counting from zero), or `None` if there are not that many.
+    pub fn select(&self, mut rank: u128) -> Option<u128> {
+        let mut start = 0;
+        for (value, length) in self.runs() {
+            if value {
+                if rank < length {
+                    return Some(start + rank);
+                }
+                rank -= length;
+            }
+            start += length;
+        }
+        None
+    }
+
+    /// Flip every element.
+    pub fn not(&self) -> Self {
+        RleBitset {
+            first: !self.first && !self.is_empty(),
+            lengths: self.lengths.clone(),
+        }
+    }
+
+    /// The elements that are `true` in both sequences.
+    pub fn and(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// The elements that are `true` in either sequence.
+    pub fn or(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// The elements that are `true` in exactly one of the sequences.
+    pub fn xor(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a ^ b)
+    }
+
+    /// Combine two sequences element by element, one overlapping piece of a run at a time.
+    /// The result is as long as the longer sequence.
+    fn combine(&self, other: &Self, op: impl Fn(bool, bool) -> bool) -> Self {
+        let (mut a, mut b) = (self.runs(), other.runs());
+        let (mut run_a, mut run_b) = (a.next(), b.next());
+        let mut builder = RunBuilder::default();
+        loop {
+            let length = match (run_a, run_b) {
+                (None, None) => return builder.finish(),
+                (Some((_, x)), None) | (None, Some((_, x))) => x,
+                (Some((_, x)), Some((_, y))) => x.min(y),
+            };
+            let value_a = run_a.is_some_and(|(value, _)| value);
+            let value_b = run_b.is_some_and(|(value, _)| value);
+            builder.push((op(value_a, value_b), length));
+            run_a = consume(run_a, length).or_else(|| a.next());
+            run_b = consume(run_b, length).or_else(|| b.next());
+        }
+    }
+
+    /// The runs of a `Rle<bool>`.
+    pub fn from_rle(rle: &Rle<bool>) -> Self {
+        Self::from_runs(rle.run_iterator().map(|(value, length)| (*value, length)))
+    }
+
+    /// An `Rle<bool>` with the same elements.
+    pub fn to_rle(&self) -> Rle<bool> {
+        let mut result = Rle::default();
+        for run in self.runs() {
+            result.append_run(run);
+        }
+        result
+    }
+
+    /// The sequence whose `true` elements are the set bits of a bitset. It ends with the last set bit.
+    pub fn from_sparse_bitset<B>(bitset: &SparseBitset<u128, B>) -> Self
+    where
+        B: BitSearch<Index = u128>,
+    {
+        let mut builder = RunBuilder::default();
+        let mut next = 0;
+        for range in RangeSet::from_bitset(bitset).iter() {
+            let (start, end) = range.into_inner();
+            builder.push((false, start - next));
+            builder.push((
+                true,
+                (end - start)
+                    .checked_add(1)
+                    .expect("a run should not cover every u128 index"),
+            ));
+            next = end.wrapping_add(1);
+        }
+        builder.finish()
+    }
+
+    /// A bitset whose set bits are the `true` elements. Every block that overlaps a run of `true` is stored.
+    pub fn to_sparse_bitset<B>(&self) -> SparseBitset<u128, B>
+    where
+        B: AlignedBlock<Index = u128, Item = bool> + AlignedBlockFromIterator,
+    {
+        let mut ones = RangeSet::default();
+        let mut start = 0_u128;
+        for (value, length) in self.runs() {
+            if value {
+                ones.insert(start..=start + (length - 1));
+            }
+            start += length;
+        }
+        ones.to_bitset()
+    }
+}
+
+impl FromIterator<bool> for RleBitset {
+    fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
+        Self::from_runs(EncodeConsecutiveRuns::new(iter.into_iter()))
+    }
+}
+
+/// What is left of a run after taking `length` elements from it, if anything.
+fn consume(run: Option<(bool, u128)>, length: u128) -> Option<(bool, u128)> {
+    let (value, remaining) = run?;
+    (remaining > length).then(|| (value, remaining - length))
+}
+
+/// Accumulates runs, merging equal neighbours, so that only alternating runs are stored.
+#[derive(Default)]
+struct RunBuilder {
+    result: RleBitset,
+    pending: Option<(bool, u128)>,
+}
+
+impl RunBuilder {
+    fn push(&mut self, (value, length): (bool, u128)) {
+        if length == 0 {
+            return;
+        }
+        match &mut self.pending {
+            Some((pending, total)) if *pending == value => *total += length,
+            _ => {
+                self.flush();
+                self.pending = Some((value, length));
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((value, length)) = self.pending.take() {
+            if self.result.lengths.is_empty() {
+                self.result.first = value;
+            }
+            self.result.lengths.extend(RleInstruction::pack(length));
+        }
+    }
+
+    fn finish(mut self) -> RleBitset {
+        self.flush();
+        self.result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RleBitset;
+    use crate::bitset::SparseBitset;
+    use crate::block::{BlockCollection, BlockStore};
+    use crate::rle::Rle;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_runs() {
+        let bits = RleBitset::from_runs([(false, 3), (false, 2), (true, 0), (true, 4), (false, 1)]);
+        assert_eq!(
+            bits.runs().collect::<Vec<_>>(),
+            vec![(false, 5), (true, 4), (false, 1)]
+        );
+        assert_eq!(bits.run_count(), 3);
+        assert_eq!(bits.len(), 10);
+        assert_eq!(bits.count_ones(), 4);
+        assert!(bits.fetch(5));
+        assert!(!bits.fetch(9));
+        assert!(!bits.fetch(u128::MAX));
+
+        assert_eq!(
+            bits.not().runs().collect::<Vec<_>>(),
+            vec![(true, 5), (false, 4), (true, 1)]
+        );
+        assert!(RleBitset::default().is_empty());
+        assert_eq!(RleBitset::default().not(), RleBitset::default());
+    }
+
+    #[test]
+    fn test_long_runs() {
+        let mask = RleBitset::from_runs([(true, 1 << 100), (false, 1 << 90), (true, 3)]);
+        let other = RleBitset::from_runs([(false, 1 << 99), (true, 1 << 101)]);
+
+        assert_eq!(mask.count_ones(), (1 << 100) + 3);
+        assert_eq!(mask.rank(1 << 100), 1 << 100);
+        assert_eq!(mask.select(1 << 100), Some((1 << 100) + (1 << 90)));
+        assert_eq!(mask.select((1 << 100) + 3), None);
+        assert_eq!(
+            mask.and(&other).runs().collect::<Vec<_>>(),
+            vec![
+                (false, 1 << 99),
+                (true, 1 << 99),
+                (false, 1 << 90),
+                (true, 3),
+                (false, (1 << 101) - (1 << 99) - (1 << 90) - 3)
+            ]
+        );
+        assert_eq!(mask.or(&other).count_ones(), (1 << 99) + (1 << 101));
+        assert!(std::mem::size_of_val(&mask) <= 32);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let mut bitset: SparseBitset<u128> = SparseBitset::default();
+        for i in [1, 2, 3, 64, 65, 1000] {
+            bitset.store(i, true);
+        }
+
+        let bits = RleBitset::from_sparse_bitset(&bitset);
+        assert_eq!(
+            bits.runs().collect::<Vec<_>>(),
+            vec![
+                (false, 1),
+                (true, 3),
+                (false, 60),
+                (true, 2),
+                (false, 934),
+                (true, 1)
+            ]
+        );
+        let round_trip: SparseBitset<u128> = bits.to_sparse_bitset();
+        assert_eq!(round_trip.blocks().len(), 2);
+        assert_eq!(
+            round_trip.iter().collect::<Vec<_>>(),
+            bitset.iter().collect::<Vec<_>>()
+        );
+
+        let rle: Rle<bool> = bits.to_rle();
+        assert_eq!(rle.ones().collect::<Vec<_>>(), vec![1, 2, 3, 64, 65, 1000]);
+        assert_eq!(RleBitset::from_rle(&rle), bits);
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_vec(a: Vec<bool>, b: Vec<bool>, index in 0..300_u128) {
+            let (ra, rb): (RleBitset, RleBitset) = (a.iter().copied().collect(), b.iter().copied().collect());
+            let get = |v: &Vec<bool>, i: usize| v.get(i).copied().unwrap_or(false);
+            let n = a.len().max(b.len());
+
+            assert_eq!(ra.len(), a.len() as u128);
+            assert_eq!(ra.fetch(index), get(&a, index as usize));
+            assert_eq!(ra.count_ones(), a.iter().filter(|x| **x).count() as u128);
+            assert_eq!(ra.rank(index), a.iter().take(index as usize).filter(|x| **x).count() as u128);
+            assert_eq!(ra.select(index), a.iter().enumerate().filter(|(_, x)| **x).nth(index as usize).map(|(i, _)| i as u128));
+            assert_eq!(ra.not().runs().flat_map(|(v, l)| std::iter::repeat(v).take(l as usize)).collect::<Vec<_>>(), a.iter().map(|x| !x).collect::<Vec<_>>());
+
+            let and: Vec<bool> = (0..n).map(|i| get(&a, i) & get(&b, i)).collect();
+            let or: Vec<bool> = (0..n).map(|i| get(&a, i) | get(&b, i)).collect();
+            let xor: Vec<bool> = (0..n).map(|i| get(&a, i) ^ get(&b, i)).collect();
+            assert_eq!(ra.and(&rb), and.into_iter().collect());
+            assert_eq!(ra.or(&rb), or.into_iter().collect());
+            assert_eq!(ra.xor(&rb), xor.into_iter().collect());
+        }
+    }
+}
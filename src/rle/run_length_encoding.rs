@@ -2,7 +2,9 @@ use crate::rle::instruction::RleInstruction;
 use smallvec::{smallvec, SmallVec};
 
 use super::{
-    decode_consecutive_runs::DecodeConsecutiveRuns, iterator::RleRunIterator, EncodeConsecutiveRuns,
+    decode_consecutive_runs::DecodeConsecutiveRuns,
+    iterator::{RleOnesIterator, RleRunIterator},
+    EncodeConsecutiveRuns,
 };
 
 /// A run-length-encoded vector.
@@ -75,19 +77,28 @@ impl<Value> Rle<Value> {
     }
 
     /// Iterate over every run (get an iterator that returns a single (value,length) pair for each run).
-    pub fn run_iterator(&self) -> RleRunIterator<Value> {
+    pub fn run_iterator(&self) -> RleRunIterator<'_, Value> {
         RleRunIterator::new(&self)
     }
 
     /// Iterate over every value (get an iterator that returns values, with each value repeated as many times as necessary to complete its run).
-    pub fn iterator(&self) -> DecodeConsecutiveRuns<RleRunIterator<Value>, &Value> {
+    pub fn iterator(&self) -> DecodeConsecutiveRuns<RleRunIterator<'_, Value>, &Value> {
         DecodeConsecutiveRuns::new(self.run_iterator())
     }
 }
 
+impl Rle<bool> {
+    /// Iterate over the indices of every `true` element, counting from zero.
+    /// The iterator skips whole runs at a time (see `SkipTo`).
+    pub fn ones(&self) -> RleOnesIterator<'_> {
+        RleOnesIterator::new(self)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Rle;
+    use crate::merge::SkipTo;
     use proptest::prelude::*;
 
     #[test]
@@ -137,7 +148,38 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_ones() {
+        let mut rle = Rle::default();
+        rle.append_run((false, 3));
+        rle.append_run((true, 2));
+        rle.append_run((false, 1_000_000_000));
+        rle.append_run((true, 2));
+        assert_eq!(
+            rle.ones().collect::<Vec<_>>(),
+            vec![3, 4, 1_000_000_005, 1_000_000_006]
+        );
+
+        let mut ones = rle.ones();
+        assert_eq!(ones.skip_to(4), Some(4));
+        assert_eq!(ones.skip_to(5), Some(1_000_000_005));
+        assert_eq!(ones.next(), Some(1_000_000_006));
+        assert_eq!(ones.next(), None);
+    }
+
     proptest! {
+        #[test]
+        fn test_ones_skip_to(original: Vec<bool>, targets in proptest::collection::vec(0..300_u128, 0..5)) {
+            let mut rle = Rle::default();
+            rle.extend(original.iter().copied());
+            let mut ones = rle.ones();
+            let mut expected = (0..original.len() as u128).filter(|i| original[*i as usize]);
+            for target in targets {
+                assert_eq!(ones.skip_to(target), expected.find(|i| *i >= target));
+            }
+            assert_eq!(ones.collect::<Vec<_>>(), expected.collect::<Vec<_>>());
+        }
+
         #[test]
         fn test_iterator_u8(original: Vec<u8>) {
             let mut rle = Rle::default();
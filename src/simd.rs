@@ -0,0 +1,361 @@
+//! Bulk kernels over words and numbers, which the blocks use for their bulk operations.
+//!
+//! On x86_64 each kernel checks at runtime for AVX2 (and for `popcnt`), falling back to SSE2, which every x86_64
+//! processor has. On other architectures the kernels are portable loops, written so that the compiler can vectorize them.
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// A bitwise operation that combines two words.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum BitOp {
+    And,
+    Or,
+    Xor,
+    AndNot,
+}
+
+impl BitOp {
+    fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            BitOp::And => a & b,
+            BitOp::Or => a | b,
+            BitOp::Xor => a ^ b,
+            BitOp::AndNot => a & !b,
+        }
+    }
+}
+
+/// Combine each word of `a` with the matching word of `b`, in place.
+pub(crate) fn bitwise(op: BitOp, a: &mut [u64], b: &[u64]) {
+    assert_eq!(a.len(), b.len(), "word arrays must have the same length");
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            #[allow(unsafe_code)]
+            // SAFETY: AVX2 is available, which is the only requirement of `bitwise_avx2`.
+            unsafe {
+                bitwise_avx2(op, a, b)
+            };
+        } else {
+            #[allow(unsafe_code)]
+            // SAFETY: SSE2 is part of x86_64, so it is always available.
+            unsafe {
+                bitwise_sse2(op, a, b)
+            };
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    bitwise_scalar(op, a, b);
+}
+
+fn bitwise_scalar(op: BitOp, a: &mut [u64], b: &[u64]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a = op.apply(*a, *b);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn bitwise_avx2(op: BitOp, a: &mut [u64], b: &[u64]) {
+    // Match once, so that each loop is specialized to a single instruction.
+    match op {
+        BitOp::And => bitwise_avx2_with(a, b, |x, y| _mm256_and_si256(x, y)),
+        BitOp::Or => bitwise_avx2_with(a, b, |x, y| _mm256_or_si256(x, y)),
+        BitOp::Xor => bitwise_avx2_with(a, b, |x, y| _mm256_xor_si256(x, y)),
+        // `andnot` negates its first operand.
+        BitOp::AndNot => bitwise_avx2_with(a, b, |x, y| _mm256_andnot_si256(y, x)),
+    }
+    let done = a.len() / 4 * 4;
+    bitwise_scalar(op, &mut a[done..], &b[done..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+fn bitwise_avx2_with(a: &mut [u64], b: &[u64], f: impl Fn(__m256i, __m256i) -> __m256i) {
+    for (a, b) in a.chunks_exact_mut(4).zip(b.chunks_exact(4)) {
+        let a = a.as_mut_ptr() as *mut __m256i;
+        let b = b.as_ptr() as *const __m256i;
+        #[allow(unsafe_code)]
+        // SAFETY: each chunk is exactly four words, which is 256 bits, and unaligned loads and stores are used.
+        unsafe {
+            _mm256_storeu_si256(a, f(_mm256_loadu_si256(a), _mm256_loadu_si256(b)))
+        };
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+fn bitwise_sse2(op: BitOp, a: &mut [u64], b: &[u64]) {
+    match op {
+        BitOp::And => bitwise_sse2_with(a, b, |x, y| _mm_and_si128(x, y)),
+        BitOp::Or => bitwise_sse2_with(a, b, |x, y| _mm_or_si128(x, y)),
+        BitOp::Xor => bitwise_sse2_with(a, b, |x, y| _mm_xor_si128(x, y)),
+        BitOp::AndNot => bitwise_sse2_with(a, b, |x, y| _mm_andnot_si128(y, x)),
+    }
+    let done = a.len() / 2 * 2;
+    bitwise_scalar(op, &mut a[done..], &b[done..]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+#[inline]
+fn bitwise_sse2_with(a: &mut [u64], b: &[u64], f: impl Fn(__m128i, __m128i) -> __m128i) {
+    for (a, b) in a.chunks_exact_mut(2).zip(b.chunks_exact(2)) {
+        let a = a.as_mut_ptr() as *mut __m128i;
+        let b = b.as_ptr() as *const __m128i;
+        #[allow(unsafe_code)]
+        // SAFETY: each chunk is exactly two words, which is 128 bits, and unaligned loads and stores are used.
+        unsafe {
+            _mm_storeu_si128(a, f(_mm_loadu_si128(a), _mm_loadu_si128(b)))
+        };
+    }
+}
+
+/// The number of set bits in all of the words.
+pub(crate) fn count_ones(words: &[u64]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            #[allow(unsafe_code)]
+            // SAFETY: AVX2 is available, which is the only requirement of `count_ones_avx2`.
+            return unsafe { count_ones_avx2(words) };
+        }
+        if is_x86_feature_detected!("popcnt") {
+            #[allow(unsafe_code)]
+            // SAFETY: `popcnt` is available, which is the only requirement of `count_ones_popcnt`.
+            return unsafe { count_ones_popcnt(words) };
+        }
+    }
+    count_ones_scalar(words)
+}
+
+fn count_ones_scalar(words: &[u64]) -> usize {
+    words.iter().map(|w| w.count_ones() as usize).sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+fn count_ones_popcnt(words: &[u64]) -> usize {
+    count_ones_scalar(words)
+}
+
+/// Count the bits of each nibble with a table lookup, then add up the bytes of each lane (Mula's algorithm).
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn count_ones_avx2(words: &[u64]) -> usize {
+    let chunks = words.chunks_exact(4);
+    let remainder = chunks.remainder();
+    let table = _mm256_setr_epi8(
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, 0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3,
+        3, 4,
+    );
+    let low_nibbles = _mm256_set1_epi8(0x0f);
+    let mut total = _mm256_setzero_si256();
+    for chunk in chunks {
+        #[allow(unsafe_code)]
+        // SAFETY: each chunk is exactly four words, which is 256 bits, and an unaligned load is used.
+        let v = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+        let low = _mm256_and_si256(v, low_nibbles);
+        let high = _mm256_and_si256(_mm256_srli_epi16::<4>(v), low_nibbles);
+        let bytes = _mm256_add_epi8(
+            _mm256_shuffle_epi8(table, low),
+            _mm256_shuffle_epi8(table, high),
+        );
+        total = _mm256_add_epi64(total, _mm256_sad_epu8(bytes, _mm256_setzero_si256()));
+    }
+    let mut lanes = [0_u64; 4];
+    #[allow(unsafe_code)]
+    // SAFETY: the array is exactly 256 bits, and an unaligned store is used.
+    unsafe {
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, total)
+    };
+    lanes.iter().sum::<u64>() as usize + count_ones_scalar(remainder)
+}
+
+/// A number that the reduction kernels can add up and compare.
+pub trait SimdNumber: Copy + PartialOrd {
+    /// The identity of addition.
+    const ZERO: Self;
+    /// The identity of `min`.
+    const MAX: Self;
+    /// The identity of `max`.
+    const MIN: Self;
+    /// Sum of two numbers. Integers wrap around on overflow.
+    fn add(self, other: Self) -> Self;
+}
+
+macro_rules! simd_integer {
+    ($($t:ty),*) => {
+        $(
+            impl SimdNumber for $t {
+                const ZERO: Self = 0;
+                const MAX: Self = <$t>::MAX;
+                const MIN: Self = <$t>::MIN;
+
+                fn add(self, other: Self) -> Self {
+                    self.wrapping_add(other)
+                }
+            }
+        )*
+    };
+}
+
+simd_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+macro_rules! simd_float {
+    ($($t:ty),*) => {
+        $(
+            impl SimdNumber for $t {
+                const ZERO: Self = 0.0;
+                const MAX: Self = <$t>::INFINITY;
+                const MIN: Self = <$t>::NEG_INFINITY;
+
+                fn add(self, other: Self) -> Self {
+                    self + other
+                }
+            }
+        )*
+    };
+}
+
+simd_float!(f32, f64);
+
+/// How many independent accumulators the reductions keep, so that they fill a vector register.
+const LANES: usize = 16;
+
+/// The sum of the values. Floats are added up in several lanes, so rounding can differ from a sequential sum.
+pub(crate) fn sum<T: SimdNumber>(values: &[T]) -> T {
+    reduce(values, T::ZERO, T::add)
+}
+
+/// The smallest value, or `T::MAX` if there are none.
+pub(crate) fn min<T: SimdNumber>(values: &[T]) -> T {
+    reduce(values, T::MAX, |a, b| if b < a { b } else { a })
+}
+
+/// The largest value, or `T::MIN` if there are none.
+pub(crate) fn max<T: SimdNumber>(values: &[T]) -> T {
+    reduce(values, T::MIN, |a, b| if b > a { b } else { a })
+}
+
+fn reduce<T: SimdNumber>(values: &[T], identity: T, f: impl Fn(T, T) -> T) -> T {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            #[allow(unsafe_code)]
+            // SAFETY: AVX2 is available, which is the only requirement of `reduce_avx2`.
+            return unsafe { reduce_avx2(values, identity, f) };
+        }
+    }
+    reduce_lanes(values, identity, f)
+}
+
+/// The same loop as `reduce_lanes`, compiled so that it can use AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn reduce_avx2<T: SimdNumber>(values: &[T], identity: T, f: impl Fn(T, T) -> T) -> T {
+    reduce_lanes(values, identity, f)
+}
+
+/// Reduce into independent lanes, which the compiler turns into vector operations, and then reduce the lanes.
+#[inline(always)]
+fn reduce_lanes<T: SimdNumber>(values: &[T], identity: T, f: impl Fn(T, T) -> T) -> T {
+    let mut lanes = [identity; LANES];
+    let chunks = values.chunks_exact(LANES);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        for (lane, v) in lanes.iter_mut().zip(chunk.iter()) {
+            *lane = f(*lane, *v);
+        }
+    }
+    lanes
+        .into_iter()
+        .chain(remainder.iter().copied())
+        .fold(identity, f)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitOp, SimdNumber};
+    use proptest::prelude::*;
+
+    fn scalar_bitwise(op: BitOp, a: &[u64], b: &[u64]) -> Vec<u64> {
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| op.apply(*a, *b))
+            .collect()
+    }
+
+    fn words() -> impl Strategy<Value = (Vec<u64>, Vec<u64>)> {
+        (0..40_usize).prop_flat_map(|n| {
+            (
+                proptest::collection::vec(any::<u64>(), n),
+                proptest::collection::vec(any::<u64>(), n),
+            )
+        })
+    }
+
+    #[test]
+    fn test_and_not_operand_order() {
+        let mut a = vec![0b1100; 9];
+        super::bitwise(BitOp::AndNot, &mut a, &[0b1010; 9]);
+        assert_eq!(a, vec![0b0100; 9]);
+    }
+
+    #[test]
+    fn test_reduce_empty() {
+        assert_eq!(super::sum::<u32>(&[]), 0);
+        assert_eq!(super::min::<i8>(&[]), i8::MAX);
+        assert_eq!(super::max::<f64>(&[]), f64::NEG_INFINITY);
+    }
+
+    proptest! {
+        #[test]
+        fn test_bitwise((a, b) in words()) {
+            for op in [BitOp::And, BitOp::Or, BitOp::Xor, BitOp::AndNot] {
+                let expected = scalar_bitwise(op, &a, &b);
+
+                let mut x = a.clone();
+                super::bitwise(op, &mut x, &b);
+                assert_eq!(x, expected);
+
+                #[cfg(target_arch = "x86_64")]
+                {
+                    let mut x = a.clone();
+                    #[allow(unsafe_code)]
+                    // SAFETY: SSE2 is part of x86_64, so it is always available.
+                    unsafe {
+                        super::bitwise_sse2(op, &mut x, &b)
+                    };
+                    assert_eq!(x, expected);
+                }
+            }
+        }
+
+        #[test]
+        fn test_count_ones((a, _) in words()) {
+            let expected = a.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+            assert_eq!(super::count_ones(&a), expected);
+            assert_eq!(super::count_ones_scalar(&a), expected);
+        }
+
+        #[test]
+        fn test_reduce(values in proptest::collection::vec(any::<i32>(), 0..100)) {
+            let sum = values.iter().fold(0_i32, |a, b| a.wrapping_add(*b));
+            assert_eq!(super::sum(&values), sum);
+            assert_eq!(super::min(&values), values.iter().copied().min().unwrap_or(i32::MAX));
+            assert_eq!(super::max(&values), values.iter().copied().max().unwrap_or(i32::MIN));
+            assert_eq!(super::reduce_lanes(&values, 0, SimdNumber::add), sum);
+        }
+
+        #[test]
+        fn test_reduce_float(values in proptest::collection::vec(-1000..1000_i32, 0..100)) {
+            // Small integers are summed exactly, in any order.
+            let floats: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+            assert_eq!(super::sum(&floats), values.iter().sum::<i32>() as f64);
+            assert_eq!(super::min(&floats), values.iter().copied().min().map_or(f64::INFINITY, f64::from));
+        }
+    }
+}
@@ -1,5 +1,7 @@
 mod pair;
+mod radix;
 mod sorted_pair;
 
 pub use pair::*;
+pub use radix::*;
 pub use sorted_pair::*;
@@ -0,0 +1,354 @@
+use super::Pair;
+
+/// Below this many elements, a bucket is finished with an insertion sort instead of another radix pass.
+const INSERTION_SORT_THRESHOLD: usize = 32;
+
+/// Buckets of up to this many elements are copied out and finished with an LSD radix sort.
+/// This bounds the scratch space of the entire sort to two buffers of this many elements.
+const SCRATCH_LIMIT: usize = 1 << 14;
+
+/// A key that can be sorted one byte at a time.
+pub trait RadixKey: Copy + Ord {
+    /// The number of bytes in the key.
+    const BYTES: usize;
+    /// Get a byte of the key, where byte 0 is the most significant byte.
+    fn radix(self, byte: usize) -> u8;
+}
+
+impl RadixKey for usize {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, byte: usize) -> u8 {
+        (self >> (8 * (Self::BYTES - 1 - byte))) as u8
+    }
+}
+
+impl RadixKey for u64 {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, byte: usize) -> u8 {
+        (self >> (8 * (Self::BYTES - 1 - byte))) as u8
+    }
+}
+
+impl RadixKey for u128 {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, byte: usize) -> u8 {
+        (self >> (8 * (Self::BYTES - 1 - byte))) as u8
+    }
+}
+
+impl RadixKey for u32 {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, byte: usize) -> u8 {
+        (self >> (8 * (Self::BYTES - 1 - byte))) as u8
+    }
+}
+
+impl RadixKey for u16 {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, byte: usize) -> u8 {
+        (self >> (8 * (Self::BYTES - 1 - byte))) as u8
+    }
+}
+
+impl RadixKey for u8 {
+    const BYTES: usize = std::mem::size_of::<Self>();
+
+    fn radix(self, _byte: usize) -> u8 {
+        self
+    }
+}
+
+/// Storage that can be sorted in place by a radix sort.
+/// Elements are addressed from zero, regardless of where the storage is positioned.
+///
+/// The sort partitions the storage in place by the most significant bytes of the key (American flag sort)
+/// until each bucket is small enough, then finishes each bucket with an LSD radix sort in a bounded scratch buffer.
+/// The scratch space never exceeds a fixed number of elements, no matter how large the storage is.
+/// The sort is not stable.
+pub trait RadixSortable {
+    /// Type of the element being sorted.
+    type Item;
+
+    /// The number of elements in the storage.
+    fn radix_len(&self) -> usize;
+    /// Get a reference to the element at the given offset.
+    fn radix_get(&self, i: usize) -> &Self::Item;
+    /// Get a mutable reference to the element at the given offset.
+    fn radix_get_mut(&mut self, i: usize) -> &mut Self::Item;
+    /// Exchange the elements at the two given offsets.
+    fn swap(&mut self, i: usize, j: usize);
+
+    /// Sort all elements.
+    fn radix_sort(&mut self)
+    where
+        Self::Item: RadixKey,
+    {
+        self.radix_sort_by_key(|x| *x);
+    }
+
+    /// Sort all elements by key.
+    fn radix_sort_by_key<K: RadixKey>(&mut self, f: impl Fn(&Self::Item) -> K)
+    where
+        Self::Item: Copy,
+    {
+        let len = self.radix_len();
+        let mut scratch = Scratch {
+            a: Vec::with_capacity(len.min(SCRATCH_LIMIT)),
+            b: Vec::with_capacity(len.min(SCRATCH_LIMIT)),
+        };
+        sort_range(self, &f, 0, len, 0, &mut scratch);
+    }
+}
+
+/// A pair of buffers that an LSD radix sort alternates between.
+struct Scratch<T> {
+    a: Vec<T>,
+    b: Vec<T>,
+}
+
+/// Sort the range `lo..hi`, assuming that all of the keys in the range agree on every byte before `byte`.
+fn sort_range<S, K>(
+    s: &mut S,
+    f: &impl Fn(&S::Item) -> K,
+    lo: usize,
+    hi: usize,
+    byte: usize,
+    scratch: &mut Scratch<S::Item>,
+) where
+    S: RadixSortable + ?Sized,
+    S::Item: Copy,
+    K: RadixKey,
+{
+    if hi - lo <= INSERTION_SORT_THRESHOLD {
+        insertion_sort(s, f, lo, hi);
+        return;
+    }
+
+    if hi - lo <= SCRATCH_LIMIT {
+        lsd_sort(s, f, lo, hi, byte, scratch);
+        return;
+    }
+
+    let mut counts = [0_usize; 256];
+    for i in lo..hi {
+        counts[f(s.radix_get(i)).radix(byte) as usize] += 1;
+    }
+
+    let mut next = [0_usize; 256];
+    let mut end = [0_usize; 256];
+    let mut offset = lo;
+    for b in 0..256 {
+        next[b] = offset;
+        offset += counts[b];
+        end[b] = offset;
+    }
+
+    for b in 0..256 {
+        while next[b] < end[b] {
+            let r = f(s.radix_get(next[b])).radix(byte) as usize;
+            if r == b {
+                next[b] += 1;
+            } else {
+                s.swap(next[b], next[r]);
+                next[r] += 1;
+            }
+        }
+    }
+
+    if byte + 1 < K::BYTES {
+        let mut start = lo;
+        for b in 0..256 {
+            if end[b] - start > 1 {
+                sort_range(s, f, start, end[b], byte + 1, scratch);
+            }
+            start = end[b];
+        }
+    }
+}
+
+/// Sort the range `lo..hi` by every byte from `byte` onwards, least significant byte first.
+/// The range is copied into the scratch buffers, sorted there, and copied back.
+fn lsd_sort<S, K>(
+    s: &mut S,
+    f: &impl Fn(&S::Item) -> K,
+    lo: usize,
+    hi: usize,
+    byte: usize,
+    scratch: &mut Scratch<S::Item>,
+) where
+    S: RadixSortable + ?Sized,
+    S::Item: Copy,
+    K: RadixKey,
+{
+    let Scratch { a: src, b: dst } = scratch;
+    src.clear();
+    src.extend((lo..hi).map(|i| *s.radix_get(i)));
+    dst.clear();
+    dst.extend_from_slice(src);
+
+    for d in (byte..K::BYTES).rev() {
+        let mut counts = [0_usize; 256];
+        for item in src.iter() {
+            counts[f(item).radix(d) as usize] += 1;
+        }
+
+        // Every key agrees on this byte, so this pass would not move anything.
+        if counts.contains(&src.len()) {
+            continue;
+        }
+
+        let mut offset = 0;
+        for count in counts.iter_mut() {
+            let c = *count;
+            *count = offset;
+            offset += c;
+        }
+
+        for item in src.iter() {
+            let r = f(item).radix(d) as usize;
+            dst[counts[r]] = *item;
+            counts[r] += 1;
+        }
+
+        std::mem::swap(src, dst);
+    }
+
+    for (i, item) in (lo..hi).zip(src.iter()) {
+        *s.radix_get_mut(i) = *item;
+    }
+}
+
+/// Sort the range `lo..hi` by comparing whole keys.
+fn insertion_sort<S, K>(s: &mut S, f: &impl Fn(&S::Item) -> K, lo: usize, hi: usize)
+where
+    S: RadixSortable + ?Sized,
+    K: RadixKey,
+{
+    for i in lo + 1..hi {
+        let mut j = i;
+        while j > lo && f(s.radix_get(j - 1)) > f(s.radix_get(j)) {
+            s.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+impl<T> RadixSortable for [T] {
+    type Item = T;
+
+    fn radix_len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn radix_get(&self, i: usize) -> &T {
+        &self[i]
+    }
+
+    fn radix_get_mut(&mut self, i: usize) -> &mut T {
+        &mut self[i]
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        <[T]>::swap(self, i, j);
+    }
+}
+
+impl<'a, 'b, T> RadixSortable for Pair<'a, 'b, T> {
+    type Item = T;
+
+    fn radix_len(&self) -> usize {
+        Pair::len(self)
+    }
+
+    fn radix_get(&self, i: usize) -> &T {
+        Pair::get(self, i)
+    }
+
+    fn radix_get_mut(&mut self, i: usize) -> &mut T {
+        Pair::get_mut(self, i)
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        Pair::swap(self, i, j);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RadixKey, RadixSortable};
+    use crate::sort::Pair;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_radix() {
+        assert_eq!(0x0102_u16.radix(0), 0x01);
+        assert_eq!(0x0102_u16.radix(1), 0x02);
+        assert_eq!(0x01020304_u32.radix(0), 0x01);
+        assert_eq!(0x01020304_u32.radix(3), 0x04);
+        assert_eq!(u64::MAX.radix(7), 0xff);
+        assert_eq!((1_u128 << 120).radix(0), 0x01);
+        assert_eq!(0x7f_u8.radix(0), 0x7f);
+    }
+
+    #[test]
+    fn test_sort_pair() {
+        let mut a = [4_u32, 3, 2, 8];
+        let mut b = [1_u32, 7, 6, 5];
+
+        {
+            let mut pair = Pair::new(&mut a, &mut b);
+            pair.radix_sort();
+        }
+
+        assert_eq!(a, [1, 2, 3, 4]);
+        assert_eq!(b, [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_sort_by_key() {
+        let mut v: Vec<(u16, &str)> = (0..1000_u16)
+            .rev()
+            .map(|i| (i.wrapping_mul(7919), "x"))
+            .collect();
+        let mut expected = v.clone();
+        expected.sort();
+
+        v.radix_sort_by_key(|x| x.0);
+
+        assert_eq!(v, expected);
+    }
+
+    proptest! {
+        #[test]
+        fn test_sort_u64(mut original: Vec<u64>) {
+            let mut expected = original.clone();
+            expected.sort();
+            original.radix_sort();
+            assert_eq!(original, expected);
+        }
+
+        #[test]
+        fn test_sort_u8(mut original: Vec<u8>) {
+            let mut expected = original.clone();
+            expected.sort();
+            original.radix_sort();
+            assert_eq!(original, expected);
+        }
+
+        #[test]
+        fn test_sort_u128_pair(mut a: Vec<u128>, mut b: Vec<u128>) {
+            let mut expected: Vec<u128> = a.iter().chain(b.iter()).copied().collect();
+            expected.sort();
+            {
+                let mut pair = Pair::new(&mut a, &mut b);
+                pair.radix_sort();
+            }
+            assert_eq!(a.iter().chain(b.iter()).copied().collect::<Vec<_>>(), expected);
+        }
+    }
+}
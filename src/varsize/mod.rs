@@ -0,0 +1,7 @@
+mod str_vec;
+mod var_block;
+mod var_vec;
+
+pub use str_vec::*;
+pub use var_block::*;
+pub use var_vec::*;
@@ -0,0 +1,101 @@
+use std::ops::RangeBounds;
+
+use super::VarVec;
+
+/// A growable vector of UTF-8 strings, stored as bytes in `VarBlock`s of N strings each.
+///
+/// Compared to a `Vec<String>`, each string costs eight bytes of overhead instead of a separate allocation.
+#[derive(Default)]
+pub struct StrVec<const N: usize> {
+    bytes: VarVec<u8, N>,
+}
+
+impl<const N: usize> StrVec<N> {
+    /// Construct a new, empty StrVec.
+    pub fn new() -> Self {
+        StrVec {
+            bytes: VarVec::new(),
+        }
+    }
+
+    /// The number of strings.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// True if there are no strings.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The underlying byte strings.
+    pub fn as_bytes(&self) -> &VarVec<u8, N> {
+        &self.bytes
+    }
+
+    /// Append a string.
+    pub fn push(&mut self, s: &str) {
+        self.bytes.push(s.as_bytes());
+    }
+
+    /// Get a string, or `None` if the index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.bytes.get(index).map(to_str)
+    }
+
+    /// Replace a string. Panics if the index is out of bounds.
+    pub fn replace(&mut self, index: usize, s: &str) {
+        self.bytes.replace(index, s.as_bytes());
+    }
+
+    /// Iterate over the strings in the given range.
+    pub fn iter_range(&self, range: impl RangeBounds<usize>) -> impl Iterator<Item = &str> + '_ {
+        self.bytes.iter_range(range).map(to_str)
+    }
+
+    /// Iterate over every string.
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        self.bytes.iter().map(to_str)
+    }
+}
+
+/// Every element of a StrVec was pushed as a `&str`, so this only fails if there is a bug.
+fn to_str(bytes: &[u8]) -> &str {
+    std::str::from_utf8(bytes).expect("StrVec elements should be valid UTF-8")
+}
+
+impl<'a, const N: usize> FromIterator<&'a str> for StrVec<N> {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut result = StrVec::new();
+        for s in iter {
+            result.push(s);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StrVec;
+
+    #[test]
+    fn test_strings() {
+        let mut v: StrVec<4> = ["zero", "one", "two", "three", "four", "five"]
+            .into_iter()
+            .collect();
+
+        assert_eq!(v.len(), 6);
+        assert_eq!(v.get(3), Some("three"));
+        assert_eq!(v.get(6), None);
+
+        v.replace(1, "uno");
+        v.replace(5, "cinq, with a longer name");
+        assert_eq!(
+            v.iter_range(1..).collect::<Vec<_>>(),
+            vec!["uno", "two", "three", "four", "cinq, with a longer name"]
+        );
+
+        v.push("héllo");
+        assert_eq!(v.iter().last(), Some("héllo"));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::block::{AlignedBlock, AlignedBlockFromIterator, BlockFetch, BlockStore, IndexedBlock};
+
+/// An AlignedBlock of N variable-length elements, each a slice of `T`.
+///
+/// The elements share a single data arena, and each element is a span (start and length) into the arena.
+/// Replacing an element with a shorter or equal-length slice overwrites it in place. Replacing it with a longer slice
+/// appends to the arena and leaves the old span behind as garbage. Once the garbage outweighs the live data,
+/// the arena is compacted.
+///
+/// As a block, each item is a `Vec<T>`. Use `slice` to borrow an element without copying it.
+#[derive(Clone)]
+pub struct VarBlock<T, const N: usize> {
+    position: usize,
+    spans: Vec<(u32, u32)>,
+    data: Vec<T>,
+    garbage: usize,
+}
+
+impl<T, const N: usize> VarBlock<T, N> {
+    /// An empty block at the given position, to be filled by `push`.
+    pub(crate) fn new(position: usize) -> Self {
+        assert!(position % N == 0);
+        VarBlock {
+            position,
+            spans: Vec::with_capacity(N),
+            data: vec![],
+            garbage: 0,
+        }
+    }
+
+    /// The number of elements that have been pushed so far. This is N for any block outside of a `VarVec`.
+    pub(crate) fn filled(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Get an element of this block.
+    pub fn slice(&self, index: usize) -> &[T] {
+        let (start, len) = self.spans[self.index_of(index)];
+        &self.data[start as usize..(start + len) as usize]
+    }
+
+    /// The number of `T`s in the data arena, including garbage.
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The number of `T`s in the data arena that no element refers to.
+    pub fn garbage(&self) -> usize {
+        self.garbage
+    }
+
+    fn index_of(&self, index: usize) -> usize {
+        assert!(index >= self.position);
+        let index = index - self.position;
+        assert!(index < self.spans.len());
+        index
+    }
+
+    /// Append to the data arena, returning the span of the appended elements.
+    fn append(&mut self, items: &[T]) -> (u32, u32)
+    where
+        T: Clone,
+    {
+        let start = self.data.len();
+        self.data.extend_from_slice(items);
+        assert!(
+            u32::try_from(self.data.len()).is_ok(),
+            "block arena should hold fewer than 2^32 elements"
+        );
+        (start as u32, items.len() as u32)
+    }
+}
+
+impl<T, const N: usize> VarBlock<T, N>
+where
+    T: Clone,
+{
+    /// Append an element to a block that is not yet full.
+    pub(crate) fn push(&mut self, items: &[T]) {
+        assert!(self.spans.len() < N, "block is full");
+        let span = self.append(items);
+        self.spans.push(span);
+    }
+
+    /// Replace an element of this block.
+    pub fn replace(&mut self, index: usize, items: &[T]) {
+        let k = self.index_of(index);
+        let (start, len) = self.spans[k];
+        if items.len() <= len as usize {
+            let start = start as usize;
+            self.data[start..start + items.len()].clone_from_slice(items);
+            self.spans[k].1 = items.len() as u32;
+            self.garbage += len as usize - items.len();
+        } else {
+            self.spans[k] = self.append(items);
+            self.garbage += len as usize;
+        }
+
+        if self.garbage > self.data.len() / 2 {
+            self.compact();
+        }
+    }
+
+    /// Rewrite the data arena so that it contains only live elements, in order.
+    pub fn compact(&mut self) {
+        let mut data = Vec::with_capacity(self.data.len() - self.garbage);
+        for span in self.spans.iter_mut() {
+            let (start, len) = (span.0 as usize, span.1 as usize);
+            span.0 = data.len() as u32;
+            data.extend_from_slice(&self.data[start..start + len]);
+        }
+        self.data = data;
+        self.garbage = 0;
+    }
+}
+
+impl<T, const N: usize> IndexedBlock for VarBlock<T, N> {
+    type Index = usize;
+    type Item = Vec<T>;
+}
+
+impl<T, const N: usize> AlignedBlock for VarBlock<T, N> {
+    fn alignment() -> Self::Index {
+        N
+    }
+
+    fn position(&self) -> Self::Index {
+        self.position
+    }
+}
+
+impl<T, const N: usize> BlockFetch for VarBlock<T, N>
+where
+    T: Clone,
+{
+    fn fetch(&self, index: Self::Index) -> Self::Item {
+        self.slice(index).to_vec()
+    }
+}
+
+impl<T, const N: usize> BlockStore for VarBlock<T, N>
+where
+    T: Clone,
+{
+    fn store(&mut self, index: Self::Index, item: Self::Item) {
+        self.replace(index, &item);
+    }
+}
+
+impl<T, const N: usize> AlignedBlockFromIterator for VarBlock<T, N>
+where
+    T: Clone,
+{
+    fn from_iterator<I>(position: Self::Index, iter: &mut I) -> Self
+    where
+        I: Iterator<Item = Self::Item>,
+    {
+        let mut result = VarBlock::new(position);
+        for _ in 0..N {
+            let item = iter
+                .next()
+                .expect("iterator to contain at least as many elements as Self::alignment()");
+            result.push(&item);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VarBlock;
+    use crate::block::{AlignedBlockFromIterator, BlockFetch, BlockStore, DefaultValue, SparseVec};
+
+    #[test]
+    fn test_replace() {
+        let mut b: VarBlock<u8, 4> = VarBlock::from_iterator(
+            4,
+            &mut [b"ab".to_vec(), b"cde".to_vec(), vec![], b"f".to_vec()].into_iter(),
+        );
+        assert_eq!(b.slice(5), b"cde");
+        assert_eq!(b.slice(6), b"");
+        assert_eq!(b.data_len(), 6);
+
+        b.replace(5, b"x");
+        assert_eq!(b.slice(5), b"x");
+        assert_eq!((b.data_len(), b.garbage()), (6, 2));
+
+        b.replace(4, b"yyyy");
+        assert_eq!(b.slice(4), b"yyyy");
+        assert_eq!(b.fetch(7), b"f".to_vec());
+        assert_eq!((b.data_len(), b.garbage()), (10, 4));
+    }
+
+    #[test]
+    fn test_compaction() {
+        let mut b: VarBlock<u32, 2> =
+            VarBlock::from_iterator(0, &mut [vec![1, 2], vec![3]].into_iter());
+
+        for i in 0..100 {
+            b.store(1, (0..i % 7).collect());
+            assert!(b.garbage() <= b.data_len() / 2);
+        }
+        assert_eq!(b.slice(0), &[1, 2]);
+        assert_eq!(b.slice(1), &[0]);
+
+        b.compact();
+        assert_eq!((b.data_len(), b.garbage()), (3, 0));
+        assert_eq!(b.slice(0), &[1, 2]);
+        assert_eq!(b.slice(1), &[0]);
+    }
+
+    #[test]
+    fn test_sparse_vec() {
+        let mut v: SparseVec<VarBlock<u8, 16>, DefaultValue> = SparseVec::default();
+        v.store(1_000, b"hello".to_vec());
+
+        assert_eq!(v.fetch(1_000), b"hello".to_vec());
+        assert_eq!(v.fetch(1_001), vec![]);
+        assert_eq!(v.fetch(5), vec![]);
+    }
+}
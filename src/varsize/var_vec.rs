@@ -0,0 +1,185 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::block::{AlignedBlock, DenseVec};
+
+use super::VarBlock;
+
+/// A growable vector of variable-length elements, each a slice of `T`.
+///
+/// Elements are stored in `VarBlock`s of N elements each, densely packed like the blocks of a `DenseVec`.
+/// The elements pushed since the last full block are kept in a partially-filled tail block.
+pub struct VarVec<T, const N: usize> {
+    blocks: Vec<VarBlock<T, N>>,
+    tail: VarBlock<T, N>,
+}
+
+impl<T, const N: usize> Default for VarVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> VarVec<T, N> {
+    /// Construct a new, empty VarVec.
+    pub fn new() -> Self {
+        VarVec {
+            blocks: vec![],
+            tail: VarBlock::new(0),
+        }
+    }
+
+    /// Construct a VarVec from full blocks.
+    pub fn new_from(blocks: DenseVec<VarBlock<T, N>>) -> Self {
+        let blocks = blocks.into_vec();
+        let position = blocks.len() * N;
+        VarVec {
+            blocks,
+            tail: VarBlock::new(position),
+        }
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.tail.position() + self.tail.filled()
+    }
+
+    /// True if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The full blocks of this VarVec. Elements in the tail block are not included.
+    pub fn blocks(&self) -> &[VarBlock<T, N>] {
+        &self.blocks
+    }
+
+    fn block(&self, index: usize) -> &VarBlock<T, N> {
+        match self.blocks.get(index / N) {
+            Some(block) => block,
+            None => &self.tail,
+        }
+    }
+
+    /// Get an element, or `None` if the index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&[T]> {
+        if index < self.len() {
+            Some(self.block(index).slice(index))
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over the elements in the given range.
+    pub fn iter_range(&self, range: impl RangeBounds<usize>) -> impl Iterator<Item = &[T]> + '_ {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(end <= self.len(), "range end out of bounds");
+        (start..end).map(move |i| self.block(i).slice(i))
+    }
+
+    /// Unwrap into a DenseVec of the full blocks, and the elements of the partially-filled tail block.
+    pub fn into_dense_vec(self) -> (DenseVec<VarBlock<T, N>>, Vec<Vec<T>>)
+    where
+        T: Clone,
+    {
+        let tail = (self.tail.position()..self.len())
+            .map(|i| self.tail.slice(i).to_vec())
+            .collect();
+        (DenseVec::new_from(self.blocks), tail)
+    }
+
+    /// Iterate over every element.
+    pub fn iter(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.iter_range(..)
+    }
+}
+
+impl<T, const N: usize> VarVec<T, N>
+where
+    T: Clone,
+{
+    /// Append an element.
+    pub fn push(&mut self, items: &[T]) {
+        self.tail.push(items);
+        if self.tail.filled() == N {
+            let position = self.tail.position() + N;
+            let full = std::mem::replace(&mut self.tail, VarBlock::new(position));
+            self.blocks.push(full);
+        }
+    }
+
+    /// Replace an element. Panics if the index is out of bounds.
+    pub fn replace(&mut self, index: usize, items: &[T]) {
+        assert!(index < self.len(), "index out of bounds");
+        match self.blocks.get_mut(index / N) {
+            Some(block) => block.replace(index, items),
+            None => self.tail.replace(index, items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VarVec;
+    use crate::block::BlockFetch;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_push_get() {
+        let mut v: VarVec<u8, 2> = VarVec::new();
+        for s in ["a", "bb", "", "dddd", "e"] {
+            v.push(s.as_bytes());
+        }
+
+        assert_eq!(v.len(), 5);
+        assert_eq!(v.blocks().len(), 2);
+        assert_eq!(v.get(1), Some(&b"bb"[..]));
+        assert_eq!(v.get(2), Some(&b""[..]));
+        assert_eq!(v.get(4), Some(&b"e"[..]));
+        assert_eq!(v.get(5), None);
+
+        assert_eq!(
+            v.iter_range(1..4).collect::<Vec<_>>(),
+            vec![&b"bb"[..], b"", b"dddd"]
+        );
+
+        let (blocks, tail) = v.into_dense_vec();
+        assert_eq!(blocks.fetch(3), b"dddd".to_vec());
+        assert_eq!(tail, vec![b"e".to_vec()]);
+        let v = VarVec::new_from(blocks);
+        assert_eq!(v.len(), 4);
+        assert_eq!(v.get(0), Some(&b"a"[..]));
+    }
+
+    proptest! {
+        #[test]
+        fn test_against_vec(
+            items in proptest::collection::vec(proptest::collection::vec(any::<u16>(), 0..8), 0..50),
+            replacements in proptest::collection::vec((any::<usize>(), proptest::collection::vec(any::<u16>(), 0..8)), 0..50),
+        ) {
+            let mut expected = items.clone();
+            let mut v: VarVec<u16, 4> = VarVec::new();
+            for item in items.iter() {
+                v.push(item);
+            }
+
+            if !expected.is_empty() {
+                for (i, item) in replacements {
+                    let i = i % expected.len();
+                    expected[i] = item.clone();
+                    v.replace(i, &item);
+                }
+            }
+
+            assert_eq!(v.iter().map(|x| x.to_vec()).collect::<Vec<_>>(), expected);
+        }
+    }
+}